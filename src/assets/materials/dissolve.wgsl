@@ -0,0 +1,23 @@
+// Burns the sprite away texel by texel.
+// params: amount (0 whole, 1 gone), edge_color, edge_width (fraction of amount that glows)
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    var color = sprite_color(in);
+    let amount = material.params[0].x;
+    let edge_color = material.params[1];
+    let edge_width = material.params[2].x;
+
+    let noise = hash(floor(in.tex_coords * sprite_texel_size()));
+    if (color.a < .01 || noise < amount) {
+        discard;
+    }
+    if (amount > 0.0 && noise < amount + edge_width) {
+        color = vec4<f32>(edge_color.rgb, color.a);
+    }
+    return sprite_output(color, in);
+}
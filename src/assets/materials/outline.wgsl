@@ -0,0 +1,27 @@
+// Draws a solid line around the sprite's silhouette, on the empty texels next to it.
+// Sheets need a texel of padding around each frame or the line is cut at the frame's edge.
+// params: color, width (texels)
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let color = sprite_color(in);
+    let outline_color = material.params[0];
+    let width = max(material.params[1].x, 1.0);
+    let texel = 1.0 / sprite_texel_size();
+
+    // sampled before branching, derivatives need every fragment of the quad
+    var coverage = 0.0;
+    for (var i = 0; i < 4; i++) {
+        let direction = vec2<f32>(f32(i % 2) * 2.0 - 1.0, 0.0);
+        let offset = select(direction.yx, direction, i < 2) * texel * width;
+        coverage = max(coverage, textureSample(t_diffuse, s_diffuse, in.tex_coords + offset).a);
+    }
+
+    if (color.a >= .01) {
+        return sprite_output(color, in);
+    }
+    if (coverage < .01) {
+        discard;
+    }
+    return sprite_output(vec4<f32>(outline_color.rgb, outline_color.a * in.tint.a), in);
+}
@@ -0,0 +1,9 @@
+// Custom post effect, see src/graphics_2d/shaders/post/common.wgsl for what is in scope.
+// params[0].x is how fast the effect pulses, 0 holds it steady.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(in.uv);
+    let gray = vec3<f32>(dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114)));
+    let pulse = 0.75 + 0.25 * cos(post.time * post.params[0].x * 6.2831853);
+    return vec4<f32>(mix(color.rgb, gray, clamp(post.strength * pulse, 0.0, 1.0)), color.a);
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::audio::{
+    clip::{decode_in_background, load_clip, open_stream, AudioClip},
+    mixer::{Bus, Mixer, PlayOptions, SoundId, Source},
+    output::{AudioOutput, NullOutput},
+};
+
+const NULL_SAMPLE_RATE: u32 = 44100;
+
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    output: Box<dyn AudioOutput>,
+    clips: HashMap<String, Arc<AudioClip>>,
+    music: Option<SoundId>,
+}
+
+impl Audio {
+    /// The default sound card when built with the `audio` feature and one is available,
+    /// the null output otherwise.
+    pub fn new() -> Self {
+        let mixer = Arc::new(Mutex::new(Mixer::new(NULL_SAMPLE_RATE)));
+
+        #[cfg(feature = "audio")]
+        match crate::audio::output::CpalOutput::new(mixer.clone()) {
+            Ok(output) => return Self::with_output(mixer, Box::new(output)),
+            Err(e) => println!("Audio device unavailable, using the null output: {}", e),
+        }
+
+        Self::with_output(mixer, Box::new(NullOutput::new(NULL_SAMPLE_RATE)))
+    }
+
+    pub fn with_output(mixer: Arc<Mutex<Mixer>>, output: Box<dyn AudioOutput>) -> Self {
+        Self {
+            mixer,
+            output,
+            clips: HashMap::new(),
+            music: None,
+        }
+    }
+
+    fn with_mixer<T>(&self, f: impl FnOnce(&mut Mixer) -> T) -> Option<T> {
+        self.mixer.lock().ok().map(|mut mixer| f(&mut mixer))
+    }
+
+    /// Decodes and caches an effect under its asset id.
+    pub fn load(&mut self, id: &str, path: &str) -> anyhow::Result<Arc<AudioClip>> {
+        if let Some(clip) = self.clips.get(id) {
+            return Ok(clip.clone());
+        }
+        let clip = Arc::new(load_clip(path)?);
+        self.clips.insert(id.to_string(), clip.clone());
+        Ok(clip)
+    }
+
+    pub fn play(&mut self, id: &str, path: &str, options: PlayOptions) -> Option<SoundId> {
+        match self.load(id, path) {
+            Ok(clip) => self.with_mixer(|mixer| mixer.play(Source::Clip(clip), options)),
+            Err(e) => {
+                println!("Could not play {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Streams a track from disk on the music bus, crossfading from the current one.
+    /// Decoding runs on its own thread, the mixer only picks up the decoded chunks.
+    pub fn play_music(
+        &mut self,
+        path: &str,
+        options: PlayOptions,
+        crossfade: f32,
+    ) -> Option<SoundId> {
+        let stream = match open_stream(path) {
+            Ok(stream) => stream,
+            Err(e) => {
+                println!("Could not play music {}: {}", path, e);
+                return None;
+            }
+        };
+        self.stop_music(crossfade);
+
+        let options = PlayOptions {
+            bus: Bus::Music,
+            fade_in: options.fade_in.max(crossfade),
+            ..options
+        };
+        let stream = decode_in_background(stream, options.looping);
+        self.music = self.with_mixer(|mixer| mixer.play(Source::Stream(stream), options));
+        self.music
+    }
+
+    pub fn stop_music(&mut self, fade: f32) {
+        if let Some(music) = self.music.take() {
+            self.stop(music, fade);
+        }
+    }
+
+    pub fn stop(&mut self, id: SoundId, fade: f32) {
+        self.with_mixer(|mixer| mixer.stop(id, fade));
+    }
+
+    pub fn is_playing(&self, id: SoundId) -> bool {
+        self.with_mixer(|mixer| mixer.is_playing(id))
+            .unwrap_or(false)
+    }
+
+    pub fn set_position(&mut self, id: SoundId, position: [f32; 2]) {
+        self.with_mixer(|mixer| mixer.set_position(id, position));
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.with_mixer(|mixer| mixer.set_bus_volume(bus, volume));
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.with_mixer(|mixer| mixer.set_master_volume(volume));
+    }
+
+    pub fn set_rolloff(&mut self, min_distance: f32, max_distance: f32) {
+        self.with_mixer(|mixer| {
+            mixer.rolloff.min_distance = min_distance;
+            mixer.rolloff.max_distance = max_distance.max(min_distance);
+        });
+    }
+
+    /// Positional sounds are heard from here, the engine keeps it on the camera.
+    pub fn set_listener(&mut self, position: [f32; 2]) {
+        self.with_mixer(|mixer| mixer.set_listener(position));
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.output.pump(&self.mixer, dt);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    sync::mpsc::{sync_channel, Receiver},
+};
+
+use anyhow::{anyhow, Context};
+use hound::{SampleFormat, WavReader};
+use lewton::inside_ogg::OggStreamReader;
+
+// samples pulled from a stream at a time, per channel
+const STREAM_CHUNK_FRAMES: usize = 4096;
+// chunks decoded ahead of playback, about a second at 44.1kHz
+const STREAM_CHUNKS_AHEAD: usize = 10;
+
+/// A fully decoded sound, interleaved f32 samples.
+#[derive(Debug)]
+pub struct AudioClip {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+/// Decodes a whole file, used for short effects that get replayed a lot.
+pub fn load_clip(path: &str) -> anyhow::Result<AudioClip> {
+    let mut stream = open_stream(path)?;
+    let mut samples = Vec::new();
+    while let Some(chunk) = stream.next_chunk() {
+        samples.extend(chunk);
+    }
+    Ok(AudioClip {
+        sample_rate: stream.sample_rate(),
+        channels: stream.channels(),
+        samples,
+    })
+}
+
+/// Decodes a file piece by piece so long music tracks never sit in memory whole.
+pub trait SampleStream: Send {
+    fn sample_rate(&self) -> u32;
+    fn channels(&self) -> u16;
+    /// Interleaved samples, `None` at the end of the file.
+    fn next_chunk(&mut self) -> Option<Vec<f32>>;
+    /// Back to the first sample, for looping.
+    fn rewind(&mut self) -> anyhow::Result<()>;
+}
+
+/// A stream being decoded on its own thread, so the audio callback never waits on the disk.
+pub struct DecodedStream {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub chunks: Receiver<Vec<f32>>, // disconnects at the end of the stream
+}
+
+/// Starts decoding `stream` ahead of playback. Loops restart on the decoder thread too.
+/// The thread ends with the stream or once the `DecodedStream` is dropped.
+pub fn decode_in_background(mut stream: Box<dyn SampleStream>, looping: bool) -> DecodedStream {
+    let (sender, chunks) = sync_channel(STREAM_CHUNKS_AHEAD);
+    let decoded = DecodedStream {
+        sample_rate: stream.sample_rate(),
+        channels: stream.channels(),
+        chunks,
+    };
+    std::thread::spawn(move || loop {
+        let chunk = match stream.next_chunk() {
+            Some(chunk) => chunk,
+            None if looping && stream.rewind().is_ok() => match stream.next_chunk() {
+                Some(chunk) => chunk,
+                None => break,
+            },
+            None => break,
+        };
+        if sender.send(chunk).is_err() {
+            break;
+        }
+    });
+    decoded
+}
+
+pub fn open_stream(path: &str) -> anyhow::Result<Box<dyn SampleStream>> {
+    let extension = path.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "wav" => Ok(Box::new(WavStream::open(path)?)),
+        "ogg" => Ok(Box::new(OggStream::open(path)?)),
+        other => Err(anyhow!("Unsupported audio format '{}' for {}", other, path)),
+    }
+}
+
+struct WavStream {
+    reader: WavReader<BufReader<File>>,
+}
+
+impl WavStream {
+    fn open(path: &str) -> anyhow::Result<Self> {
+        let reader =
+            WavReader::open(path).with_context(|| format!("Could not open sound {}", path))?;
+        Ok(Self { reader })
+    }
+}
+
+impl SampleStream for WavStream {
+    fn sample_rate(&self) -> u32 {
+        self.reader.spec().sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.reader.spec().channels
+    }
+
+    fn next_chunk(&mut self) -> Option<Vec<f32>> {
+        let spec = self.reader.spec();
+        let count = STREAM_CHUNK_FRAMES * spec.channels as usize;
+        let chunk: Vec<f32> = match spec.sample_format {
+            SampleFormat::Float => self
+                .reader
+                .samples::<f32>()
+                .take(count)
+                .filter_map(Result::ok)
+                .collect(),
+            SampleFormat::Int => {
+                let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                self.reader
+                    .samples::<i32>()
+                    .take(count)
+                    .filter_map(Result::ok)
+                    .map(|sample| sample as f32 * scale)
+                    .collect()
+            }
+        };
+        (!chunk.is_empty()).then_some(chunk)
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        self.reader.seek(0)?;
+        Ok(())
+    }
+}
+
+struct OggStream {
+    path: String,
+    reader: OggStreamReader<File>,
+}
+
+impl OggStream {
+    fn open(path: &str) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("Could not open sound {}", path))?;
+        let reader =
+            OggStreamReader::new(file).with_context(|| format!("Invalid ogg file {}", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            reader,
+        })
+    }
+}
+
+impl SampleStream for OggStream {
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.reader.ident_hdr.audio_channels as u16
+    }
+
+    fn next_chunk(&mut self) -> Option<Vec<f32>> {
+        // vorbis packets can decode to nothing, keep going until samples or the end
+        loop {
+            match self.reader.read_dec_packet_itl() {
+                Ok(Some(packet)) if packet.is_empty() => continue,
+                Ok(Some(packet)) => {
+                    return Some(
+                        packet
+                            .into_iter()
+                            .map(|sample| sample as f32 / 32768.0)
+                            .collect(),
+                    )
+                }
+                Ok(None) => return None,
+                Err(e) => {
+                    println!("Error decoding {}: {}", self.path, e);
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        *self = Self::open(&self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // counts up from 0, `frames` mono samples in chunks of `chunk`
+    struct CountingStream {
+        frames: usize,
+        chunk: usize,
+        position: usize,
+    }
+
+    impl SampleStream for CountingStream {
+        fn sample_rate(&self) -> u32 {
+            1000
+        }
+
+        fn channels(&self) -> u16 {
+            1
+        }
+
+        fn next_chunk(&mut self) -> Option<Vec<f32>> {
+            let end = (self.position + self.chunk).min(self.frames);
+            let chunk: Vec<f32> = (self.position..end).map(|sample| sample as f32).collect();
+            self.position = end;
+            (!chunk.is_empty()).then_some(chunk)
+        }
+
+        fn rewind(&mut self) -> anyhow::Result<()> {
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    fn counting(frames: usize) -> Box<dyn SampleStream> {
+        Box::new(CountingStream {
+            frames,
+            chunk: 2,
+            position: 0,
+        })
+    }
+
+    #[test]
+    fn background_decoding_delivers_every_chunk_then_disconnects() {
+        let decoded = decode_in_background(counting(5), false);
+        assert_eq!((decoded.sample_rate, decoded.channels), (1000, 1));
+        let samples: Vec<f32> = decoded.chunks.iter().flatten().collect();
+        assert_eq!(samples, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn looping_streams_restart_on_the_decoder_thread() {
+        let decoded = decode_in_background(counting(3), true);
+        let samples: Vec<f32> = decoded.chunks.iter().flatten().take(8).collect();
+        assert_eq!(samples, vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0, 1.0]);
+    }
+}
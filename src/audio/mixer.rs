@@ -0,0 +1,569 @@
+use std::{
+    collections::HashMap,
+    f32::consts::FRAC_PI_4,
+    sync::{mpsc::TryRecvError, Arc},
+};
+
+use crate::audio::clip::{AudioClip, DecodedStream};
+
+pub type SoundId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl From<&str> for Bus {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "music" => Bus::Music,
+            "ui" => Bus::Ui,
+            _ => Bus::Sfx,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayOptions {
+    pub volume: f32,
+    pub pitch: f32, // playback speed, 2.0 is an octave up
+    pub pan: f32,   // -1 left .. 1 right, added to the positional pan
+    pub bus: Bus,
+    pub position: Option<[f32; 2]>, // world units, attenuated against the listener
+    pub looping: bool,
+    pub fade_in: f32, // seconds
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pitch: 1.0,
+            pan: 0.0,
+            bus: Bus::Sfx,
+            position: None,
+            looping: false,
+            fade_in: 0.0,
+        }
+    }
+}
+
+impl PlayOptions {
+    /// `{ volume, pitch, pan, bus = "sfx", x, y, loop, fade_in }`, all optional.
+    pub fn from_lua_table(table: &mlua::Table) -> Self {
+        let defaults = Self::default();
+        let bus: String = table.get("bus").unwrap_or("sfx".to_string());
+        let position = match (table.get::<f32>("x"), table.get::<f32>("y")) {
+            (Ok(x), Ok(y)) => Some([x, y]),
+            _ => None,
+        };
+        Self {
+            volume: table.get("volume").unwrap_or(defaults.volume),
+            pitch: table.get("pitch").unwrap_or(defaults.pitch),
+            pan: table.get("pan").unwrap_or(defaults.pan),
+            bus: Bus::from(bus.as_str()),
+            position,
+            looping: table.get("loop").unwrap_or(defaults.looping),
+            fade_in: table.get("fade_in").unwrap_or(defaults.fade_in),
+        }
+    }
+}
+
+/// Positional sounds play at full volume inside `min_distance` and go silent at `max_distance`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rolloff {
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+pub enum Source {
+    Clip(Arc<AudioClip>),
+    Stream(DecodedStream),
+}
+
+enum Refill {
+    Ready,
+    Starved, // the decoder is behind, play silence until it catches up
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    from: f32,
+    to: f32,
+    duration: f32,
+    elapsed: f32,
+    stop_when_done: bool,
+}
+
+struct Voice {
+    id: SoundId,
+    source: Source,
+    channels: usize,
+    step: f64,        // source frames per output frame
+    buffer: Vec<f32>, // current chunk for streams, clips read their samples in place
+    cursor: f64,      // in frames of `data()`
+    options: PlayOptions,
+    fade: Option<Fade>,
+    fade_gain: f32,
+    finished: bool,
+}
+
+impl Voice {
+    fn data(&self) -> &[f32] {
+        match &self.source {
+            Source::Clip(clip) => &clip.samples,
+            Source::Stream(_) => &self.buffer,
+        }
+    }
+
+    fn frames_in_buffer(&self) -> usize {
+        self.data().len() / self.channels
+    }
+
+    // next chunk for streams, the start again for looping clips. Streams loop on their
+    // decoder thread, this runs on the audio callback and must not touch the disk
+    fn refill(&mut self) -> Refill {
+        let played = self.frames_in_buffer() as f64;
+        match &mut self.source {
+            Source::Clip(_) if !self.options.looping => return Refill::Ended,
+            Source::Clip(_) => {}
+            Source::Stream(stream) => match stream.chunks.try_recv() {
+                Ok(chunk) => self.buffer = chunk,
+                Err(TryRecvError::Empty) => return Refill::Starved,
+                Err(TryRecvError::Disconnected) => return Refill::Ended,
+            },
+        }
+        self.cursor -= played;
+        Refill::Ready
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        let channel = channel.min(self.channels - 1);
+        self.data()
+            .get(frame * self.channels + channel)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Linear interpolation between neighbouring frames, mono is copied to both sides.
+    fn next_frame(&mut self) -> Option<[f32; 2]> {
+        while self.cursor as usize >= self.frames_in_buffer() {
+            match self.refill() {
+                Refill::Ready if self.frames_in_buffer() > 0 => {}
+                Refill::Starved => return Some([0.0, 0.0]),
+                Refill::Ready | Refill::Ended => return None,
+            }
+        }
+        let index = self.cursor as usize;
+        let t = (self.cursor - index as f64) as f32;
+        let next = (index + 1).min(self.frames_in_buffer() - 1);
+        let frame = [
+            self.sample(index, 0) * (1.0 - t) + self.sample(next, 0) * t,
+            self.sample(index, 1) * (1.0 - t) + self.sample(next, 1) * t,
+        ];
+        self.cursor += self.step;
+        Some(frame)
+    }
+
+    fn tick_fade(&mut self, dt: f32) {
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed = (fade.elapsed + dt).min(fade.duration);
+            let t = if fade.duration > 0.0 {
+                fade.elapsed / fade.duration
+            } else {
+                1.0
+            };
+            self.fade_gain = fade.from + (fade.to - fade.from) * t;
+            if t >= 1.0 {
+                if fade.stop_when_done {
+                    self.finished = true;
+                }
+                self.fade = None;
+            }
+        }
+    }
+}
+
+/// Software mixer shared between the engine and the output device.
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    next_id: SoundId,
+    bus_volumes: HashMap<Bus, f32>,
+    master_volume: f32,
+    listener: [f32; 2],
+    pub rolloff: Rolloff,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: Vec::new(),
+            next_id: 1,
+            bus_volumes: HashMap::new(),
+            master_volume: 1.0,
+            listener: [0.0, 0.0],
+            rolloff: Rolloff {
+                min_distance: 5.0,
+                max_distance: 30.0,
+            },
+        }
+    }
+
+    pub fn play(&mut self, source: Source, options: PlayOptions) -> SoundId {
+        let (channels, sample_rate) = match &source {
+            Source::Clip(clip) => (clip.channels, clip.sample_rate),
+            Source::Stream(stream) => (stream.channels, stream.sample_rate),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let fade = (options.fade_in > 0.0).then_some(Fade {
+            from: 0.0,
+            to: 1.0,
+            duration: options.fade_in,
+            elapsed: 0.0,
+            stop_when_done: false,
+        });
+        self.voices.push(Voice {
+            id,
+            source,
+            channels: channels.max(1) as usize,
+            step: sample_rate as f64 / self.sample_rate as f64 * options.pitch.max(0.01) as f64,
+            buffer: Vec::new(),
+            cursor: 0.0,
+            options,
+            fade,
+            fade_gain: if fade.is_some() { 0.0 } else { 1.0 },
+            finished: false,
+        });
+        id
+    }
+
+    /// Stops right away, or fades out over `fade` seconds first.
+    pub fn stop(&mut self, id: SoundId, fade: f32) {
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) {
+            if fade <= 0.0 {
+                voice.finished = true;
+            } else {
+                voice.fade = Some(Fade {
+                    from: voice.fade_gain,
+                    to: 0.0,
+                    duration: fade,
+                    elapsed: 0.0,
+                    stop_when_done: true,
+                });
+            }
+        }
+    }
+
+    pub fn is_playing(&self, id: SoundId) -> bool {
+        self.voices
+            .iter()
+            .any(|voice| voice.id == id && !voice.finished)
+    }
+
+    pub fn set_position(&mut self, id: SoundId, position: [f32; 2]) {
+        if let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) {
+            voice.options.position = Some(position);
+        }
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.bus_volumes.insert(bus, volume.max(0.0));
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.max(0.0);
+    }
+
+    pub fn set_listener(&mut self, position: [f32; 2]) {
+        self.listener = position;
+    }
+
+    // (gain, pan) from the distance and side relative to the listener
+    fn spatialize(&self, position: Option<[f32; 2]>) -> (f32, f32) {
+        let position = match position {
+            Some(position) => position,
+            None => return (1.0, 0.0),
+        };
+        let dx = position[0] - self.listener[0];
+        let dy = position[1] - self.listener[1];
+        let distance = (dx * dx + dy * dy).sqrt();
+        let Rolloff {
+            min_distance,
+            max_distance,
+        } = self.rolloff;
+        let gain = if distance <= min_distance {
+            1.0
+        } else {
+            (1.0 - (distance - min_distance) / (max_distance - min_distance).max(0.001))
+                .clamp(0.0, 1.0)
+        };
+        let pan = (dx / max_distance.max(0.001)).clamp(-1.0, 1.0);
+        (gain, pan)
+    }
+
+    /// Adds every playing voice into `out`, interleaved with `channels` per frame.
+    pub fn mix(&mut self, out: &mut [f32], channels: usize) {
+        out.iter_mut().for_each(|sample| *sample = 0.0);
+        let dt = 1.0 / self.sample_rate as f32;
+
+        let mut voices = std::mem::take(&mut self.voices);
+        for voice in voices.iter_mut().filter(|voice| !voice.finished) {
+            let (spatial_gain, spatial_pan) = self.spatialize(voice.options.position);
+            let bus_volume = self
+                .bus_volumes
+                .get(&voice.options.bus)
+                .copied()
+                .unwrap_or(1.0);
+            let gain = voice.options.volume * bus_volume * self.master_volume * spatial_gain;
+            // constant power so centered sounds are not louder than panned ones
+            let angle = ((voice.options.pan + spatial_pan).clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+            let (left, right) = (angle.cos(), angle.sin());
+
+            for frame in out.chunks_mut(channels) {
+                let sample = match voice.next_frame() {
+                    Some(sample) => sample,
+                    None => {
+                        voice.finished = true;
+                        break;
+                    }
+                };
+                voice.tick_fade(dt);
+                let volume = gain * voice.fade_gain;
+                if channels == 1 {
+                    frame[0] += (sample[0] + sample[1]) * 0.5 * volume;
+                } else {
+                    frame[0] += sample[0] * left * volume;
+                    frame[1] += sample[1] * right * volume;
+                }
+                if voice.finished {
+                    break;
+                }
+            }
+        }
+        voices.retain(|voice| !voice.finished);
+        self.voices = voices;
+
+        out.iter_mut()
+            .for_each(|sample| *sample = sample.clamp(-1.0, 1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        f32::consts::FRAC_1_SQRT_2,
+        sync::{mpsc::sync_channel, Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::audio::output::{AudioOutput, NullOutput};
+
+    const RATE: u32 = 1000;
+
+    // one second of a constant mono level, easy to read back after gains
+    fn constant_clip(level: f32, seconds: f32) -> Source {
+        Source::Clip(Arc::new(AudioClip {
+            sample_rate: RATE,
+            channels: 1,
+            samples: vec![level; (RATE as f32 * seconds) as usize],
+        }))
+    }
+
+    fn setup() -> (Arc<Mutex<Mixer>>, NullOutput) {
+        (
+            Arc::new(Mutex::new(Mixer::new(RATE))),
+            NullOutput::new(RATE),
+        )
+    }
+
+    // the next stereo frame the mixer produces
+    fn next_frame(mixer: &Arc<Mutex<Mixer>>) -> [f32; 2] {
+        let mut frame = [0.0; 2];
+        mixer.lock().unwrap().mix(&mut frame, 2);
+        frame
+    }
+
+    // fades move 1 / RATE per frame, allow a few frames of slack
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 5e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn bus_and_master_volumes_multiply() {
+        let (mixer, _) = setup();
+        {
+            let mut mixer = mixer.lock().unwrap();
+            mixer.set_bus_volume(Bus::Sfx, 0.5);
+            mixer.set_master_volume(0.8);
+            mixer.play(constant_clip(1.0, 1.0), PlayOptions::default());
+            mixer.play(
+                constant_clip(1.0, 1.0),
+                PlayOptions {
+                    bus: Bus::Music,
+                    ..PlayOptions::default()
+                },
+            );
+        }
+        let [left, right] = next_frame(&mixer);
+        // centered sounds get cos(pi / 4) per side
+        assert_close(left, (0.5 + 1.0) * 0.8 * FRAC_1_SQRT_2);
+        assert_close(right, left);
+
+        mixer.lock().unwrap().set_bus_volume(Bus::Music, 0.0);
+        let [left, _] = next_frame(&mixer);
+        assert_close(left, 0.5 * 0.8 * FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn fade_in_ramps_up_over_its_duration() {
+        let (mixer, mut output) = setup();
+        mixer.lock().unwrap().play(
+            constant_clip(1.0, 2.0),
+            PlayOptions {
+                fade_in: 1.0,
+                ..PlayOptions::default()
+            },
+        );
+        output.pump(&mixer, 0.5);
+        let [left, _] = next_frame(&mixer);
+        assert_close(left, 0.5 * FRAC_1_SQRT_2);
+
+        output.pump(&mixer, 0.6);
+        let [left, _] = next_frame(&mixer);
+        assert_close(left, FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn fade_out_stops_the_sound_when_done() {
+        let (mixer, mut output) = setup();
+        let id = mixer
+            .lock()
+            .unwrap()
+            .play(constant_clip(1.0, 2.0), PlayOptions::default());
+        mixer.lock().unwrap().stop(id, 0.5);
+
+        output.pump(&mixer, 0.25);
+        assert!(mixer.lock().unwrap().is_playing(id));
+        let [left, _] = next_frame(&mixer);
+        assert_close(left, 0.5 * FRAC_1_SQRT_2);
+
+        output.pump(&mixer, 0.3);
+        assert!(!mixer.lock().unwrap().is_playing(id));
+        assert_eq!(next_frame(&mixer), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn clips_finish_unless_looping() {
+        let (mixer, mut output) = setup();
+        let (once, looped) = {
+            let mut mixer = mixer.lock().unwrap();
+            (
+                mixer.play(constant_clip(0.1, 0.5), PlayOptions::default()),
+                mixer.play(
+                    constant_clip(0.1, 0.5),
+                    PlayOptions {
+                        looping: true,
+                        ..PlayOptions::default()
+                    },
+                ),
+            )
+        };
+        output.pump(&mixer, 1.0);
+        let mixer = mixer.lock().unwrap();
+        assert!(!mixer.is_playing(once));
+        assert!(mixer.is_playing(looped));
+    }
+
+    #[test]
+    fn positional_sounds_roll_off_between_min_and_max_distance() {
+        let (mixer, _) = setup();
+        let level_at = |x: f32| {
+            let mut locked = mixer.lock().unwrap();
+            locked.rolloff = Rolloff {
+                min_distance: 10.0,
+                max_distance: 30.0,
+            };
+            locked.set_listener([100.0, 100.0]);
+            let id = locked.play(
+                constant_clip(1.0, 1.0),
+                PlayOptions {
+                    position: Some([100.0, 100.0 + x]),
+                    ..PlayOptions::default()
+                },
+            );
+            drop(locked);
+            let [left, right] = next_frame(&mixer);
+            mixer.lock().unwrap().stop(id, 0.0);
+            left + right
+        };
+        let full = 2.0 * FRAC_1_SQRT_2;
+        assert_close(level_at(5.0), full);
+        assert_close(level_at(10.0), full);
+        assert_close(level_at(20.0), full * 0.5);
+        assert_close(level_at(30.0), 0.0);
+        assert_close(level_at(50.0), 0.0);
+    }
+
+    #[test]
+    fn streams_play_silence_while_starved_and_end_when_decoding_does() {
+        let (mixer, _) = setup();
+        let (decoder, chunks) = sync_channel(4);
+        let id = mixer.lock().unwrap().play(
+            Source::Stream(DecodedStream {
+                sample_rate: RATE,
+                channels: 1,
+                chunks,
+            }),
+            PlayOptions::default(),
+        );
+        assert_eq!(next_frame(&mixer), [0.0, 0.0]);
+        assert!(mixer.lock().unwrap().is_playing(id));
+
+        decoder.send(vec![1.0; 4]).unwrap();
+        let [left, _] = next_frame(&mixer);
+        assert_close(left, FRAC_1_SQRT_2);
+
+        drop(decoder);
+        let mut rest = [0.0; 16];
+        mixer.lock().unwrap().mix(&mut rest, 2);
+        assert_close(rest[4], FRAC_1_SQRT_2);
+        assert_eq!(rest[6], 0.0);
+        assert!(!mixer.lock().unwrap().is_playing(id));
+    }
+
+    #[test]
+    fn pan_moves_power_between_sides() {
+        let (mixer, _) = setup();
+        let id = mixer.lock().unwrap().play(
+            constant_clip(1.0, 1.0),
+            PlayOptions {
+                pan: 1.0,
+                ..PlayOptions::default()
+            },
+        );
+        let [left, right] = next_frame(&mixer);
+        assert_close(left, 0.0);
+        assert_close(right, 1.0);
+        mixer.lock().unwrap().stop(id, 0.0);
+
+        // sounds to the right of the listener lean right
+        mixer.lock().unwrap().play(
+            constant_clip(1.0, 1.0),
+            PlayOptions {
+                position: Some([3.0, 0.0]),
+                ..PlayOptions::default()
+            },
+        );
+        let [left, right] = next_frame(&mixer);
+        assert!(right > left);
+        // constant power, still at full volume inside min_distance
+        assert_close(left * left + right * right, 1.0);
+    }
+}
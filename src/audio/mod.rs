@@ -0,0 +1,7 @@
+mod audio;
+mod clip;
+mod mixer;
+mod output;
+
+pub use audio::Audio;
+pub use mixer::{Bus, PlayOptions};
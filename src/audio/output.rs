@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+
+use crate::audio::mixer::Mixer;
+
+/// Where mixed samples end up.
+pub trait AudioOutput {
+    /// Called once per engine frame. Device outputs pull from their own callback and do nothing here.
+    fn pump(&mut self, mixer: &Arc<Mutex<Mixer>>, dt: f32);
+}
+
+/// Mixes in real time and throws the samples away, for machines without a sound card.
+/// Playback still advances so fades, loops and finished sounds behave like on a device.
+pub struct NullOutput {
+    sample_rate: u32,
+    buffer: Vec<f32>,
+    pending_frames: f32,
+}
+
+impl NullOutput {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            buffer: Vec::new(),
+            pending_frames: 0.0,
+        }
+    }
+}
+
+impl AudioOutput for NullOutput {
+    fn pump(&mut self, mixer: &Arc<Mutex<Mixer>>, dt: f32) {
+        // carry the fraction so long sessions don't drift
+        self.pending_frames += dt * self.sample_rate as f32;
+        let frames = self.pending_frames as usize;
+        self.pending_frames -= frames as f32;
+
+        self.buffer.resize(frames * 2, 0.0);
+        if let Ok(mut mixer) = mixer.lock() {
+            mixer.mix(&mut self.buffer, 2);
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+pub use cpal_output::CpalOutput;
+
+#[cfg(feature = "audio")]
+mod cpal_output {
+    use std::sync::{Arc, Mutex};
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, SizedSample};
+
+    use super::AudioOutput;
+    use crate::audio::mixer::Mixer;
+
+    /// The default sound card, mixing from the device callback.
+    pub struct CpalOutput {
+        _stream: cpal::Stream, // playback stops when this drops
+    }
+
+    impl CpalOutput {
+        pub fn new(mixer: Arc<Mutex<Mixer>>) -> anyhow::Result<Self> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| anyhow::anyhow!("No audio output device"))?;
+            let supported = device.default_output_config()?;
+            let config = supported.config();
+
+            // nothing is playing yet, start over at the device's rate
+            *mixer
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Audio mixer poisoned"))? =
+                Mixer::new(config.sample_rate.0);
+            let stream = match supported.sample_format() {
+                cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer),
+                cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer),
+                cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer),
+                cpal::SampleFormat::I32 => build_stream::<i32>(&device, &config, mixer),
+                format => Err(anyhow::anyhow!("Unsupported sample format {:?}", format)),
+            }?;
+            stream.play()?;
+
+            Ok(Self { _stream: stream })
+        }
+    }
+
+    // mixes in f32 and converts to whatever the device takes
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mixer: Arc<Mutex<Mixer>>,
+    ) -> anyhow::Result<cpal::Stream>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let channels = config.channels as usize;
+        let mut mixed: Vec<f32> = Vec::new(); // only grows, after the first callbacks
+        let stream = device.build_output_stream(
+            config,
+            move |out: &mut [T], _| {
+                mixed.resize(out.len(), 0.0);
+                match mixer.lock() {
+                    Ok(mut mixer) => mixer.mix(&mut mixed, channels),
+                    Err(_) => mixed.iter_mut().for_each(|sample| *sample = 0.0),
+                }
+                for (out, sample) in out.iter_mut().zip(&mixed) {
+                    *out = T::from_sample(*sample);
+                }
+            },
+            |e| println!("Audio stream error: {}", e),
+            None,
+        )?;
+        Ok(stream)
+    }
+
+    impl AudioOutput for CpalOutput {
+        fn pump(&mut self, _mixer: &Arc<Mutex<Mixer>>, _dt: f32) {}
+    }
+}
@@ -1,5 +1,11 @@
 use cgmath::{ortho, InnerSpace, Matrix4, Vector2};
 
+use crate::components_systems::Entity;
+
+pub type CameraId = u32;
+/// Always present, draws first and is what screen positions and debug shapes go through.
+pub const MAIN_CAMERA: CameraId = 0;
+
 pub struct Camera2DConfig {
     pub zoom: f32, // how much of the world you see. larger = more
     pub initial_position: [f32; 2],
@@ -8,11 +14,116 @@ pub struct Camera2DConfig {
     pub look_ahead_lerp_speed: f32,
     pub screen_width: f32,
     pub screen_height: f32,
+    pub pixel_perfect: Option<PixelPerfectConfig>,
+}
+
+/// Renders the world to a fixed low-res target, scaled up by whole numbers onto the window.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelPerfectConfig {
+    pub width: u32, // virtual resolution in pixels
+    pub height: u32,
+    pub pixels_per_unit: f32, // art texels per world unit
+}
+
+impl PixelPerfectConfig {
+    pub fn units_per_pixel(&self) -> f32 {
+        1.0 / self.pixels_per_unit
+    }
+
+    /// Where the virtual frame sits in a window, `[x, y, width, height]` in window pixels.
+    /// Scaled by the largest whole number that fits, the rest is letterboxed.
+    /// Windows smaller than the frame shrink it to fit, giving up on square texels.
+    pub fn viewport(&self, window_width: u32, window_height: u32) -> [f32; 4] {
+        let fit = (window_width as f32 / self.width as f32)
+            .min(window_height as f32 / self.height as f32);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let width = self.width as f32 * scale;
+        let height = self.height as f32 * scale;
+        [
+            ((window_width as f32 - width) * 0.5).floor(),
+            ((window_height as f32 - height) * 0.5).floor(),
+            width,
+            height,
+        ]
+    }
+}
+
+/// What a camera follows each physics tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraTarget {
+    Player, // whichever entity is the engine's player
+    Entity(Entity),
+    None, // stays where it was put
+}
+
+/// How split-screen viewports are arranged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitLayout {
+    Horizontal, // side by side
+    Vertical,   // stacked top to bottom
+    Grid,       // 2x2, left to right then top to bottom
+}
+
+impl SplitLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "horizontal" => Some(Self::Horizontal),
+            "vertical" => Some(Self::Vertical),
+            "grid" | "2x2" => Some(Self::Grid),
+            _ => None,
+        }
+    }
+
+    /// Viewports for `count` players, a grid leaves the cells after the last player empty.
+    pub fn viewports(&self, count: usize) -> Vec<[f32; 4]> {
+        let count = count.max(1);
+        match self {
+            Self::Horizontal => {
+                let width = 1.0 / count as f32;
+                (0..count)
+                    .map(|index| [index as f32 * width, 0.0, width, 1.0])
+                    .collect()
+            }
+            Self::Vertical => {
+                let height = 1.0 / count as f32;
+                (0..count)
+                    .map(|index| [0.0, index as f32 * height, 1.0, height])
+                    .collect()
+            }
+            Self::Grid => (0..count.min(4))
+                .map(|index| [(index % 2) as f32 * 0.5, (index / 2) as f32 * 0.5, 0.5, 0.5])
+                .collect(),
+        }
+    }
 }
 
+/// A scripted move from one point to another, eased in and out.
+#[derive(Debug, Clone, Copy)]
+struct CameraPan {
+    from: Vector2<f32>,
+    to: Vector2<f32>,
+    elapsed: f32,
+    duration: f32,
+}
+
+#[derive(Clone)]
 pub struct Camera2D {
     pub position: Vector2<f32>,
     pub zoom: f32,
+    pub target: CameraTarget,
+    pub viewport: [f32; 4], // x y width height as fractions of the frame, from the top left
+    pub order: i32,         // cameras draw from low to high, ties by creation
+    pub bounds: Option<[f32; 4]>, // min x, min y, max x, max y the view is kept inside
+    pub dead_zone: [f32; 2], // half extents around the center the target moves in freely
+    pub zoom_speed: f32,    // per second toward the target zoom, 0 jumps straight to it
+    pub trauma_decay: f32,  // trauma lost per second
+    pub max_shake: f32,     // offset in world units at full trauma
+    pub shake_frequency: f32,
+    target_zoom: f32,
+    trauma: f32, // 0 to 1, shake grows with its square so small hits stay subtle
+    shake_time: f32,
+    shake_offset: Vector2<f32>,
+    pan: Option<CameraPan>,
     aspect_ratio: f32,
     smooth_factor: f32,
     look_ahead: f32,
@@ -21,13 +132,28 @@ pub struct Camera2D {
     screen_width: f32,
     screen_height: f32,
     previous_velocity: Vector2<f32>,
+    pixel_perfect: Option<PixelPerfectConfig>,
 }
 
 impl Camera2D {
     pub fn new(config: &Camera2DConfig) -> Self {
-        Self {
+        let mut camera = Self {
             position: Vector2::new(config.initial_position[0], config.initial_position[1]),
             zoom: config.zoom,
+            target: CameraTarget::Player,
+            viewport: [0.0, 0.0, 1.0, 1.0],
+            order: 0,
+            bounds: None,
+            dead_zone: [0.0, 0.0],
+            zoom_speed: 0.0,
+            trauma_decay: 1.0,
+            max_shake: 0.5,
+            shake_frequency: 20.0,
+            target_zoom: config.zoom,
+            trauma: 0.0,
+            shake_time: 0.0,
+            shake_offset: Vector2::new(0.0, 0.0),
+            pan: None,
             aspect_ratio: config.screen_width / config.screen_height,
             smooth_factor: config.look_ahead_smooth_factor,
             look_ahead: config.look_ahead_distance,
@@ -36,10 +162,223 @@ impl Camera2D {
             previous_velocity: Vector2::new(0.0, 0.0),
             screen_width: config.screen_width,
             screen_height: config.screen_height,
+            pixel_perfect: config.pixel_perfect,
+        };
+        camera.set_viewport(camera.viewport);
+        camera
+    }
+
+    pub fn pixel_perfect(&self) -> Option<&PixelPerfectConfig> {
+        self.pixel_perfect.as_ref()
+    }
+
+    /// The part of the frame the camera draws to. In pixel-perfect mode this also resets the
+    /// zoom to one texel per virtual pixel for the new size.
+    pub fn set_viewport(&mut self, viewport: [f32; 4]) {
+        self.viewport = viewport;
+        if let Some(pixel_perfect) = self.pixel_perfect {
+            let height = pixel_perfect.height as f32 * viewport[3];
+            self.set_zoom(height * 0.5 / pixel_perfect.pixels_per_unit, false);
+        }
+        self.update_aspect_ratio(self.screen_width as u32, self.screen_height as u32);
+    }
+
+    /// `[x, y, width, height]` of the viewport in pixels of a `width` by `height` frame.
+    pub fn viewport_rect(&self, width: u32, height: u32) -> [f32; 4] {
+        let [x, y, w, h] = self.viewport;
+        let left = (x * width as f32).floor();
+        let top = (y * height as f32).floor();
+        [
+            left,
+            top,
+            ((x + w) * width as f32).floor() - left,
+            ((y + h) * height as f32).floor() - top,
+        ]
+    }
+
+    /// The world rectangle in view, `[min x, min y, max x, max y]`, exactly what
+    /// `build_matrix` projects, shake and pixel snapping included.
+    pub fn visible_rect(&self) -> [f32; 4] {
+        let half_width = self.aspect_ratio * self.zoom;
+        let half_height = self.zoom;
+
+        let center = self.position + self.shake_offset;
+        let mut left = center.x - half_width;
+        let mut bottom = center.y - half_height;
+        // the view's edges land on texel boundaries so nothing shimmers as the camera glides
+        if let Some(pixel_perfect) = &self.pixel_perfect {
+            let unit = pixel_perfect.units_per_pixel();
+            left = (left / unit).round() * unit;
+            bottom = (bottom / unit).round() * unit;
+        }
+        [
+            left,
+            bottom,
+            left + half_width * 2.0,
+            bottom + half_height * 2.0,
+        ]
+    }
+
+    /// Applies the keys present in a Lua table, anything left out is kept.
+    /// ```lua
+    /// {
+    ///   target = "player", -- or an entity id, or false to stop following
+    ///   position = { 0, 0 }, -- jumps there
+    ///   zoom = 12, zoom_speed = 4, -- half the view height in world units, eased at zoom_speed
+    ///   viewport = { 0.75, 0, 0.25, 0.25 }, -- fractions of the frame from the top left
+    ///   order = 1, -- drawn over cameras with a lower order
+    ///   bounds = { -26, -26, 26, 26 }, -- or false, min x, min y, max x, max y
+    ///   dead_zone = { 2, 1 }, -- half extents
+    ///   look_ahead = 3, smoothing = 0.1,
+    ///   max_shake = 0.5, trauma_decay = 1, shake_frequency = 20,
+    /// }
+    /// ```
+    pub fn configure_from_lua(&mut self, table: &mlua::Table) -> mlua::Result<()> {
+        match table.get::<mlua::Value>("target")? {
+            mlua::Value::Nil => {}
+            mlua::Value::Boolean(false) => self.target = CameraTarget::None,
+            mlua::Value::Integer(id) => self.target = CameraTarget::Entity(id as Entity),
+            mlua::Value::String(name) if name.to_str()? == "player" => {
+                self.target = CameraTarget::Player
+            }
+            other => {
+                return Err(mlua::Error::runtime(format!(
+                    "camera target must be \"player\", an entity or false, got {:?}",
+                    other
+                )))
+            }
+        }
+        if let Some(viewport) = table.get::<Option<[f32; 4]>>("viewport")? {
+            self.set_viewport(viewport);
+        }
+        if let Some(order) = table.get::<Option<i32>>("order")? {
+            self.order = order;
+        }
+        if let Some(zoom_speed) = table.get::<Option<f32>>("zoom_speed")? {
+            self.zoom_speed = zoom_speed.max(0.0);
         }
+        if let Some(zoom) = table.get::<Option<f32>>("zoom")? {
+            self.set_zoom(zoom, true);
+        }
+        match table.get::<mlua::Value>("bounds")? {
+            mlua::Value::Nil => {}
+            mlua::Value::Boolean(false) => self.bounds = None,
+            _ => self.bounds = Some(table.get::<[f32; 4]>("bounds")?),
+        }
+        if let Some(dead_zone) = table.get::<Option<[f32; 2]>>("dead_zone")? {
+            self.dead_zone = dead_zone.map(|extent| extent.max(0.0));
+        }
+        if let Some(look_ahead) = table.get::<Option<f32>>("look_ahead")? {
+            self.look_ahead = look_ahead.max(0.0);
+        }
+        if let Some(smoothing) = table.get::<Option<f32>>("smoothing")? {
+            self.smooth_factor = smoothing.clamp(0.0, 1.0);
+        }
+        if let Some(max_shake) = table.get::<Option<f32>>("max_shake")? {
+            self.max_shake = max_shake;
+        }
+        if let Some(trauma_decay) = table.get::<Option<f32>>("trauma_decay")? {
+            self.trauma_decay = trauma_decay;
+        }
+        if let Some(shake_frequency) = table.get::<Option<f32>>("shake_frequency")? {
+            self.shake_frequency = shake_frequency;
+        }
+        if let Some(position) = table.get::<Option<[f32; 2]>>("position")? {
+            self.jump_to(position);
+        }
+        Ok(())
+    }
+
+    /// Zooms toward `zoom` at `zoom_speed`, or at once when `smooth` is false.
+    pub fn set_zoom(&mut self, zoom: f32, smooth: bool) {
+        self.target_zoom = zoom.max(0.01);
+        if !smooth || self.zoom_speed <= 0.0 {
+            self.zoom = self.target_zoom;
+        }
+    }
+
+    /// Adds to the shake, capped at full strength.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Glides to a point over `duration` seconds and stays there, following nothing.
+    pub fn pan_to(&mut self, to: [f32; 2], duration: f32) {
+        self.target = CameraTarget::None;
+        self.pan = Some(CameraPan {
+            from: self.position,
+            to: Vector2::new(to[0], to[1]),
+            elapsed: 0.0,
+            duration: duration.max(0.0),
+        });
+    }
+
+    /// Moves at once, cancelling a pan.
+    pub fn jump_to(&mut self, position: [f32; 2]) {
+        self.pan = None;
+        self.position = Vector2::new(position[0], position[1]);
+        self.look_ahead_offset = Vector2::new(0.0, 0.0);
+        self.clamp_to_bounds();
+    }
+
+    /// Advances pans, zoom and shake, once per frame.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(pan) = self.pan.as_mut() {
+            pan.elapsed += dt;
+            let t = if pan.duration > 0.0 {
+                (pan.elapsed / pan.duration).min(1.0)
+            } else {
+                1.0
+            };
+            let eased = t * t * (3.0 - 2.0 * t);
+            self.position = pan.from + (pan.to - pan.from) * eased;
+            if t >= 1.0 {
+                self.pan = None;
+            }
+        }
+
+        if self.zoom_speed > 0.0 {
+            self.zoom += (self.target_zoom - self.zoom) * (1.0 - (-self.zoom_speed * dt).exp());
+        } else {
+            self.zoom = self.target_zoom;
+        }
+        self.clamp_to_bounds();
+
+        self.trauma = (self.trauma - self.trauma_decay * dt).max(0.0);
+        self.shake_time += dt;
+        let strength = self.trauma * self.trauma * self.max_shake;
+        let t = self.shake_time * self.shake_frequency;
+        self.shake_offset = Vector2::new(shake_noise(t, 0.0), shake_noise(t, 31.7)) * strength;
+    }
+
+    /// Keeps the view inside `bounds`, centering on them on an axis they are smaller than.
+    fn clamp_to_bounds(&mut self) {
+        let Some([min_x, min_y, max_x, max_y]) = self.bounds else {
+            return;
+        };
+        let half_width = self.aspect_ratio * self.zoom;
+        let half_height = self.zoom;
+        self.position.x = clamp_or_center(self.position.x, min_x + half_width, max_x - half_width);
+        self.position.y =
+            clamp_or_center(self.position.y, min_y + half_height, max_y - half_height);
+    }
+
+    /// The point the camera eases toward, the target pushed back by the dead zone.
+    fn dead_zone_target(&self, target: Vector2<f32>) -> Vector2<f32> {
+        let offset = target - self.position;
+        let outside = Vector2::new(
+            offset.x - offset.x.clamp(-self.dead_zone[0], self.dead_zone[0]),
+            offset.y - offset.y.clamp(-self.dead_zone[1], self.dead_zone[1]),
+        );
+        self.position + outside
     }
 
     pub fn update_follow(&mut self, target: Vector2<f32>, velocity: Vector2<f32>) {
+        // a pan has the camera until it ends
+        if self.pan.is_some() {
+            return;
+        }
+        let target = self.dead_zone_target(target);
         let speed = velocity.magnitude();
 
         let dir_changed = (velocity.normalize().dot(self.previous_velocity.normalize())).is_nan()
@@ -75,17 +414,20 @@ impl Camera2D {
     }
 
     pub fn update_aspect_ratio(&mut self, width: u32, height: u32) {
-        self.aspect_ratio = width as f32 / height as f32;
+        // in pixel-perfect mode the viewport is part of the virtual frame, not the window
+        let frame = match self.pixel_perfect {
+            Some(pixel_perfect) => [pixel_perfect.width as f32, pixel_perfect.height as f32],
+            None => [width as f32, height as f32],
+        };
+        self.aspect_ratio =
+            (frame[0] * self.viewport[2]) / (frame[1] * self.viewport[3]).max(f32::EPSILON);
+        // the static (canvas) matrices are in pixels, so they track the window size too
+        self.screen_width = width as f32;
+        self.screen_height = height as f32;
     }
 
     pub fn build_matrix(&self) -> Matrix4<f32> {
-        let half_width = self.aspect_ratio * self.zoom;
-        let half_height = self.zoom;
-
-        let left = self.position.x - half_width;
-        let right = self.position.x + half_width;
-        let bottom = self.position.y - half_height;
-        let top = self.position.y + half_height;
+        let [left, bottom, right, top] = self.visible_rect();
 
         // z: -1 to 1, because we're not using 3D
         ortho(left, right, bottom, top, -1.0, 1.0)
@@ -107,3 +449,19 @@ impl Camera2D {
         ortho(left, right, bottom, top, -1.0, 1.0)
     }
 }
+
+/// Smooth noise in -1 to 1, sines at unrelated frequencies so the shake never visibly loops.
+fn shake_noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() * 0.5
+        + (t * 2.17 + seed * 1.3).sin() * 0.3
+        + (t * 4.73 + seed * 0.7).sin() * 0.2)
+        .clamp(-1.0, 1.0)
+}
+
+fn clamp_or_center(value: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        (min + max) * 0.5
+    } else {
+        value.clamp(min, max)
+    }
+}
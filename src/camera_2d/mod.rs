@@ -1,3 +1,3 @@
 pub mod camera_2d;
 
-pub use camera_2d::Camera2D;
+pub use camera_2d::{Camera2D, CameraId, CameraTarget, SplitLayout, MAIN_CAMERA};
@@ -33,10 +33,6 @@ pub fn set_entity_state(world: &mut World, entity: Entity, state: ActionState) {
     );
 
     if let Some(animations) = world.animations.get_mut(&entity) {
-        if let Some(anim) = animations.animations.get_mut(&state) {
-            animations.current_frame_index = 0;
-            animations.current_frame = anim.frames[0].clone();
-            animations.frame_timer = 0.0;
-        };
+        animations.restart(&state);
     }
 }
@@ -4,7 +4,7 @@ use cgmath::Vector2;
 
 use crate::{
     bitmaps::vecbool_to_u8,
-    components_systems::{physics_2d::Area2D, ActionState, Entity},
+    components_systems::{physics_2d::Area2D, ActionState, Entity, Facing},
     world::World,
 };
 
@@ -15,6 +15,28 @@ pub struct SpriteFrame {
     pub hitboxes: Vec<Area2D>,
     pub hurtboxes: Vec<Area2D>,
     pub frame_pixel_dims: [f32; 2],
+    pub events: Vec<String>, // named events fired when the frame is entered
+}
+
+/// Frame order of a clip, named after Aseprite's tag directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackMode {
+    #[default]
+    Forward,
+    Reverse,
+    PingPong,        // first to last and back, a full trip counts as one loop
+    PingPongReverse, // last to first and back
+}
+
+impl From<&str> for PlaybackMode {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "reverse" => PlaybackMode::Reverse,
+            "pingpong" | "ping_pong" => PlaybackMode::PingPong,
+            "pingpong_reverse" | "ping_pong_reverse" => PlaybackMode::PingPongReverse,
+            _ => PlaybackMode::Forward,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,26 +46,44 @@ pub struct Animation {
     pub is_transparent: bool,
     pub frames: Vec<SpriteFrame>,
     pub looped: bool,
+    pub mode: PlaybackMode,
 }
 
 #[derive(Debug, Clone)]
 pub struct AnimationComponent {
     pub animations: HashMap<ActionState, Animation>,
+    // one clip per facing, used over `animations` for the states that have them
+    pub directional: HashMap<ActionState, HashMap<Facing, Animation>>,
+    pub facing: Facing,
     pub current_frame: SpriteFrame,
     pub current_frame_index: usize,
     pub frame_timer: f32,
+    pub finished: bool,      // non-looped clip reached its end
+    pub frame_entered: bool, // current frame's events haven't been reported yet
+    pub backwards: bool,     // travel direction of the current clip
+    pub time_scale: f32,     // per-entity, multiplied with the world's animation time scale
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEvent {
+    Frame { name: String, frame: usize },
+    Finished,
 }
 
 impl Animation {
     pub fn from_lua_table(table: mlua::Table) -> (Self, String) {
         let looped: bool = table.get("looped").unwrap_or(true);
         let is_transparent: bool = table.get("is_transparent").unwrap_or(false);
+        let direction: String = table.get("direction").unwrap_or("forward".to_string());
 
         let frames_table: mlua::Table = table
             .get("frames")
             .expect("Missing 'frames' table in animation");
         let hitboxes: mlua::Table = table.get("hitboxes").unwrap();
         let hurtboxes: mlua::Table = table.get("hurtboxes").unwrap();
+        // { [frame] = { "footstep", ... } }, frames counted from 1 like the frames table
+        let events: Option<mlua::Table> = table.get("events").ok();
 
         let tex_w: f32 = table.get("sprite_sheet_width").unwrap();
         let tex_h: f32 = table.get("sprite_sheet_height").unwrap();
@@ -85,6 +125,10 @@ impl Animation {
                     hitboxes: parse_hitboxes_from_table(&hitboxes, i, frame_pixel_dims),
                     hurtboxes: parse_hitboxes_from_table(&hurtboxes, i, frame_pixel_dims),
                     frame_pixel_dims,
+                    events: events
+                        .as_ref()
+                        .and_then(|events| events.get::<Vec<String>>((i + 1) as i64).ok())
+                        .unwrap_or_default(),
                 });
             }
         }
@@ -94,6 +138,7 @@ impl Animation {
                 frames,
                 looped,
                 is_transparent,
+                mode: PlaybackMode::from(direction.as_str()),
             },
             sprite_path,
         )
@@ -141,37 +186,405 @@ fn parse_hitboxes_from_table(
     boxes
 }
 
-pub fn animation_system_update_frames(world: &mut World, dt: f32) {
-    for (entity, animation) in world.animations.iter_mut() {
-        if let Some(action_state) = world.action_states.get(entity) {
-            if let Some(anim) = animation.animations.get(&action_state.state) {
-                if anim.frames.is_empty() {
-                    return;
+impl AnimationComponent {
+    /// Starts on the first frame `state` plays, which is the last one for reversed clips.
+    pub fn new(animations: HashMap<ActionState, Animation>, state: &ActionState) -> Self {
+        Self::new_directional(animations, HashMap::new(), Facing::default(), state)
+    }
+
+    pub fn new_directional(
+        animations: HashMap<ActionState, Animation>,
+        directional: HashMap<ActionState, HashMap<Facing, Animation>>,
+        facing: Facing,
+        state: &ActionState,
+    ) -> Self {
+        let current_frame = find_clip(&animations, &directional, facing, state)
+            .and_then(|anim| anim.frames.first())
+            .expect("Animation for the initial state needs at least one frame")
+            .clone();
+        let mut component = Self {
+            animations,
+            directional,
+            facing,
+            current_frame,
+            current_frame_index: 0,
+            frame_timer: 0.0,
+            finished: false,
+            frame_entered: true,
+            backwards: false,
+            time_scale: 1.0,
+            paused: false,
+        };
+        component.restart(state);
+        component
+    }
+
+    /// The clip `state` plays in the current facing.
+    pub fn clip(&self, state: &ActionState) -> Option<&Animation> {
+        find_clip(&self.animations, &self.directional, self.facing, state)
+    }
+
+    /// Swaps to the clip for the new facing at the same frame, so turning mid-walk
+    /// doesn't restart the cycle or repeat the frame's events.
+    pub fn set_facing(&mut self, state: &ActionState, facing: Facing) {
+        if self.facing == facing {
+            return;
+        }
+        self.facing = facing;
+        if let Some(anim) = find_clip(&self.animations, &self.directional, facing, state) {
+            if let Some(last) = anim.frames.len().checked_sub(1) {
+                self.current_frame_index = self.current_frame_index.min(last);
+                self.current_frame = anim.frames[self.current_frame_index].clone();
+            }
+        }
+    }
+
+    /// Advances the frame timer of the animation mapped to `state` and returns the events of
+    /// every frame it entered. Shared by the world and the UI canvas so both play clips the same way.
+    pub fn advance(&mut self, state: &ActionState, dt: f32) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+        let anim = match find_clip(&self.animations, &self.directional, self.facing, state) {
+            Some(anim) if !anim.frames.is_empty() => anim,
+            _ => return events,
+        };
+
+        if self.frame_entered {
+            self.frame_entered = false;
+            let current = self.current_frame_index.min(anim.frames.len() - 1);
+            push_frame_events(anim, current, &mut events);
+        }
+        if self.finished || self.paused {
+            return events;
+        }
+
+        self.frame_timer += dt * self.time_scale.max(0.0);
+        // a long frame can cross several short ones, none of their events get skipped
+        loop {
+            let current = self.current_frame_index.min(anim.frames.len() - 1);
+            let frame_duration = anim.frames[current].duration;
+            if frame_duration <= 0.0 || self.frame_timer < frame_duration {
+                break;
+            }
+
+            self.frame_timer -= frame_duration; // carry over extra time
+            match next_frame(anim, current, self.backwards) {
+                Some((next, backwards)) => {
+                    self.current_frame_index = next;
+                    self.backwards = backwards;
                 }
+                None => {
+                    // hold the last frame
+                    self.finished = true;
+                    self.frame_timer = 0.0;
+                    events.push(AnimationEvent::Finished);
+                    break;
+                }
+            }
+            self.current_frame = anim.frames[self.current_frame_index].clone();
+            push_frame_events(anim, self.current_frame_index, &mut events);
+        }
+        events
+    }
 
-                animation.frame_timer += dt;
-
-                let current = animation.current_frame_index.min(anim.frames.len() - 1);
-                let frame_duration = anim.frames[current].duration;
-
-                if animation.frame_timer >= frame_duration {
-                    animation.frame_timer -= frame_duration; // carry over extra time
-                    animation.current_frame_index += 1;
-                    if animation.current_frame_index >= anim.frames.len() {
-                        animation.current_frame_index = if anim.looped {
-                            0
-                        } else {
-                            anim.frames.len() - 1
-                        };
-                    }
-                    animation.current_frame = animation
-                        .animations
-                        .get(&action_state.state)
-                        .unwrap()
-                        .frames[animation.current_frame_index]
-                        .clone();
+    /// Starts the clip for the new state from its first frame.
+    pub fn restart(&mut self, state: &ActionState) {
+        if let Some(anim) = find_clip(&self.animations, &self.directional, self.facing, state) {
+            if anim.frames.is_empty() {
+                return;
+            }
+            let (index, backwards) = match anim.mode {
+                PlaybackMode::Forward | PlaybackMode::PingPong => (0, false),
+                PlaybackMode::Reverse | PlaybackMode::PingPongReverse => {
+                    (anim.frames.len() - 1, true)
                 }
+            };
+            self.current_frame_index = index;
+            self.current_frame = anim.frames[index].clone();
+            self.backwards = backwards;
+            self.frame_timer = 0.0;
+            self.finished = false;
+            self.frame_entered = true;
+        }
+    }
+
+    /// Jumps to `index` in the clip for `state`, clamped to its last frame, `timer` seconds in.
+    pub fn seek(&mut self, state: &ActionState, index: usize, timer: f32) {
+        if let Some(anim) = find_clip(&self.animations, &self.directional, self.facing, state) {
+            if anim.frames.is_empty() {
+                return;
+            }
+            let index = index.min(anim.frames.len() - 1);
+            self.current_frame_index = index;
+            self.current_frame = anim.frames[index].clone();
+            self.frame_timer = timer.clamp(0.0, anim.frames[index].duration.max(0.0));
+            self.finished = false;
+            self.frame_entered = true;
+        }
+    }
+}
+
+fn find_clip<'a>(
+    animations: &'a HashMap<ActionState, Animation>,
+    directional: &'a HashMap<ActionState, HashMap<Facing, Animation>>,
+    facing: Facing,
+    state: &ActionState,
+) -> Option<&'a Animation> {
+    match directional.get(state) {
+        Some(clips) => facing
+            .fallbacks()
+            .iter()
+            .find_map(|facing| clips.get(facing))
+            .or_else(|| animations.get(state))
+            .or_else(|| facing.closest().find_map(|facing| clips.get(&facing))),
+        None => animations.get(state),
+    }
+}
+
+/// The frame after `current` and the direction to keep going in, `None` once a
+/// non-looped clip has played through.
+fn next_frame(anim: &Animation, current: usize, backwards: bool) -> Option<(usize, bool)> {
+    let last = anim.frames.len() - 1;
+    match anim.mode {
+        PlaybackMode::Forward if current < last => Some((current + 1, false)),
+        PlaybackMode::Forward => anim.looped.then_some((0, false)),
+        PlaybackMode::Reverse if current > 0 => Some((current - 1, true)),
+        PlaybackMode::Reverse => anim.looped.then_some((last, true)),
+        PlaybackMode::PingPong | PlaybackMode::PingPongReverse => {
+            if last == 0 {
+                return anim.looped.then_some((0, backwards));
+            }
+            // the trip ends back where it started
+            let ends_at_start = anim.mode == PlaybackMode::PingPong;
+            match (backwards, current) {
+                (false, c) if c < last => Some((c + 1, false)),
+                (true, c) if c > 0 => Some((c - 1, true)),
+                (false, _) if ends_at_start => Some((last - 1, true)),
+                (true, _) if !ends_at_start => Some((1, false)),
+                (false, _) => anim.looped.then_some((last - 1, true)),
+                (true, _) => anim.looped.then_some((1, false)),
+            }
+        }
+    }
+}
+
+fn push_frame_events(anim: &Animation, frame: usize, events: &mut Vec<AnimationEvent>) {
+    for name in anim.frames[frame].events.iter() {
+        events.push(AnimationEvent::Frame {
+            name: name.clone(),
+            frame,
+        });
+    }
+}
+
+/// Advances every entity's animation, returning the events in the order they happened per entity.
+pub fn animation_system_update_frames(
+    world: &mut World,
+    dt: f32,
+) -> Vec<(Entity, ActionState, AnimationEvent)> {
+    let mut events = Vec::new();
+    for (entity, animation) in world.animations.iter_mut() {
+        if let Some(action_state) = world.action_states.get(entity) {
+            let speed = world.animation_time_scale
+                * world
+                    .animation_graphs
+                    .get(entity)
+                    .map_or(1.0, |graph| graph.speed(&action_state.state));
+            for event in animation.advance(&action_state.state, dt * speed) {
+                events.push((*entity, action_state.state.clone(), event));
             }
         }
     }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: ActionState = ActionState::Custom(0);
+
+    // one second per frame, frame `i` fires event "i" when given `events`
+    fn clip(frames: usize, mode: PlaybackMode, looped: bool, events: bool) -> Animation {
+        Animation {
+            sprite_sheet_id: 0,
+            is_transparent: false,
+            frames: (0..frames)
+                .map(|i| SpriteFrame {
+                    uv_coords: [[0.0; 2]; 4],
+                    duration: 1.0,
+                    hitboxes: Vec::new(),
+                    hurtboxes: Vec::new(),
+                    frame_pixel_dims: [16.0, 16.0],
+                    events: if events {
+                        vec![i.to_string()]
+                    } else {
+                        Vec::new()
+                    },
+                })
+                .collect(),
+            looped,
+            mode,
+        }
+    }
+
+    fn component(anim: Animation) -> AnimationComponent {
+        AnimationComponent::new(HashMap::from([(STATE, anim)]), &STATE)
+    }
+
+    // the frame shown after each of `steps` advances by `dt`
+    fn sequence(component: &mut AnimationComponent, dt: f32, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                component.advance(&STATE, dt);
+                component.current_frame_index
+            })
+            .collect()
+    }
+
+    fn frame_event(frame: usize) -> AnimationEvent {
+        AnimationEvent::Frame {
+            name: frame.to_string(),
+            frame,
+        }
+    }
+
+    fn finished_count(events: &[AnimationEvent]) -> usize {
+        events
+            .iter()
+            .filter(|event| **event == AnimationEvent::Finished)
+            .count()
+    }
+
+    #[test]
+    fn forward_wraps_to_the_first_frame() {
+        let mut anim = component(clip(3, PlaybackMode::Forward, true, false));
+        assert_eq!(anim.current_frame_index, 0);
+        assert_eq!(sequence(&mut anim, 1.0, 5), vec![1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn reverse_starts_on_the_last_frame() {
+        let mut anim = component(clip(3, PlaybackMode::Reverse, true, false));
+        assert_eq!(anim.current_frame_index, 2);
+        assert_eq!(sequence(&mut anim, 1.0, 5), vec![1, 0, 2, 1, 0]);
+    }
+
+    #[test]
+    fn ping_pong_bounces_off_both_ends() {
+        let mut anim = component(clip(3, PlaybackMode::PingPong, true, false));
+        assert_eq!(sequence(&mut anim, 1.0, 6), vec![1, 2, 1, 0, 1, 2]);
+
+        // played once, the trip ends back on the first frame
+        let mut anim = component(clip(3, PlaybackMode::PingPong, false, false));
+        assert_eq!(sequence(&mut anim, 1.0, 6), vec![1, 2, 1, 0, 0, 0]);
+        assert!(anim.finished);
+    }
+
+    #[test]
+    fn ping_pong_reverse_bounces_from_the_last_frame() {
+        let mut anim = component(clip(3, PlaybackMode::PingPongReverse, true, false));
+        assert_eq!(anim.current_frame_index, 2);
+        assert_eq!(sequence(&mut anim, 1.0, 6), vec![1, 0, 1, 2, 1, 0]);
+
+        let mut anim = component(clip(3, PlaybackMode::PingPongReverse, false, false));
+        assert_eq!(sequence(&mut anim, 1.0, 6), vec![1, 0, 1, 2, 2, 2]);
+        assert!(anim.finished);
+    }
+
+    #[test]
+    fn one_step_can_cross_several_frames() {
+        let mut anim = component(clip(3, PlaybackMode::Forward, true, false));
+        assert_eq!(sequence(&mut anim, 2.5, 1), vec![2]);
+        // the half frame left over carries into the next step
+        assert_eq!(sequence(&mut anim, 0.5, 1), vec![0]);
+
+        // 0 -> 1 -> 2 -> 1 on the way back, then 0 -> 1 -> 2 on the way out
+        let mut anim = component(clip(3, PlaybackMode::PingPong, true, false));
+        assert_eq!(sequence(&mut anim, 3.0, 1), vec![1]);
+        assert!(anim.backwards);
+        assert_eq!(sequence(&mut anim, 3.0, 1), vec![2]);
+        assert!(!anim.backwards);
+    }
+
+    #[test]
+    fn time_scale_and_pause_change_how_far_a_step_goes() {
+        let mut anim = component(clip(3, PlaybackMode::Forward, true, false));
+        anim.time_scale = 0.5;
+        assert_eq!(sequence(&mut anim, 1.0, 4), vec![0, 1, 1, 2]);
+
+        anim.paused = true;
+        assert_eq!(sequence(&mut anim, 5.0, 2), vec![2, 2]);
+        anim.paused = false;
+        anim.time_scale = 2.0;
+        assert_eq!(sequence(&mut anim, 1.0, 1), vec![1]);
+
+        // negative scales hold the frame instead of running backwards
+        anim.time_scale = -1.0;
+        assert_eq!(sequence(&mut anim, 1.0, 2), vec![1, 1]);
+    }
+
+    #[test]
+    fn seek_clamps_and_resumes_a_finished_clip() {
+        let mut anim = component(clip(3, PlaybackMode::Forward, false, true));
+        anim.advance(&STATE, 10.0);
+        assert!(anim.finished);
+
+        anim.seek(&STATE, 7, 0.75);
+        assert_eq!(anim.current_frame_index, 2);
+        assert_eq!(anim.frame_timer, 0.75);
+        assert!(!anim.finished);
+        // the frame sought to reports its events again
+        assert_eq!(anim.advance(&STATE, 0.0), vec![frame_event(2)]);
+
+        anim.seek(&STATE, 0, 5.0);
+        assert_eq!(anim.frame_timer, 1.0);
+        assert_eq!(sequence(&mut anim, 0.0, 1), vec![1]);
+    }
+
+    #[test]
+    fn every_frame_crossed_reports_its_events_once() {
+        let mut anim = component(clip(3, PlaybackMode::Forward, true, true));
+        assert_eq!(anim.advance(&STATE, 0.5), vec![frame_event(0)]);
+        assert_eq!(
+            anim.advance(&STATE, 2.0),
+            vec![frame_event(1), frame_event(2)]
+        );
+        assert_eq!(anim.advance(&STATE, 0.1), vec![]);
+
+        // whole loops inside one step report every frame each time it is entered
+        assert_eq!(
+            anim.advance(&STATE, 3.0),
+            vec![frame_event(0), frame_event(1), frame_event(2)]
+        );
+    }
+
+    #[test]
+    fn finished_fires_once_for_clips_played_once() {
+        for mode in [PlaybackMode::Forward, PlaybackMode::PingPong] {
+            let mut anim = component(clip(3, mode, false, true));
+            let events = anim.advance(&STATE, 100.0);
+            assert_eq!(finished_count(&events), 1, "{:?}", mode);
+            assert_eq!(events.last(), Some(&AnimationEvent::Finished));
+
+            let later: Vec<AnimationEvent> =
+                (0..5).flat_map(|_| anim.advance(&STATE, 1.0)).collect();
+            assert_eq!(later, vec![], "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn looping_clips_never_finish() {
+        for mode in [
+            PlaybackMode::Forward,
+            PlaybackMode::Reverse,
+            PlaybackMode::PingPong,
+            PlaybackMode::PingPongReverse,
+        ] {
+            let mut anim = component(clip(3, mode, true, false));
+            let events: Vec<AnimationEvent> =
+                (0..20).flat_map(|_| anim.advance(&STATE, 1.7)).collect();
+            assert_eq!(finished_count(&events), 0, "{:?}", mode);
+            assert!(!anim.finished);
+        }
+    }
 }
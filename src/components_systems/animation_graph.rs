@@ -0,0 +1,327 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use crate::{
+    components_systems::{set_entity_state, ActionState, Entity},
+    world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Equal,
+    NotEqual,
+}
+
+impl CompareOp {
+    fn from_str(op: &str) -> Option<Self> {
+        match op {
+            ">" => Some(CompareOp::Greater),
+            ">=" => Some(CompareOp::GreaterEq),
+            "<" => Some(CompareOp::Less),
+            "<=" => Some(CompareOp::LessEq),
+            "==" => Some(CompareOp::Equal),
+            "~=" | "!=" => Some(CompareOp::NotEqual),
+            _ => None,
+        }
+    }
+
+    fn test(&self, a: f32, b: f32) -> bool {
+        match self {
+            CompareOp::Greater => a > b,
+            CompareOp::GreaterEq => a >= b,
+            CompareOp::Less => a < b,
+            CompareOp::LessEq => a <= b,
+            CompareOp::Equal => a == b,
+            CompareOp::NotEqual => a != b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Compare {
+        param: String,
+        op: CompareOp,
+        value: f32,
+    },
+    Trigger(String), // set for a single update by `fire_trigger`
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GraphState {
+    pub speed: f32,              // playback speed multiplier of the clip
+    pub then_state: Option<u8>,  // play once, then go here when the clip finishes
+    pub interrupt_priority: i32, // transitions need at least this priority to cut the clip short
+}
+
+impl Default for GraphState {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            then_state: None,
+            interrupt_priority: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub from: Option<u8>, // None matches any state
+    pub to: u8,
+    pub conditions: Vec<Condition>, // all must hold
+    pub priority: i32,
+    pub wait_for_finish: bool, // only leave once the current clip has finished
+    pub keep_frame: bool,      // continue at the same frame index instead of restarting the clip
+}
+
+/// Per-entity animation state machine. The current state stays in the entity's
+/// `ActionStateComponent`, so `set_state` from Lua still forces a state directly.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationGraph {
+    pub states: HashMap<u8, GraphState>,
+    pub transitions: Vec<Transition>,
+    params: HashMap<String, f32>,
+    triggers: HashSet<String>,
+}
+
+impl AnimationGraph {
+    /// ```lua
+    /// {
+    ///   states = { [Dashing] = { speed = 1.5, then_state = Idle, interrupt_priority = 10 } },
+    ///   transitions = {
+    ///     { from = Idle, to = Running, when = { { "moving", "==", true } } },
+    ///     { to = Dying, when = { "die" }, priority = 100 }, -- a plain string is a trigger
+    ///   },
+    /// }
+    /// ```
+    pub fn from_lua_table(table: mlua::Table) -> Self {
+        let mut graph = AnimationGraph::default();
+
+        if let Ok(states) = table.get::<mlua::Table>("states") {
+            for (state, tbl) in states.pairs::<u8, mlua::Table>().flatten() {
+                graph.states.insert(
+                    state,
+                    GraphState {
+                        speed: tbl.get("speed").unwrap_or(1.0),
+                        then_state: tbl.get("then_state").ok(),
+                        interrupt_priority: tbl.get("interrupt_priority").unwrap_or(0),
+                    },
+                );
+            }
+        }
+
+        if let Ok(transitions) = table.get::<mlua::Table>("transitions") {
+            for tbl in transitions.sequence_values::<mlua::Table>().flatten() {
+                let to: u8 = match tbl.get("to") {
+                    Ok(to) => to,
+                    Err(_) => {
+                        println!("Animation graph transition without a 'to' state, skipped");
+                        continue;
+                    }
+                };
+                let conditions = match tbl.get::<mlua::Table>("when") {
+                    Ok(when) => parse_conditions(when),
+                    Err(_) => Vec::new(),
+                };
+                graph.transitions.push(Transition {
+                    from: tbl.get("from").ok(),
+                    to,
+                    conditions,
+                    priority: tbl.get("priority").unwrap_or(0),
+                    wait_for_finish: tbl.get("wait_for_finish").unwrap_or(false),
+                    keep_frame: tbl.get("keep_frame").unwrap_or(false),
+                });
+            }
+        }
+        // highest priority first, declaration order breaks ties
+        graph
+            .transitions
+            .sort_by_key(|transition| Reverse(transition.priority));
+        graph
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) {
+        self.params.insert(name.to_string(), value);
+    }
+
+    pub fn fire_trigger(&mut self, name: &str) {
+        self.triggers.insert(name.to_string());
+    }
+
+    pub fn speed(&self, state: &ActionState) -> f32 {
+        let ActionState::Custom(id) = state;
+        self.states.get(id).map(|s| s.speed).unwrap_or(1.0)
+    }
+
+    fn holds(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Compare { param, op, value } => {
+                op.test(self.params.get(param).copied().unwrap_or(0.0), *value)
+            }
+            Condition::Trigger(name) => self.triggers.contains(name),
+        }
+    }
+
+    /// Picks the state to move to, if any, and whether to keep the frame getting there.
+    /// A finished play-once state only falls back to its `then_state` when no transition
+    /// matches, so a trigger fired on the clip's last frame still wins.
+    fn next(&self, current: u8, finished: bool) -> Option<(u8, bool)> {
+        let state = self.states.get(&current).copied().unwrap_or_default();
+        let transition = self
+            .transitions
+            .iter()
+            .filter(|t| t.to != current && t.from.is_none_or(|from| from == current))
+            .filter(|t| finished || (!t.wait_for_finish && t.priority >= state.interrupt_priority))
+            .find(|t| t.conditions.iter().all(|c| self.holds(c)))
+            .map(|t| (t.to, t.keep_frame));
+
+        match (transition, state.then_state) {
+            (Some(transition), _) => Some(transition),
+            (None, Some(then_state)) if finished => Some((then_state, false)),
+            _ => None,
+        }
+    }
+}
+
+fn parse_conditions(when: mlua::Table) -> Vec<Condition> {
+    let mut conditions = Vec::new();
+    for value in when.sequence_values::<mlua::Value>().flatten() {
+        match value {
+            mlua::Value::String(name) => {
+                conditions.push(Condition::Trigger(name.to_string_lossy()));
+            }
+            mlua::Value::Table(tbl) => {
+                let param: String = tbl.get(1).unwrap_or_default();
+                let op: String = tbl.get(2).unwrap_or_default();
+                let value = match tbl.get::<mlua::Value>(3) {
+                    Ok(mlua::Value::Boolean(b)) => b as i32 as f32,
+                    Ok(other) => other.as_f32().unwrap_or(0.0),
+                    Err(_) => 0.0,
+                };
+                match CompareOp::from_str(&op) {
+                    Some(op) => conditions.push(Condition::Compare { param, op, value }),
+                    None => println!("Unknown animation graph comparison '{}'", op),
+                }
+            }
+            _ => println!("Animation graph conditions are trigger names or {{ param, op, value }}"),
+        }
+    }
+    conditions
+}
+
+/// Runs after the frames have advanced so "play once" states see their clip finish this frame.
+/// Triggers only live for one update, whether a transition consumed them or not.
+pub fn animation_graph_system_update(world: &mut World) {
+    let mut changes: Vec<(Entity, u8, bool)> = Vec::new();
+    for (entity, graph) in world.animation_graphs.iter_mut() {
+        if let Some(action_state) = world.action_states.get(entity) {
+            let ActionState::Custom(current) = action_state.state;
+            let finished = world
+                .animations
+                .get(entity)
+                .is_some_and(|animation| animation.finished);
+            if let Some((to, keep_frame)) = graph.next(current, finished) {
+                changes.push((*entity, to, keep_frame));
+            }
+        }
+        graph.triggers.clear();
+    }
+
+    for (entity, to, keep_frame) in changes {
+        let state = ActionState::from(to);
+        let frame = world
+            .animations
+            .get(&entity)
+            .map(|animation| (animation.current_frame_index, animation.frame_timer));
+        set_entity_state(world, entity, state.clone());
+
+        if let (true, Some((index, timer)), Some(animation)) =
+            (keep_frame, frame, world.animations.get_mut(&entity))
+        {
+            animation.seek(&state, index, timer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: u8 = 0;
+    const ATTACK: u8 = 1;
+    const DYING: u8 = 2;
+    const RUNNING: u8 = 3;
+
+    // attacking plays once back to idle and can only be cut short by dying
+    fn graph() -> AnimationGraph {
+        let mut graph = AnimationGraph::default();
+        graph.states.insert(
+            ATTACK,
+            GraphState {
+                then_state: Some(IDLE),
+                interrupt_priority: 50,
+                ..GraphState::default()
+            },
+        );
+        graph.transitions = vec![
+            Transition {
+                from: None,
+                to: DYING,
+                conditions: vec![Condition::Trigger("die".to_string())],
+                priority: 100,
+                wait_for_finish: false,
+                keep_frame: false,
+            },
+            Transition {
+                from: Some(IDLE),
+                to: RUNNING,
+                conditions: vec![Condition::Compare {
+                    param: "speed".to_string(),
+                    op: CompareOp::Greater,
+                    value: 0.1,
+                }],
+                priority: 0,
+                wait_for_finish: false,
+                keep_frame: true,
+            },
+        ];
+        graph
+    }
+
+    #[test]
+    fn conditions_move_between_states() {
+        let mut graph = graph();
+        assert_eq!(graph.next(IDLE, false), None);
+        graph.set_param("speed", 1.0);
+        assert_eq!(graph.next(IDLE, false), Some((RUNNING, true)));
+    }
+
+    #[test]
+    fn play_once_states_go_to_then_state_when_finished() {
+        let graph = graph();
+        assert_eq!(graph.next(ATTACK, false), None);
+        assert_eq!(graph.next(ATTACK, true), Some((IDLE, false)));
+    }
+
+    #[test]
+    fn interrupt_priority_blocks_weaker_transitions() {
+        let mut graph = graph();
+        graph.transitions[0].priority = 10;
+        graph.fire_trigger("die");
+        assert_eq!(graph.next(ATTACK, false), None);
+        assert_eq!(graph.next(ATTACK, true), Some((DYING, false)));
+    }
+
+    #[test]
+    fn trigger_fired_as_a_play_once_clip_finishes_is_not_lost() {
+        let mut graph = graph();
+        graph.fire_trigger("die");
+        assert_eq!(graph.next(ATTACK, true), Some((DYING, false)));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use crate::{components_systems::Entity, world::World};
+
+// slower than this keeps the last facing so bodies don't snap around while stopping
+const FACING_MIN_SPEED: f32 = 0.1;
+
+/// Compass facing of a sprite, +y is north like the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Facing {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    #[default]
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+const COMPASS: [Facing; 8] = [
+    Facing::East,
+    Facing::NorthEast,
+    Facing::North,
+    Facing::NorthWest,
+    Facing::West,
+    Facing::SouthWest,
+    Facing::South,
+    Facing::SouthEast,
+];
+
+impl Facing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "n" | "north" | "up" => Some(Facing::North),
+            "ne" | "northeast" | "up_right" => Some(Facing::NorthEast),
+            "e" | "east" | "right" => Some(Facing::East),
+            "se" | "southeast" | "down_right" => Some(Facing::SouthEast),
+            "s" | "south" | "down" => Some(Facing::South),
+            "sw" | "southwest" | "down_left" => Some(Facing::SouthWest),
+            "w" | "west" | "left" => Some(Facing::West),
+            "nw" | "northwest" | "up_left" => Some(Facing::NorthWest),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Facing::North => "n",
+            Facing::NorthEast => "ne",
+            Facing::East => "e",
+            Facing::SouthEast => "se",
+            Facing::South => "s",
+            Facing::SouthWest => "sw",
+            Facing::West => "w",
+            Facing::NorthWest => "nw",
+        }
+    }
+
+    /// Snaps a direction to the closest of 4 or 8 facings.
+    pub fn from_vector(x: f32, y: f32, directions: u8) -> Self {
+        let turn = y.atan2(x).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+        if directions == 4 {
+            // E N W S, every other compass point
+            let index = (turn * 4.0).round() as usize % 4;
+            COMPASS[index * 2]
+        } else {
+            COMPASS[(turn * 8.0).round() as usize % 8]
+        }
+    }
+
+    /// This facing, then the closest ones to use when a sheet doesn't have it.
+    /// Diagonals prefer the horizontal side so walking sideways reads right.
+    pub fn fallbacks(&self) -> [Facing; 3] {
+        match self {
+            Facing::NorthEast => [Facing::NorthEast, Facing::East, Facing::North],
+            Facing::SouthEast => [Facing::SouthEast, Facing::East, Facing::South],
+            Facing::SouthWest => [Facing::SouthWest, Facing::West, Facing::South],
+            Facing::NorthWest => [Facing::NorthWest, Facing::West, Facing::North],
+            other => [*other, *other, *other],
+        }
+    }
+
+    /// Every facing, closest first around the compass, counter-clockwise breaking ties.
+    /// The last resort when a sheet has none of the `fallbacks`.
+    pub fn closest(&self) -> impl Iterator<Item = Facing> {
+        let start = COMPASS
+            .iter()
+            .position(|facing| facing == self)
+            .unwrap_or(0);
+        [0, 1, 7, 2, 6, 3, 5, 4]
+            .into_iter()
+            .map(move |step| COMPASS[(start + step) % 8])
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FacingComponent {
+    pub facing: Facing,
+    pub directions: u8,      // 4 or 8
+    pub from_velocity: bool, // off when Lua sets the facing itself
+}
+
+/// Turns bodies toward their velocity and hands the facing to their animations.
+pub fn facing_system_update(world: &mut World, velocities: &HashMap<Entity, [f32; 2]>) {
+    for (entity, facing) in world.facings.iter_mut() {
+        if facing.from_velocity {
+            if let Some([x, y]) = velocities.get(entity) {
+                if (x * x + y * y).sqrt() > FACING_MIN_SPEED {
+                    facing.facing = Facing::from_vector(*x, *y, facing.directions);
+                }
+            }
+        }
+
+        if let (Some(animation), Some(action_state)) = (
+            world.animations.get_mut(entity),
+            world.action_states.get(entity),
+        ) {
+            animation.set_facing(&action_state.state, facing.facing);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_walks_out_from_the_facing() {
+        let order: Vec<Facing> = Facing::North.closest().collect();
+        assert_eq!(
+            order,
+            vec![
+                Facing::North,
+                Facing::NorthWest,
+                Facing::NorthEast,
+                Facing::West,
+                Facing::East,
+                Facing::SouthWest,
+                Facing::SouthEast,
+                Facing::South,
+            ]
+        );
+    }
+
+    #[test]
+    fn from_vector_snaps_to_4_or_8_directions() {
+        assert_eq!(Facing::from_vector(1.0, 1.0, 8), Facing::NorthEast);
+        assert_eq!(Facing::from_vector(1.0, 0.9, 4), Facing::East);
+        assert_eq!(Facing::from_vector(0.0, -1.0, 4), Facing::South);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{components_systems::Entity, world::World};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Point,
+    Spot {
+        direction: [f32; 2], // normalized
+        inner_angle: f32,    // degrees either side of `direction` at full strength
+        outer_angle: f32,    // degrees either side where the cone has faded out
+    },
+}
+
+/// A 2D light, drawn additively on top of the world's ambient light.
+#[derive(Debug, Clone)]
+pub struct Light2D {
+    pub position: [f32; 2], // world position, or the offset from `follow`
+    pub follow: Option<Entity>,
+    pub kind: LightKind,
+    pub radius: f32,
+    pub falloff: f32, // exponent of the fade toward the radius, 1 = linear
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub height: f32, // above the ground, only matters for normal mapped sprites
+    pub casts_shadows: bool,
+    pub enabled: bool,
+    origin: [f32; 2], // resolved world position for this frame
+}
+
+impl Default for Light2D {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            follow: None,
+            kind: LightKind::Point,
+            radius: 5.0,
+            falloff: 2.0,
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            height: 1.0,
+            casts_shadows: true,
+            enabled: true,
+            origin: [0.0, 0.0],
+        }
+    }
+}
+
+impl Light2D {
+    /// ```lua
+    /// {
+    ///   x = 0, y = 0, follow = entity,
+    ///   radius = 6, falloff = 2, color = { 1, 0.8, 0.5 }, intensity = 1.2, height = 1,
+    ///   shadows = true,
+    ///   -- a spot light when `direction` (degrees, 0 = right, 90 = up) is set
+    ///   direction = 90, inner_angle = 20, outer_angle = 35,
+    /// }
+    /// ```
+    pub fn from_lua_table(table: &mlua::Table) -> Self {
+        let default = Self::default();
+        let position = [table.get("x").unwrap_or(0.0), table.get("y").unwrap_or(0.0)];
+
+        let kind = match table.get::<f32>("direction") {
+            Ok(degrees) => {
+                let outer_angle: f32 = table.get("outer_angle").unwrap_or(30.0);
+                LightKind::Spot {
+                    direction: [degrees.to_radians().cos(), degrees.to_radians().sin()],
+                    inner_angle: table
+                        .get::<f32>("inner_angle")
+                        .unwrap_or(outer_angle * 0.5)
+                        .min(outer_angle),
+                    outer_angle,
+                }
+            }
+            Err(_) => LightKind::Point,
+        };
+
+        let color = match table.get::<mlua::Table>("color") {
+            Ok(color) => [
+                color.get(1).unwrap_or(1.0),
+                color.get(2).unwrap_or(1.0),
+                color.get(3).unwrap_or(1.0),
+            ],
+            Err(_) => default.color,
+        };
+
+        Self {
+            position,
+            follow: table.get("follow").ok(),
+            kind,
+            radius: table.get("radius").unwrap_or(default.radius),
+            falloff: table.get("falloff").unwrap_or(default.falloff),
+            color,
+            intensity: table.get("intensity").unwrap_or(default.intensity),
+            height: table.get("height").unwrap_or(default.height),
+            casts_shadows: table.get("shadows").unwrap_or(default.casts_shadows),
+            enabled: true,
+            origin: position,
+        }
+    }
+
+    /// Where the light is this frame, after following its entity.
+    pub fn origin(&self) -> [f32; 2] {
+        self.origin
+    }
+
+    /// `[cos outer, cos inner]` of the cone, point lights use a cone that covers everything.
+    pub fn cone(&self) -> [f32; 2] {
+        match self.kind {
+            LightKind::Point => [-2.0, -1.0],
+            LightKind::Spot {
+                inner_angle,
+                outer_angle,
+                ..
+            } => [
+                outer_angle.to_radians().cos(),
+                inner_angle.to_radians().cos(),
+            ],
+        }
+    }
+
+    pub fn direction(&self) -> [f32; 2] {
+        match self.kind {
+            LightKind::Point => [1.0, 0.0],
+            LightKind::Spot { direction, .. } => direction,
+        }
+    }
+}
+
+/// Moves lights onto the entities they follow.
+/// Lights whose entity is gone stay where it was last seen.
+pub fn light_system_update(world: &mut World) {
+    for light in world.lights.values_mut() {
+        light.origin = match light.follow {
+            Some(target) => match world.transforms_2d.get(&target) {
+                Some(transform) => [
+                    transform.position.x + light.position[0],
+                    transform.position.y + light.position[1],
+                ],
+                None => light.origin,
+            },
+            None => light.position,
+        };
+    }
+}
@@ -0,0 +1,111 @@
+pub const MATERIAL_PARAM_SLOTS: usize = 8;
+
+/// One vec4 per named parameter, in the order the material declared them.
+pub type MaterialParams = [[f32; 4]; MATERIAL_PARAM_SLOTS];
+
+/// A sprite shader registered from Lua, with the parameter values every entity using it
+/// starts from.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub param_names: Vec<String>, // a name's index is its slot in `material.params`
+    pub params: MaterialParams,
+    pub texture: Option<String>, // asset path, bound as `t_material`
+}
+
+impl Material {
+    /// ```lua
+    /// {
+    ///   params = { "amount", "edge_color" }, -- slots 0 and 1 of `material.params`
+    ///   amount = 0, edge_color = { 1, 0.5, 0.2, 1 }, -- starting values, 0 when left out
+    ///   texture = "materials/noise.png",
+    /// }
+    /// ```
+    pub fn from_lua_table(table: Option<&mlua::Table>) -> Self {
+        let mut material = Self {
+            param_names: Vec::new(),
+            params: [[0.0; 4]; MATERIAL_PARAM_SLOTS],
+            texture: None,
+        };
+        let Some(table) = table else {
+            return material;
+        };
+        material.texture = table.get("texture").ok();
+        material.param_names = table
+            .get::<Vec<String>>("params")
+            .unwrap_or_default()
+            .into_iter()
+            .take(MATERIAL_PARAM_SLOTS)
+            .collect();
+        for slot in 0..material.param_names.len() {
+            let value = table.get(material.param_names[slot].as_str());
+            if let Some(value) = value.ok().and_then(param_value) {
+                material.params[slot] = value;
+            }
+        }
+        material
+    }
+
+    pub fn slot(&self, param: &str) -> Option<usize> {
+        self.param_names.iter().position(|name| name == param)
+    }
+}
+
+/// Reads a number as `[n, 0, 0, 0]` and a list as up to four components.
+pub fn param_value(value: mlua::Value) -> Option<[f32; 4]> {
+    match value {
+        mlua::Value::Integer(n) => Some([n as f32, 0.0, 0.0, 0.0]),
+        mlua::Value::Number(n) => Some([n as f32, 0.0, 0.0, 0.0]),
+        mlua::Value::Table(table) => {
+            let mut components = [0.0; 4];
+            for (index, component) in components.iter_mut().enumerate() {
+                *component = table.get(index + 1).unwrap_or(0.0);
+            }
+            Some(components)
+        }
+        _ => None,
+    }
+}
+
+/// Draws an entity with a registered material instead of the default sprite shader.
+#[derive(Debug, Clone)]
+pub struct MaterialComponent {
+    pub material: String,
+    overrides: [Option<[f32; 4]>; MATERIAL_PARAM_SLOTS], // this entity's own values
+}
+
+impl MaterialComponent {
+    pub fn new(material: String) -> Self {
+        Self {
+            material,
+            overrides: [None; MATERIAL_PARAM_SLOTS],
+        }
+    }
+
+    pub fn set_param(&mut self, slot: usize, value: [f32; 4]) {
+        self.overrides[slot] = Some(value);
+    }
+
+    /// The material's values with this entity's overrides on top.
+    pub fn resolve(&self, material: &Material) -> ResolvedMaterial {
+        let mut params = material.params;
+        for (slot, value) in self.overrides.iter().enumerate() {
+            if let Some(value) = value {
+                params[slot] = *value;
+            }
+        }
+        ResolvedMaterial {
+            name: self.material.clone(),
+            params,
+            texture: material.texture.clone(),
+        }
+    }
+}
+
+/// What the renderer needs to draw one sprite with a material.
+/// Sprites that resolve to the same values share a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedMaterial {
+    pub name: String,
+    pub params: MaterialParams,
+    pub texture: Option<String>,
+}
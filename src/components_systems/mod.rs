@@ -1,7 +1,15 @@
 mod action_state;
 mod animation;
+mod animation_graph;
 mod entity;
+mod facing;
 mod health;
+mod light;
+mod material;
+mod particles;
+mod post_effects;
+mod render_layer;
+mod sprite_color;
 mod sprite_sheet;
 
 pub mod physics2d;
@@ -9,7 +17,17 @@ pub mod physics_2d;
 pub mod physics_3d;
 
 pub use action_state::{set_entity_state, ActionState, ActionStateComponent};
-pub use animation::{animation_system_update_frames, Animation, AnimationComponent, SpriteFrame};
+pub use animation::{
+    animation_system_update_frames, Animation, AnimationComponent, AnimationEvent, SpriteFrame,
+};
+pub use animation_graph::{animation_graph_system_update, AnimationGraph};
 pub use entity::Entity;
+pub use facing::{facing_system_update, Facing, FacingComponent};
 pub use health::{damage, HealthComponent};
+pub use light::{light_system_update, Light2D, LightKind};
+pub use material::{param_value, Material, MaterialComponent, MaterialParams, ResolvedMaterial};
+pub use particles::{particle_system_update, ParticleEmitter, ParticleEmitterConfig};
+pub use post_effects::{post_effect_system_update, PostEffect, PostEffectKind};
+pub use render_layer::{RenderLayer, RenderLayerComponent, SortMode};
+pub use sprite_color::SpriteColorComponent;
 pub use sprite_sheet::SpriteSheetComponent;
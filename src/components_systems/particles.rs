@@ -0,0 +1,520 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{
+    components_systems::{Entity, RenderLayer},
+    world::World,
+};
+
+const DEFAULT_MAX_PARTICLES: usize = 512;
+
+/// How a particle texture is split into frames, played once over each particle's life.
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleFrames {
+    pub columns: u32,
+    pub rows: u32,
+    pub count: u32,
+}
+
+impl ParticleFrames {
+    /// `[u0, v0, u1, v1]` of `frame`, laid out left to right then top to bottom like sprite sheets.
+    pub fn uv_rect(&self, frame: u32) -> [f32; 4] {
+        let columns = self.columns.max(1);
+        let rows = self.rows.max(1);
+        let (x, y) = ((frame % columns) as f32, (frame / columns) as f32);
+        let (w, h) = (columns as f32, rows as f32);
+        [x / w, 1.0 - (y + 1.0) / h, (x + 1.0) / w, 1.0 - y / h]
+    }
+}
+
+/// Everything Lua can set on an emitter, see `from_lua_table` for the field names.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitterConfig {
+    pub texture: String,
+    pub frames: ParticleFrames,
+    pub layer: RenderLayer,
+    pub rate: f32,             // particles per second while emitting
+    pub burst: u32,            // spawned at once when the emitter starts
+    pub duration: Option<f32>, // stops emitting after this many seconds, None runs until stopped
+    // removed once it has stopped and its particles are gone, without a duration it only bursts
+    pub one_shot: bool,
+    pub max_particles: usize,
+    pub lifetime: [f32; 2], // min, max seconds
+    pub speed: [f32; 2],
+    pub direction: f32, // degrees, 0 = right, 90 = up
+    pub spread: f32,    // degrees either side of `direction`
+    pub spawn_radius: f32,
+    pub gravity: [f32; 2],
+    pub drag: f32,             // fraction of velocity lost per second
+    pub spin: [f32; 2],        // degrees per second
+    pub sizes: Vec<f32>,       // over lifetime, evenly spaced keys
+    pub colors: Vec<[f32; 4]>, // over lifetime, evenly spaced keys
+}
+
+impl Default for ParticleEmitterConfig {
+    fn default() -> Self {
+        Self {
+            texture: "particles/dot.png".to_string(),
+            frames: ParticleFrames {
+                columns: 1,
+                rows: 1,
+                count: 1,
+            },
+            layer: RenderLayer::Effects,
+            rate: 0.0,
+            burst: 0,
+            duration: None,
+            one_shot: false,
+            max_particles: DEFAULT_MAX_PARTICLES,
+            lifetime: [1.0, 1.0],
+            speed: [1.0, 1.0],
+            direction: 90.0,
+            spread: 180.0,
+            spawn_radius: 0.0,
+            gravity: [0.0, 0.0],
+            drag: 0.0,
+            spin: [0.0, 0.0],
+            sizes: vec![0.25],
+            colors: vec![[1.0, 1.0, 1.0, 1.0]],
+        }
+    }
+}
+
+// `{ min, max }` or a single number for both
+fn range_from_lua(table: &mlua::Table, key: &str, default: [f32; 2]) -> [f32; 2] {
+    match table.get::<mlua::Value>(key) {
+        Ok(mlua::Value::Table(range)) => [
+            range.get(1).unwrap_or(default[0]),
+            range.get(2).unwrap_or(default[1]),
+        ],
+        Ok(value) => value.as_f32().map(|v| [v, v]).unwrap_or(default),
+        Err(_) => default,
+    }
+}
+
+impl ParticleEmitterConfig {
+    /// ```lua
+    /// {
+    ///   texture = "particles/dot.png", frames = { columns = 4, rows = 1, count = 4 },
+    ///   layer = GLOBALS.RENDER_LAYERS.Effects,
+    ///   rate = 20, burst = 10, duration = 0.5, one_shot = true, max_particles = 512,
+    ///   lifetime = { 0.3, 0.6 }, speed = { 2, 5 }, direction = 90, spread = 30,
+    ///   spawn_radius = 0.2, gravity = { 0, -9.8 }, drag = 1.5, spin = { -90, 90 },
+    ///   sizes = { 0.3, 0.1 }, colors = { { r = 1, g = 1, b = 1, a = 1 }, { r = 1, g = 1, b = 1, a = 0 } },
+    /// }
+    /// ```
+    pub fn from_lua_table(table: &mlua::Table) -> Self {
+        let defaults = Self::default();
+        let frames = match table.get::<mlua::Table>("frames") {
+            Ok(frames) => ParticleFrames {
+                columns: frames.get("columns").unwrap_or(1),
+                rows: frames.get("rows").unwrap_or(1),
+                count: frames.get("count").unwrap_or(1),
+            },
+            Err(_) => defaults.frames,
+        };
+        let gravity = match table.get::<mlua::Table>("gravity") {
+            Ok(gravity) => [gravity.get(1).unwrap_or(0.0), gravity.get(2).unwrap_or(0.0)],
+            Err(_) => defaults.gravity,
+        };
+        let sizes: Vec<f32> = table
+            .get::<Vec<f32>>("sizes")
+            .ok()
+            .filter(|sizes| !sizes.is_empty())
+            .unwrap_or(defaults.sizes);
+        let colors: Vec<[f32; 4]> = match table.get::<mlua::Table>("colors") {
+            Ok(colors) => colors
+                .sequence_values::<mlua::Table>()
+                .flatten()
+                .map(|c| {
+                    [
+                        c.get("r").unwrap_or(1.0),
+                        c.get("g").unwrap_or(1.0),
+                        c.get("b").unwrap_or(1.0),
+                        c.get("a").unwrap_or(1.0),
+                    ]
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            texture: table.get("texture").unwrap_or(defaults.texture),
+            frames,
+            layer: RenderLayer::from(table.get("layer").unwrap_or(RenderLayer::Effects as u8)),
+            rate: table.get("rate").unwrap_or(defaults.rate),
+            burst: table.get("burst").unwrap_or(defaults.burst),
+            duration: table.get("duration").ok(),
+            one_shot: table.get("one_shot").unwrap_or(defaults.one_shot),
+            max_particles: table.get("max_particles").unwrap_or(defaults.max_particles),
+            lifetime: range_from_lua(table, "lifetime", defaults.lifetime),
+            speed: range_from_lua(table, "speed", defaults.speed),
+            direction: table.get("direction").unwrap_or(defaults.direction),
+            spread: table.get("spread").unwrap_or(defaults.spread),
+            spawn_radius: table.get("spawn_radius").unwrap_or(defaults.spawn_radius),
+            gravity,
+            drag: table.get("drag").unwrap_or(defaults.drag),
+            spin: range_from_lua(table, "spin", defaults.spin),
+            sizes,
+            colors: if colors.is_empty() {
+                defaults.colors
+            } else {
+                colors
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub age: f32,
+    pub lifetime: f32,
+    pub rotation: f32,         // radians
+    pub angular_velocity: f32, // radians per second
+}
+
+impl Particle {
+    /// 0 when spawned, 1 when it dies.
+    pub fn progress(&self) -> f32 {
+        if self.lifetime <= 0.0 {
+            1.0
+        } else {
+            (self.age / self.lifetime).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// A particle ready to draw, in world units.
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleInstance {
+    pub position: [f32; 2],
+    pub size: f32,
+    pub rotation: f32,
+    pub uv_rect: [f32; 4],
+    pub color: [f32; 4],
+}
+
+/// CPU simulated particles. Moves on its own, bodies in `PhysicsWorld` never see them.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub config: ParticleEmitterConfig,
+    pub position: [f32; 2],
+    pub follow: Option<Entity>, // use the entity's position plus `position` as an offset
+    pub emitting: bool,
+    pub particles: Vec<Particle>,
+    elapsed: f32,
+    spawn_accumulator: f32,
+    pending_burst: u32,
+    rng: SmallRng,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: ParticleEmitterConfig, position: [f32; 2], follow: Option<Entity>) -> Self {
+        Self::with_seed(config, position, follow, rand::rng().random())
+    }
+
+    /// Same seed, same particles, for replays and checking the simulation without a window.
+    pub fn with_seed(
+        config: ParticleEmitterConfig,
+        position: [f32; 2],
+        follow: Option<Entity>,
+        seed: u64,
+    ) -> Self {
+        let pending_burst = config.burst;
+        Self {
+            config,
+            position,
+            follow,
+            emitting: true,
+            particles: Vec::new(),
+            elapsed: 0.0,
+            spawn_accumulator: 0.0,
+            pending_burst,
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn burst(&mut self, count: u32) {
+        self.pending_burst += count;
+    }
+
+    /// Done emitting with nothing left on screen.
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.pending_burst == 0 && self.particles.is_empty()
+    }
+
+    fn random(&mut self, range: [f32; 2]) -> f32 {
+        if range[1] > range[0] {
+            self.rng.random_range(range[0]..range[1])
+        } else {
+            range[0]
+        }
+    }
+
+    fn spawn(&mut self, origin: [f32; 2]) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+        let spread = self.config.spread.abs();
+        let angle = (self.config.direction + self.random([-spread, spread])).to_radians();
+        let speed = self.random(self.config.speed);
+        let offset_angle = self.random([0.0, std::f32::consts::TAU]);
+        let offset = self.config.spawn_radius * self.random([0.0, 1.0]).sqrt();
+        let lifetime = self.random(self.config.lifetime);
+        let angular_velocity = self.random(self.config.spin).to_radians();
+        self.particles.push(Particle {
+            position: [
+                origin[0] + offset_angle.cos() * offset,
+                origin[1] + offset_angle.sin() * offset,
+            ],
+            velocity: [angle.cos() * speed, angle.sin() * speed],
+            age: 0.0,
+            lifetime,
+            rotation: 0.0,
+            angular_velocity,
+        });
+    }
+
+    /// Spawns, moves and ages particles. `origin` is where new ones appear.
+    pub fn update(&mut self, origin: [f32; 2], dt: f32) {
+        for _ in 0..std::mem::take(&mut self.pending_burst) {
+            self.spawn(origin);
+        }
+
+        if self.emitting {
+            self.elapsed += dt;
+            if self.config.rate > 0.0 {
+                self.spawn_accumulator += self.config.rate * dt;
+                while self.spawn_accumulator >= 1.0 {
+                    self.spawn_accumulator -= 1.0;
+                    self.spawn(origin);
+                }
+            }
+            // a one-shot without a duration is just its burst
+            let duration = match self.config.duration {
+                None if self.config.one_shot => Some(0.0),
+                duration => duration,
+            };
+            if duration.is_some_and(|d| self.elapsed >= d) {
+                self.emitting = false;
+            }
+        }
+
+        let gravity = self.config.gravity;
+        let damping = (1.0 - self.config.drag * dt).max(0.0);
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.velocity[0] = (particle.velocity[0] + gravity[0] * dt) * damping;
+            particle.velocity[1] = (particle.velocity[1] + gravity[1] * dt) * damping;
+            particle.position[0] += particle.velocity[0] * dt;
+            particle.position[1] += particle.velocity[1] * dt;
+            particle.rotation += particle.angular_velocity * dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    pub fn instances(&self) -> impl Iterator<Item = ParticleInstance> + '_ {
+        let frames = self.config.frames;
+        self.particles.iter().map(move |particle| {
+            let t = particle.progress();
+            let frame = ((t * frames.count as f32) as u32).min(frames.count.max(1) - 1);
+            ParticleInstance {
+                position: particle.position,
+                size: sample_keys(&self.config.sizes, t),
+                rotation: particle.rotation,
+                uv_rect: frames.uv_rect(frame),
+                color: sample_color_keys(&self.config.colors, t),
+            }
+        })
+    }
+}
+
+// linear between evenly spaced keys
+fn key_span(len: usize, t: f32) -> (usize, usize, f32) {
+    if len <= 1 {
+        return (0, 0, 0.0);
+    }
+    let scaled = t.clamp(0.0, 1.0) * (len - 1) as f32;
+    let index = (scaled as usize).min(len - 2);
+    (index, index + 1, scaled - index as f32)
+}
+
+fn sample_keys(keys: &[f32], t: f32) -> f32 {
+    let (a, b, f) = key_span(keys.len(), t);
+    keys.get(a).map_or(0.0, |&a| a + (keys[b] - a) * f)
+}
+
+fn sample_color_keys(keys: &[[f32; 4]], t: f32) -> [f32; 4] {
+    let (a, b, f) = key_span(keys.len(), t);
+    match keys.get(a) {
+        Some(a) => std::array::from_fn(|i| a[i] + (keys[b][i] - a[i]) * f),
+        None => [1.0, 1.0, 1.0, 1.0],
+    }
+}
+
+/// Moves every emitter to its followed entity, steps it, and drops finished one-shots.
+/// Emitters stop spawning when the entity they follow is gone.
+pub fn particle_system_update(world: &mut World, dt: f32) {
+    let mut finished = Vec::new();
+    for (entity, emitter) in world.particle_emitters.iter_mut() {
+        let origin = match emitter.follow {
+            Some(target) => match world.transforms_2d.get(&target) {
+                Some(transform) => [
+                    transform.position.x + emitter.position[0],
+                    transform.position.y + emitter.position[1],
+                ],
+                None => {
+                    emitter.emitting = false;
+                    emitter.position
+                }
+            },
+            None => emitter.position,
+        };
+        emitter.update(origin, dt);
+        if emitter.config.one_shot && emitter.is_finished() {
+            finished.push(*entity);
+        }
+    }
+    for entity in finished {
+        world.particle_emitters.remove(&entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn run(emitter: &mut ParticleEmitter, seconds: f32) {
+        for _ in 0..(seconds / DT).round() as usize {
+            emitter.update([0.0, 0.0], DT);
+        }
+    }
+
+    fn sparks() -> ParticleEmitterConfig {
+        ParticleEmitterConfig {
+            burst: 20,
+            lifetime: [0.2, 0.5],
+            speed: [1.0, 4.0],
+            spread: 45.0,
+            spawn_radius: 0.5,
+            spin: [-90.0, 90.0],
+            ..ParticleEmitterConfig::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_same_particles() {
+        let mut a = ParticleEmitter::with_seed(sparks(), [0.0, 0.0], None, 7);
+        let mut b = ParticleEmitter::with_seed(sparks(), [0.0, 0.0], None, 7);
+        let mut c = ParticleEmitter::with_seed(sparks(), [0.0, 0.0], None, 8);
+        run(&mut a, 0.1);
+        run(&mut b, 0.1);
+        run(&mut c, 0.1);
+
+        let positions = |e: &ParticleEmitter| -> Vec<[f32; 2]> {
+            e.particles.iter().map(|p| p.position).collect()
+        };
+        assert_eq!(positions(&a), positions(&b));
+        assert_ne!(positions(&a), positions(&c));
+    }
+
+    #[test]
+    fn burst_only_one_shot_finishes() {
+        let mut emitter = ParticleEmitter::with_seed(
+            ParticleEmitterConfig {
+                one_shot: true,
+                ..sparks()
+            },
+            [0.0, 0.0],
+            None,
+            1,
+        );
+        emitter.update([0.0, 0.0], DT);
+        assert_eq!(emitter.particles.len(), 20);
+        assert!(!emitter.emitting);
+        assert!(!emitter.is_finished());
+
+        run(&mut emitter, 0.5);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn emitters_without_one_shot_keep_running() {
+        let mut emitter = ParticleEmitter::with_seed(sparks(), [0.0, 0.0], None, 1);
+        run(&mut emitter, 1.0);
+        assert!(emitter.emitting);
+        assert!(!emitter.is_finished());
+    }
+
+    #[test]
+    fn rate_spawns_until_the_duration_ends() {
+        let mut emitter = ParticleEmitter::with_seed(
+            ParticleEmitterConfig {
+                rate: 60.0,
+                duration: Some(0.5),
+                lifetime: [10.0, 10.0],
+                ..ParticleEmitterConfig::default()
+            },
+            [0.0, 0.0],
+            None,
+            1,
+        );
+        run(&mut emitter, 1.0);
+        assert!(!emitter.emitting);
+        // one per frame for half a second, give or take the frame it stops on
+        assert!((29..=31).contains(&emitter.particles.len()));
+    }
+
+    #[test]
+    fn max_particles_caps_spawning() {
+        let mut emitter = ParticleEmitter::with_seed(
+            ParticleEmitterConfig {
+                burst: 100,
+                max_particles: 16,
+                ..ParticleEmitterConfig::default()
+            },
+            [0.0, 0.0],
+            None,
+            1,
+        );
+        emitter.update([0.0, 0.0], DT);
+        assert_eq!(emitter.particles.len(), 16);
+    }
+
+    #[test]
+    fn gravity_and_drag_shape_the_motion() {
+        let still = ParticleEmitterConfig {
+            burst: 1,
+            speed: [0.0, 0.0],
+            lifetime: [2.0, 2.0],
+            gravity: [0.0, -10.0],
+            ..ParticleEmitterConfig::default()
+        };
+        let mut falling = ParticleEmitter::with_seed(still.clone(), [0.0, 0.0], None, 1);
+        let mut dragged = ParticleEmitter::with_seed(
+            ParticleEmitterConfig { drag: 2.0, ..still },
+            [0.0, 0.0],
+            None,
+            1,
+        );
+        run(&mut falling, 1.0);
+        run(&mut dragged, 1.0);
+
+        let (falling, dragged) = (falling.particles[0], dragged.particles[0]);
+        assert!(falling.position[1] < -4.0);
+        assert!(dragged.position[1] < 0.0);
+        assert!(dragged.velocity[1] > falling.velocity[1]);
+        assert_eq!(falling.position[0], 0.0);
+    }
+
+    #[test]
+    fn keys_interpolate_over_the_lifetime() {
+        assert_eq!(sample_keys(&[1.0, 0.0], 0.25), 0.75);
+        assert_eq!(sample_keys(&[0.0, 1.0, 0.0], 0.75), 0.5);
+        assert_eq!(sample_keys(&[0.3], 0.9), 0.3);
+    }
+}
@@ -484,6 +484,21 @@ impl PhysicsWorld {
         self.entity_map.insert(entity, index);
     }
 
+    /// `(min, max)` of every static collider, these don't move so lights can cast shadows off them.
+    pub fn static_collider_bounds(&self) -> Vec<([f32; 2], [f32; 2])> {
+        self.bodies
+            .iter()
+            .filter(|body| body.is_active && body.body_type == BodyType2D::Static)
+            .flat_map(|body| body.aabbs.iter())
+            .map(|area| {
+                (
+                    [area.aabb.min.x, area.aabb.min.y],
+                    [area.aabb.max.x, area.aabb.max.y],
+                )
+            })
+            .collect()
+    }
+
     pub fn positions(&self) -> HashMap<Entity, Point2D> {
         self.entity_map
             .iter()
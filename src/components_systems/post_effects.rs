@@ -0,0 +1,198 @@
+use crate::world::World;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PostEffectKind {
+    ColorGrade,
+    Vignette,
+    Bloom,
+    Crt,
+    Flash,
+    ChromaticAberration,
+    Custom(String), // loaded from WGSL with `load_post_effect`
+}
+
+impl PostEffectKind {
+    pub const BUILT_IN: [PostEffectKind; 6] = [
+        PostEffectKind::ColorGrade,
+        PostEffectKind::Vignette,
+        PostEffectKind::Bloom,
+        PostEffectKind::Crt,
+        PostEffectKind::Flash,
+        PostEffectKind::ChromaticAberration,
+    ];
+
+    /// Names that aren't built in refer to custom effects.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "color_grade" => PostEffectKind::ColorGrade,
+            "vignette" => PostEffectKind::Vignette,
+            "bloom" => PostEffectKind::Bloom,
+            "crt" => PostEffectKind::Crt,
+            "flash" => PostEffectKind::Flash,
+            "chromatic_aberration" => PostEffectKind::ChromaticAberration,
+            other => PostEffectKind::Custom(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PostEffectKind::ColorGrade => "color_grade",
+            PostEffectKind::Vignette => "vignette",
+            PostEffectKind::Bloom => "bloom",
+            PostEffectKind::Crt => "crt",
+            PostEffectKind::Flash => "flash",
+            PostEffectKind::ChromaticAberration => "chromatic_aberration",
+            PostEffectKind::Custom(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StrengthFade {
+    from: f32,
+    to: f32,
+    duration: f32,
+    elapsed: f32,
+}
+
+/// One full-screen pass of the post-processing chain. Every effect gets `strength` and
+/// sixteen floats of `params`, built in effects name the slots they use in `apply_lua_table`.
+#[derive(Debug, Clone)]
+pub struct PostEffect {
+    pub kind: PostEffectKind,
+    pub enabled: bool,
+    pub strength: f32,
+    pub params: [[f32; 4]; 4],
+    pub texture: Option<String>, // asset path, the LUT of color grading
+    fade: Option<StrengthFade>,
+}
+
+impl PostEffect {
+    pub fn new(kind: PostEffectKind) -> Self {
+        let (strength, params) = match kind {
+            PostEffectKind::ColorGrade => (1.0, [[0.0; 4]; 4]),
+            // color, then radius and softness in screen half-diagonals
+            PostEffectKind::Vignette => (
+                0.5,
+                [
+                    [0.0, 0.0, 0.0, 0.0],
+                    [0.75, 0.45, 0.0, 0.0],
+                    [0.0; 4],
+                    [0.0; 4],
+                ],
+            ),
+            // threshold, radius in pixels
+            PostEffectKind::Bloom => (0.8, [[0.7, 6.0, 0.0, 0.0], [0.0; 4], [0.0; 4], [0.0; 4]]),
+            // curvature, scanline darkness, scanline height in pixels
+            PostEffectKind::Crt => (1.0, [[0.08, 0.3, 3.0, 0.0], [0.0; 4], [0.0; 4], [0.0; 4]]),
+            // color, strength is how far toward it the screen goes
+            PostEffectKind::Flash => (0.0, [[1.0, 1.0, 1.0, 1.0], [0.0; 4], [0.0; 4], [0.0; 4]]),
+            // offset at the screen edge in pixels
+            PostEffectKind::ChromaticAberration => {
+                (1.0, [[2.5, 0.0, 0.0, 0.0], [0.0; 4], [0.0; 4], [0.0; 4]])
+            }
+            PostEffectKind::Custom(_) => (1.0, [[0.0; 4]; 4]),
+        };
+        Self {
+            kind,
+            enabled: true,
+            strength,
+            params,
+            texture: None,
+            fade: None,
+        }
+    }
+
+    /// Every effect reads `enabled`, `strength` and `texture`. Custom effects take `params`,
+    /// a list of up to 16 numbers. Built in effects take named fields:
+    /// ```lua
+    /// color_grade          = { lut = "post/lut.png" } -- 16 slices of 16x16 side by side
+    /// vignette             = { color = { r, g, b }, radius = 0.75, softness = 0.45 }
+    /// bloom                = { threshold = 0.7, radius = 6 }
+    /// crt                  = { curvature = 0.08, scanlines = 0.3, line_height = 3 }
+    /// flash                = { color = { r, g, b } }
+    /// chromatic_aberration = { offset = 2.5 }
+    /// ```
+    pub fn apply_lua_table(&mut self, table: &mlua::Table) {
+        self.enabled = table.get("enabled").unwrap_or(self.enabled);
+        if let Ok(strength) = table.get::<f32>("strength") {
+            self.strength = strength;
+            self.fade = None;
+        }
+        if let Ok(texture) = table.get::<String>("texture") {
+            self.texture = Some(texture);
+        }
+
+        let mut set = |slot: usize, field: &str| {
+            if let Ok(value) = table.get::<f32>(field) {
+                self.params[slot / 4][slot % 4] = value;
+            }
+        };
+        match self.kind {
+            PostEffectKind::ColorGrade => {}
+            PostEffectKind::Vignette => {
+                set(4, "radius");
+                set(5, "softness");
+            }
+            PostEffectKind::Bloom => {
+                set(0, "threshold");
+                set(1, "radius");
+            }
+            PostEffectKind::Crt => {
+                set(0, "curvature");
+                set(1, "scanlines");
+                set(2, "line_height");
+            }
+            PostEffectKind::Flash => {}
+            PostEffectKind::ChromaticAberration => set(0, "offset"),
+            PostEffectKind::Custom(_) => {
+                if let Ok(params) = table.get::<Vec<f32>>("params") {
+                    for (slot, value) in params.into_iter().take(16).enumerate() {
+                        self.params[slot / 4][slot % 4] = value;
+                    }
+                }
+            }
+        }
+
+        let has_color = matches!(self.kind, PostEffectKind::Vignette | PostEffectKind::Flash);
+        if let (true, Ok(color)) = (has_color, table.get::<mlua::Table>("color")) {
+            for channel in 0..3 {
+                self.params[0][channel] = color.get(channel + 1).unwrap_or(self.params[0][channel]);
+            }
+        }
+        if self.kind == PostEffectKind::ColorGrade {
+            if let Ok(lut) = table.get::<String>("lut") {
+                self.texture = Some(lut);
+            }
+        }
+    }
+
+    /// Eases `strength` to `to` over `duration` seconds, a duration of 0 sets it at once.
+    pub fn fade_to(&mut self, to: f32, duration: f32) {
+        if duration <= 0.0 {
+            self.strength = to;
+            self.fade = None;
+        } else {
+            self.fade = Some(StrengthFade {
+                from: self.strength,
+                to,
+                duration,
+                elapsed: 0.0,
+            });
+        }
+    }
+}
+
+/// Advances strength fades. Runs on unscaled time so flashes still fade during hit stops.
+pub fn post_effect_system_update(world: &mut World, dt: f32) {
+    for effect in world.post_effects.iter_mut() {
+        if let Some(fade) = effect.fade.as_mut() {
+            fade.elapsed += dt;
+            let t = (fade.elapsed / fade.duration).min(1.0);
+            effect.strength = fade.from + (fade.to - fade.from) * t;
+            if t >= 1.0 {
+                effect.fade = None;
+            }
+        }
+    }
+}
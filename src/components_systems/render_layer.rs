@@ -0,0 +1,93 @@
+use crate::components_systems::Entity;
+
+/// Coarse draw buckets for the world pass. Layers are drawn back to front in declaration order;
+/// the layer's `SortMode` decides the order of elements within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Background,
+    Ground,
+    Actors,
+    Overhead,
+    Effects,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 5] = [
+        RenderLayer::Background,
+        RenderLayer::Ground,
+        RenderLayer::Actors,
+        RenderLayer::Overhead,
+        RenderLayer::Effects,
+    ];
+
+    pub fn default_sort_mode(&self) -> SortMode {
+        match self {
+            RenderLayer::Background | RenderLayer::Ground => SortMode::None,
+            RenderLayer::Actors | RenderLayer::Overhead => SortMode::YSort,
+            RenderLayer::Effects => SortMode::ExplicitZ,
+        }
+    }
+}
+
+impl From<u8> for RenderLayer {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => RenderLayer::Background,
+            1 => RenderLayer::Ground,
+            2 => RenderLayer::Actors,
+            3 => RenderLayer::Overhead,
+            4 => RenderLayer::Effects,
+            _ => RenderLayer::Actors,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    /// lower on screen draws later (in front); uses the pivot, not the sprite center
+    YSort,
+    /// larger z draws later
+    ExplicitZ,
+    /// creation order
+    None,
+}
+
+impl From<u8> for SortMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => SortMode::YSort,
+            1 => SortMode::ExplicitZ,
+            2 => SortMode::None,
+            _ => SortMode::YSort,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderLayerComponent {
+    pub layer: RenderLayer,
+    pub z: f32,
+    // offset from the transform position to the sprite's "feet" used for y-sorting
+    pub pivot_offset_y: f32,
+}
+
+impl Default for RenderLayerComponent {
+    fn default() -> Self {
+        Self {
+            layer: RenderLayer::Actors,
+            z: 0.0,
+            pivot_offset_y: 0.0,
+        }
+    }
+}
+
+impl RenderLayerComponent {
+    /// Key within the layer, ascending = drawn first.
+    pub fn sort_key(&self, mode: SortMode, position_y: f32, entity: Entity) -> f32 {
+        match mode {
+            SortMode::YSort => -(position_y + self.pivot_offset_y),
+            SortMode::ExplicitZ => self.z,
+            SortMode::None => entity as f32,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteColorComponent {
+    pub tint: [f32; 4],  // multiplied with the sampled texel
+    pub flash: [f32; 4], // rgb blended over the texel, a = blend amount (0 = off, 1 = solid)
+    pub opacity: f32,
+}
+
+impl Default for SpriteColorComponent {
+    fn default() -> Self {
+        Self {
+            tint: [1.0, 1.0, 1.0, 1.0],
+            flash: [0.0, 0.0, 0.0, 0.0],
+            opacity: 1.0,
+        }
+    }
+}
+
+impl SpriteColorComponent {
+    pub fn is_translucent(&self) -> bool {
+        self.opacity < 1.0 || self.tint[3] < 1.0
+    }
+}
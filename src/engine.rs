@@ -1,19 +1,35 @@
+use crate::audio::{Audio, Bus, PlayOptions};
 use crate::bitmaps::vecbool_to_u8;
 use crate::camera_2d::camera_2d::Camera2DConfig;
-use crate::camera_2d::Camera2D;
+use crate::camera_2d::{Camera2D, CameraId, CameraTarget, SplitLayout, MAIN_CAMERA};
 use crate::camera_3d::CameraAction;
 use crate::components_systems::physics2d::{self, PhysicsWorld, Point2D};
 use crate::components_systems::physics_2d::{FlipComponent, Shape2D, Transform2D};
 use crate::components_systems::{
-    animation_system_update_frames, damage, set_entity_state, ActionState, ActionStateComponent,
-    Animation, AnimationComponent, Entity, HealthComponent, SpriteSheetComponent,
+    animation_graph_system_update, animation_system_update_frames, damage, facing_system_update,
+    light_system_update, param_value, particle_system_update, post_effect_system_update,
+    set_entity_state, ActionState, ActionStateComponent, Animation, AnimationComponent,
+    AnimationEvent, AnimationGraph, Entity, Facing, FacingComponent, HealthComponent, Light2D,
+    LightKind, Material, MaterialComponent, ParticleEmitter, ParticleEmitterConfig, PostEffect,
+    PostEffectKind, RenderLayer, RenderLayerComponent, SortMode, SpriteSheetComponent,
 };
+use crate::frame_capture::{screenshot_path, FrameCapture};
+use crate::gamepad::{GamepadAxis, GamepadButton, GamepadInput, GamepadSignal, VirtualGamepad};
 use crate::graphics::Graphics;
-use crate::inputs::{keycode_to_str, mousebutton_to_str};
+use crate::graphics_2d::Space;
+use crate::input_map::{AxisBinding, InputMap};
+use crate::inputs::{keycode_to_str, modifiers_to_lua, mousebutton_to_str, str_to_keycode};
 use crate::lua_scriptor::LuaExtendedExecutor;
+use crate::palette::{
+    load_palette_rows, PaletteAtlas, PALETTE_ATLAS_TEXTURE, PALETTE_SWAP_MATERIAL,
+};
 use crate::scene::{Element, Scene};
+use crate::text::{
+    default_charset, layout_text, parse_bmfont, rasterize_ttf, text_color_from_lua, Font,
+    TextLayoutOptions, WorldText,
+};
 use crate::texture::Texture;
-use crate::ui_canvas::{parse_scene_from_lua, Canvas};
+use crate::ui_canvas::{parse_anchor, parse_scene_from_lua, Canvas, UiClick};
 use crate::world::World;
 use crate::{debug, graphics_2d, graphics_3d};
 use cgmath::Vector2;
@@ -22,21 +38,34 @@ use graphics_2d::Graphics2D;
 use graphics_3d::Graphics3D;
 use mlua::{Result, Table};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
-use winit::event::{KeyEvent, MouseButton, WindowEvent};
+use winit::event::{Ime, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow};
-use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
 use winit::window::Window;
 
 static SAFETY_MAX_FOR_DEV: u64 = 10000;
+// far from device ids so scripted input never lands on a real pad
+static VIRTUAL_GAMEPAD_ID: u32 = 1000;
+// pixel scroll deltas (touchpads) are reported to Lua in lines like wheel clicks
+static PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+// input contexts in which arrows, the d-pad and confirm drive canvas focus instead of the game
+static UI_INPUT_CONTEXTS: [&str; 2] = ["menu", "ui"];
 
 pub struct Engine {
     player: Entity,
     mouse_pos: [f32; 2], // TODO!!!
+    modifiers: ModifiersState,
+    text_input_active: bool,
+    gamepads: GamepadInput,
+    virtual_gamepad: VirtualGamepad,
+    input_map: InputMap,
+    audio: Audio,
     //
     physics_tick_rate: f32,
     physics_accumulator: f32,
@@ -51,6 +80,11 @@ pub struct Engine {
     last_frame: Instant,
     debugger: Debug,
     asset_cache: HashMap<String, Texture>,
+    split_cameras: Vec<CameraId>, // split-screen viewports after the main camera's
+    ui_scene_cameras: HashMap<Entity, CameraId>, // canvas scenes laid out in a camera's viewport
+    fonts: HashMap<String, Font>,
+    palettes: PaletteAtlas,
+    frame_capture: FrameCapture,
     lua_context: LuaExtendedExecutor,
     world: World,
     physics: PhysicsWorld,
@@ -104,8 +138,22 @@ impl Engine {
 
         let target_rate = fps_opt.map(|fps| Duration::from_millis(1000 / fps));
 
+        let virtual_gamepad = VirtualGamepad::new(VIRTUAL_GAMEPAD_ID);
+        let mut gamepads = GamepadInput::new();
+        gamepads.add_source(Box::new(virtual_gamepad.clone()));
+        #[cfg(feature = "gamepad")]
+        if let Some(source) = crate::gamepad::GilrsSource::new() {
+            gamepads.add_source(Box::new(source));
+        }
+
         Self {
             mouse_pos: [0.0, 0.0],
+            modifiers: ModifiersState::empty(),
+            text_input_active: false,
+            gamepads,
+            virtual_gamepad,
+            input_map: InputMap::new(),
+            audio: Audio::new(),
             player: 0,
             physics_tick_rate: 1.0 / 60.0,
             physics_accumulator: 0.0,
@@ -120,14 +168,20 @@ impl Engine {
             target_rate: target_rate,
             last_frame: Instant::now() - target_rate.unwrap_or_default(),
             asset_cache: HashMap::new(),
+            split_cameras: Vec::new(),
+            ui_scene_cameras: HashMap::new(),
+            fonts: HashMap::new(),
+            palettes: PaletteAtlas::default(),
+            frame_capture: FrameCapture::new(),
             width: config.width,
             height: config.height,
             world: World::new(),
             physics: PhysicsWorld::new(),
-            canvas: Canvas::new(),
+            canvas: Canvas::new(config.width, config.height),
             fps: FPS {
                 frame_count: 0,
                 time_accum: 0.0,
+                last: 0,
             },
             camera2d_config: config.camera2d_config,
         }
@@ -146,6 +200,93 @@ impl Engine {
         texture.clone()
     }
 
+    /// Loads a BMFont (`.fnt`) or rasterizes a TTF/OTF at `size_px` and registers it under `name`.
+    fn load_font(&mut self, name: String, path: String, size_px: f32) -> bool {
+        let full_path = format!("./src/assets/{}", path);
+        let font = if path.ends_with(".fnt") {
+            let directory = match path.rfind('/') {
+                Some(index) => path[..=index].to_string(),
+                None => String::new(),
+            };
+            let font = std::fs::read_to_string(&full_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|source| parse_bmfont(&source, &directory));
+            if let Ok(font) = &font {
+                for page in font.pages.iter() {
+                    self.get_texture(page.texture_id.clone());
+                }
+            }
+            font
+        } else {
+            let texture_id = format!("font:{}", name);
+            let rasterized = std::fs::read(&full_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| rasterize_ttf(bytes, size_px, &texture_id, &default_charset()));
+            rasterized.map(|(font, atlas)| {
+                let texture = self
+                    .graphics
+                    .as_mut()
+                    .expect("Graphics not initialized")
+                    .load_texture_from_image(&texture_id, &image::DynamicImage::ImageRgba8(atlas));
+                self.asset_cache.insert(texture_id, texture);
+                font
+            })
+        };
+
+        match font {
+            Ok(font) => {
+                debug_log!(self.debugger, "Initialized font: {} ({})", name, full_path);
+                self.fonts.insert(name, font);
+                true
+            }
+            Err(e) => {
+                println!("Failed to load font {}: {}", full_path, e);
+                false
+            }
+        }
+    }
+
+    /// Queues text for the current frame.
+    /// World text is placed in world units, canvas text in pixels from the top left.
+    /// `anchor` takes the canvas layout names or `anchor_x` / `anchor_y`, top left by default.
+    fn draw_text(&mut self, text: String, x: f32, y: f32, options: Table) {
+        let font_name: String = options.get("font").unwrap_or("default".to_string());
+        let font = match self.fonts.get(&font_name) {
+            Some(font) => font,
+            None => return,
+        };
+        let space = match options.get::<String>("space").as_deref() {
+            Ok("world") => Space::World,
+            _ => Space::Canvas,
+        };
+        let mesh = layout_text(font, &text, &TextLayoutOptions::from_lua_table(&options));
+        let color = text_color_from_lua(&options);
+        let anchor = parse_anchor(&options, [0.0, 0.0]);
+
+        match space {
+            Space::World => {
+                // `size` is the line height in world units
+                let size: f32 = options.get("size").unwrap_or(1.0);
+                self.world.texts.push(WorldText {
+                    mesh,
+                    position: [x, y],
+                    anchor,
+                    scale: size / font.line_height,
+                    color,
+                });
+            }
+            Space::Canvas => {
+                let size: f32 = options.get("size").unwrap_or(font.line_height);
+                let scale = size / font.line_height;
+                self.canvas.draw_text(mesh, [x, y], anchor, scale, color);
+            }
+        }
+    }
+
+    fn get_fps(&self) -> u32 {
+        self.fps.last
+    }
+
     fn flip(&mut self, entity: u32, x: bool, y: bool) {
         self.world.flips.insert(entity, FlipComponent { x, y });
         if let Some(t) = self.world.transforms_2d.get_mut(&entity) {
@@ -154,20 +295,40 @@ impl Engine {
         }
     }
 
-    pub fn update_camera_follow_player(&mut self) {
-        if self.dimensions == Dimensions::Two {
-            if let Some(transform) = self.world.transforms_2d.get(&self.player) {
-                let velocity = self.physics.get_velocity(&self.player);
-                let graphics = match &mut self.graphics {
-                    Some(canvas) => canvas,
-                    None => return,
-                };
-                graphics.move_camera_for_follow(
-                    [transform.position[0], transform.position[1], 0.0],
-                    [velocity.x, velocity.y, 0.0],
-                    [0.0, 0.0, 0.0],
-                    [0.0, 0.0, 0.0],
-                );
+    /// Moves every 2D camera toward its target, once per physics tick.
+    pub fn update_camera_follow(&mut self) {
+        if self.dimensions != Dimensions::Two {
+            return;
+        }
+        let graphics = match &mut self.graphics {
+            Some(canvas) => canvas,
+            None => return,
+        };
+        for id in graphics.camera_ids() {
+            let Some(camera) = graphics.camera_2d(id) else {
+                continue;
+            };
+            let entity = match camera.target {
+                CameraTarget::Player => self.player,
+                CameraTarget::Entity(entity) => entity,
+                CameraTarget::None => continue,
+            };
+            if let Some(transform) = self.world.transforms_2d.get(&entity) {
+                let velocity = self.physics.get_velocity(&entity);
+                camera.update_follow(transform.position, Vector2::new(velocity.x, velocity.y));
+            }
+        }
+    }
+
+    /// Pans, zoom and shake run on frame time rather than physics ticks.
+    fn update_cameras(&mut self, dt: f32) {
+        let graphics = match &mut self.graphics {
+            Some(canvas) => canvas,
+            None => return,
+        };
+        for id in graphics.camera_ids() {
+            if let Some(camera) = graphics.camera_2d(id) {
+                camera.update(dt);
             }
         }
     }
@@ -192,6 +353,8 @@ impl Engine {
 
         self.physics_accumulator += dt32;
 
+        self.poll_gamepads();
+        self.input_map.update(dt32);
         let _ = update.call::<()>(dt32);
         let b = Instant::now();
         while self.physics_accumulator >= self.physics_tick_rate {
@@ -221,7 +384,7 @@ impl Engine {
                 */
 
                 if self.camera_mode == CameraOption::Follow {
-                    self.update_camera_follow_player();
+                    self.update_camera_follow();
                 }
             }
             self.world.update_positions(self.physics.positions());
@@ -235,11 +398,138 @@ impl Engine {
             .get_function("ENGINE_after_physics")
             .call::<()>(dt32);
 
-        animation_system_update_frames(&mut self.world, dt32);
+        self.update_facings();
+        let animation_events = animation_system_update_frames(&mut self.world, dt32);
+        self.call_lua_animation_events(animation_events);
+        animation_graph_system_update(&mut self.world);
+        particle_system_update(&mut self.world, dt32);
+        light_system_update(&mut self.world);
+        post_effect_system_update(&mut self.world, dt32);
+        self.canvas.sync_health(&self.world.health_bars);
+        self.canvas.update_animations(dt32);
+        self.sync_ui_scene_regions();
+        self.canvas.update_layout();
+        self.input_map.end_frame();
+        self.update_audio(dt32);
         //println!("After P Loops : {:?}", c.elapsed().as_secs_f64());
         return Ok(());
     }
 
+    /// All of the frame's animation events in one `ENGINE_animation_events(events)` call, each
+    /// `{ entity, state, event, frame }` where `event` is the frame event's name or "finished".
+    fn call_lua_animation_events(&self, events: Vec<(Entity, ActionState, AnimationEvent)>) {
+        if events.is_empty() {
+            return;
+        }
+        let on_events = match self
+            .lua_context
+            .get_optional_function("ENGINE_animation_events")
+        {
+            Some(on_events) => on_events,
+            None => return,
+        };
+
+        let batch = self.lua_context.create_table();
+        for (entity, state, event) in events {
+            let ActionState::Custom(state) = state;
+            let entry = self.lua_context.create_table();
+            let _ = entry.set("entity", entity);
+            let _ = entry.set("state", state);
+            match event {
+                AnimationEvent::Frame { name, frame } => {
+                    let _ = entry.set("event", name);
+                    let _ = entry.set("frame", frame + 1);
+                }
+                AnimationEvent::Finished => {
+                    let _ = entry.set("event", "finished");
+                }
+            }
+            let _ = batch.push(entry);
+        }
+        let _ = on_events.call::<()>(batch);
+    }
+
+    fn update_audio(&mut self, dt: f32) {
+        if let Some(graphics) = &self.graphics {
+            let camera = graphics.get_camera_info();
+            self.audio
+                .set_listener([camera.position[0], camera.position[1]]);
+        }
+        self.audio.update(dt);
+    }
+
+    /// Decodes a sound ahead of time so the first `play_sound` doesn't hitch.
+    fn load_sound(&mut self, path: String) -> bool {
+        let full_path = format!("./src/assets/{}", path);
+        match self.audio.load(&path, &full_path) {
+            Ok(_) => true,
+            Err(e) => {
+                println!("Could not load sound {}: {}", path, e);
+                false
+            }
+        }
+    }
+
+    fn play_sound(&mut self, path: String, options: Option<Table>) -> Option<u32> {
+        let full_path = format!("./src/assets/{}", path);
+        let options = options
+            .map(|options| PlayOptions::from_lua_table(&options))
+            .unwrap_or_default();
+        self.audio.play(&path, &full_path, options)
+    }
+
+    fn stop_sound(&mut self, id: u32, fade: Option<f32>) {
+        self.audio.stop(id, fade.unwrap_or(0.0));
+    }
+
+    fn is_sound_playing(&self, id: u32) -> bool {
+        self.audio.is_playing(id)
+    }
+
+    /// Moves a positional sound, e.g. to follow the entity that made it.
+    fn set_sound_position(&mut self, id: u32, x: f32, y: f32) {
+        self.audio.set_position(id, [x, y]);
+    }
+
+    /// Loops by default, `crossfade` (seconds, default 1) blends out whatever was playing.
+    fn play_music(&mut self, path: String, options: Option<Table>) -> Option<u32> {
+        let full_path = format!("./src/assets/{}", path);
+        let (options, crossfade) = match options {
+            Some(table) => (
+                PlayOptions {
+                    looping: table.get("loop").unwrap_or(true),
+                    ..PlayOptions::from_lua_table(&table)
+                },
+                table.get("crossfade").unwrap_or(1.0),
+            ),
+            None => (
+                PlayOptions {
+                    looping: true,
+                    ..Default::default()
+                },
+                1.0,
+            ),
+        };
+        self.audio.play_music(&full_path, options, crossfade)
+    }
+
+    fn stop_music(&mut self, fade: Option<f32>) {
+        self.audio.stop_music(fade.unwrap_or(0.0));
+    }
+
+    /// `bus` is "music", "sfx" or "ui".
+    fn set_bus_volume(&mut self, bus: String, volume: f32) {
+        self.audio.set_bus_volume(Bus::from(bus.as_str()), volume);
+    }
+
+    fn set_master_volume(&mut self, volume: f32) {
+        self.audio.set_master_volume(volume);
+    }
+
+    fn set_audio_rolloff(&mut self, min_distance: f32, max_distance: f32) {
+        self.audio.set_rolloff(min_distance, max_distance);
+    }
+
     fn cleanup(&mut self) {
         debug_log!(self.debugger, "Cleaned it? {}", true)
     }
@@ -320,18 +610,638 @@ impl Engine {
         health
     }
 
+    fn set_tint(&mut self, id: Entity, r: f32, g: f32, b: f32, a: f32) {
+        self.world.sprite_colors.entry(id).or_default().tint = [r, g, b, a];
+    }
+
+    fn set_flash(&mut self, id: Entity, r: f32, g: f32, b: f32, amount: f32) {
+        self.world.sprite_colors.entry(id).or_default().flash = [r, g, b, amount.clamp(0.0, 1.0)];
+    }
+
+    fn set_opacity(&mut self, id: Entity, opacity: f32) {
+        self.world.sprite_colors.entry(id).or_default().opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    fn clear_sprite_color(&mut self, id: Entity) {
+        self.world.sprite_colors.remove(&id);
+    }
+
+    fn set_rotation(&mut self, id: Entity, radians: f32) {
+        if let Some(t) = self.world.transforms_2d.get_mut(&id) {
+            t.rotation_radians = radians;
+        }
+    }
+
+    fn set_render_layer(&mut self, id: Entity, layer: u8, sort_offset: f32) {
+        let component = self.world.render_layers.entry(id).or_default();
+        component.layer = RenderLayer::from(layer);
+        component.pivot_offset_y = sort_offset;
+    }
+
+    fn set_render_z(&mut self, id: Entity, z: f32) {
+        self.world.render_layers.entry(id).or_default().z = z;
+    }
+
+    fn set_layer_sort_mode(&mut self, layer: u8, mode: u8) {
+        self.world
+            .set_layer_sort_mode(RenderLayer::from(layer), SortMode::from(mode));
+    }
+
     fn set_state(&mut self, id: Entity, state: u8) {
         set_entity_state(&mut self.world, id, ActionState::from(state.clone()));
     }
 
-    fn create_ui_scene(&mut self, lua_scene: mlua::Table) -> [u32; 1] {
-        let entity = self.canvas.new_entity();
-        let scene = parse_scene_from_lua(lua_scene, &mut self.canvas);
-        for (_, element) in scene.0.elements.iter() {
-            self.get_texture(element.sprite_sheet.clone());
+    fn set_animation_time_scale(&mut self, scale: f32) {
+        self.world.animation_time_scale = scale.max(0.0);
+    }
+
+    fn set_animation_speed(&mut self, id: Entity, scale: f32) {
+        if let Some(animation) = self.world.animations.get_mut(&id) {
+            animation.time_scale = scale.max(0.0);
+        }
+    }
+
+    fn pause_animation(&mut self, id: Entity) {
+        if let Some(animation) = self.world.animations.get_mut(&id) {
+            animation.paused = true;
+        }
+    }
+
+    fn resume_animation(&mut self, id: Entity) {
+        if let Some(animation) = self.world.animations.get_mut(&id) {
+            animation.paused = false;
+        }
+    }
+
+    // frames are counted from 1 like in the Lua animation tables
+    fn seek_animation(&mut self, id: Entity, frame: usize) {
+        let state = match self.world.action_states.get(&id) {
+            Some(action_state) => action_state.state.clone(),
+            None => return,
+        };
+        if let Some(animation) = self.world.animations.get_mut(&id) {
+            animation.seek(&state, frame.saturating_sub(1), 0.0);
+        }
+    }
+
+    fn get_animation_frame(&mut self, id: Entity) -> Option<usize> {
+        self.world
+            .animations
+            .get(&id)
+            .map(|animation| animation.current_frame_index + 1)
+    }
+
+    fn update_facings(&mut self) {
+        let velocities: HashMap<Entity, [f32; 2]> = self
+            .world
+            .facings
+            .keys()
+            .map(|entity| {
+                let velocity = self.physics.get_velocity(entity);
+                (*entity, [velocity.x, velocity.y])
+            })
+            .collect();
+        facing_system_update(&mut self.world, &velocities);
+    }
+
+    // turns off facing the velocity, the body keeps this facing until told otherwise
+    fn set_facing(&mut self, id: Entity, name: String) {
+        let facing = match Facing::from_name(&name) {
+            Some(facing) => facing,
+            None => {
+                println!("Unknown facing: {}", name);
+                return;
+            }
+        };
+        let component = self.world.facings.entry(id).or_insert(FacingComponent {
+            facing,
+            directions: 8,
+            from_velocity: false,
+        });
+        component.facing = facing;
+        component.from_velocity = false;
+    }
+
+    fn set_facing_from_velocity(&mut self, id: Entity, enabled: bool) {
+        if let Some(component) = self.world.facings.get_mut(&id) {
+            component.from_velocity = enabled;
+        }
+    }
+
+    fn get_facing(&mut self, id: Entity) -> Option<String> {
+        self.world
+            .facings
+            .get(&id)
+            .map(|component| component.facing.name().to_string())
+    }
+
+    /// Config fields are listed on `ParticleEmitterConfig::from_lua_table`, plus `x`, `y`
+    /// and `follow` (an entity id, which makes x and y an offset from it).
+    fn create_particle_emitter(&mut self, config: mlua::Table) -> u32 {
+        let emitter_config = ParticleEmitterConfig::from_lua_table(&config);
+        if self.graphics.is_some() {
+            self.get_texture(emitter_config.texture.clone());
+        }
+        let position = [
+            config.get("x").unwrap_or(0.0),
+            config.get("y").unwrap_or(0.0),
+        ];
+        let follow: Option<Entity> = config.get("follow").ok();
+
+        let entity = self.world.new_entity();
+        self.world.particle_emitters.insert(
+            entity,
+            ParticleEmitter::new(emitter_config, position, follow),
+        );
+        entity
+    }
+
+    fn emit_particles(&mut self, id: Entity, count: u32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&id) {
+            emitter.burst(count);
+        }
+    }
+
+    fn set_particle_emitter_position(&mut self, id: Entity, x: f32, y: f32) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&id) {
+            emitter.position = [x, y];
+        }
+    }
+
+    fn set_particle_emitter_active(&mut self, id: Entity, active: bool) {
+        if let Some(emitter) = self.world.particle_emitters.get_mut(&id) {
+            emitter.emitting = active;
+        }
+    }
+
+    // stops spawning and lets the live particles finish unless `immediate`
+    fn remove_particle_emitter(&mut self, id: Entity, immediate: Option<bool>) {
+        if immediate.unwrap_or(false) {
+            self.world.particle_emitters.remove(&id);
+        } else if let Some(emitter) = self.world.particle_emitters.get_mut(&id) {
+            emitter.emitting = false;
+            emitter.config.one_shot = true;
+        }
+    }
+
+    fn set_ambient_light(&mut self, r: f32, g: f32, b: f32) {
+        self.world.ambient_light = [r, g, b];
+    }
+
+    /// Config fields are listed on `Light2D::from_lua_table`.
+    fn create_light(&mut self, config: mlua::Table) -> u32 {
+        let entity = self.world.new_entity();
+        self.world
+            .lights
+            .insert(entity, Light2D::from_lua_table(&config));
+        entity
+    }
+
+    // an offset when the light follows an entity
+    fn set_light_position(&mut self, id: Entity, x: f32, y: f32) {
+        if let Some(light) = self.world.lights.get_mut(&id) {
+            light.position = [x, y];
+        }
+    }
+
+    fn set_light_color(&mut self, id: Entity, r: f32, g: f32, b: f32, intensity: Option<f32>) {
+        if let Some(light) = self.world.lights.get_mut(&id) {
+            light.color = [r, g, b];
+            light.intensity = intensity.unwrap_or(light.intensity);
+        }
+    }
+
+    fn set_light_radius(&mut self, id: Entity, radius: f32) {
+        if let Some(light) = self.world.lights.get_mut(&id) {
+            light.radius = radius;
+        }
+    }
+
+    // degrees, only spot lights have a direction
+    fn set_light_direction(&mut self, id: Entity, degrees: f32) {
+        if let Some(light) = self.world.lights.get_mut(&id) {
+            if let LightKind::Spot { direction, .. } = &mut light.kind {
+                *direction = [degrees.to_radians().cos(), degrees.to_radians().sin()];
+            }
+        }
+    }
+
+    fn set_light_enabled(&mut self, id: Entity, enabled: bool) {
+        if let Some(light) = self.world.lights.get_mut(&id) {
+            light.enabled = enabled;
+        }
+    }
+
+    fn remove_light(&mut self, id: Entity) {
+        self.world.lights.remove(&id);
+    }
+
+    /// Lights sprites drawn from `texture` with the normal map at `normal_map`, both asset paths.
+    fn load_normal_map(&mut self, texture: String, normal_map: String) -> bool {
+        if self.graphics.is_none() {
+            return false;
+        }
+        let texture = self.get_texture(texture);
+        let path = format!("./src/assets/{}", normal_map);
+        self.graphics
+            .as_mut()
+            .expect("Graphics not initialized")
+            .load_normal_map(&texture, &path)
+    }
+
+    // appended to the end of the chain the first time it is named
+    fn post_effect_mut(&mut self, name: &str) -> &mut PostEffect {
+        let kind = PostEffectKind::from_name(name);
+        let index = match self.world.post_effects.iter().position(|e| e.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.world.post_effects.push(PostEffect::new(kind));
+                self.world.post_effects.len() - 1
+            }
+        };
+        &mut self.world.post_effects[index]
+    }
+
+    /// Options are listed on `PostEffect::apply_lua_table`.
+    fn set_post_effect(&mut self, name: String, options: Option<mlua::Table>) {
+        let effect = self.post_effect_mut(&name);
+        if let Some(options) = options {
+            effect.apply_lua_table(&options);
+        }
+        if let (Some(texture), true) = (effect.texture.clone(), self.graphics.is_some()) {
+            self.get_texture(texture);
+        }
+    }
+
+    fn set_post_effect_enabled(&mut self, name: String, enabled: bool) {
+        let kind = PostEffectKind::from_name(&name);
+        for effect in self
+            .world
+            .post_effects
+            .iter_mut()
+            .filter(|e| e.kind == kind)
+        {
+            effect.enabled = enabled;
+        }
+    }
+
+    fn remove_post_effect(&mut self, name: String) {
+        let kind = PostEffectKind::from_name(&name);
+        self.world.post_effects.retain(|effect| effect.kind != kind);
+    }
+
+    fn fade_post_effect(&mut self, name: String, strength: f32, duration: f32) {
+        self.post_effect_mut(&name).fade_to(strength, duration);
+    }
+
+    /// Jumps the screen toward a color and fades back over `duration` seconds.
+    fn flash_screen(&mut self, r: f32, g: f32, b: f32, strength: f32, duration: f32) {
+        let flash = self.post_effect_mut("flash");
+        flash.enabled = true;
+        flash.params[0] = [r, g, b, 1.0];
+        flash.strength = strength;
+        flash.fade_to(0.0, duration);
+    }
+
+    /// Custom WGSL effect from an asset path, used afterwards by name like the built in ones.
+    fn load_post_effect(&mut self, name: String, path: String) -> bool {
+        let path = format!("./src/assets/{}", path);
+        match self.graphics.as_mut() {
+            Some(graphics) => graphics.load_post_effect(&name, &path),
+            None => false,
+        }
+    }
+
+    /// Saves the next frame as a PNG, as the window shows it. Paths are relative to the working
+    /// directory, not the assets, and default to a timestamped file in `screenshots/`.
+    fn screenshot(&mut self, path: Option<String>) {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => screenshot_path(Path::new("screenshots")),
+        };
+        self.frame_capture.screenshot(path);
+    }
+
+    /// Saves every `every`th frame from now on as `frame_00000.png`, `frame_00001.png`, ...
+    /// in `directory`. Returns the frame count of a recording this one replaced.
+    fn start_recording(&mut self, directory: String, every: Option<u32>) -> Option<u32> {
+        self.frame_capture
+            .start_recording(PathBuf::from(directory), every.unwrap_or(1))
+    }
+
+    /// Returns how many frames were saved, nil when nothing was recording.
+    fn stop_recording(&mut self) -> Option<u32> {
+        self.frame_capture.stop_recording()
+    }
+
+    fn is_recording(&mut self) -> bool {
+        self.frame_capture.is_recording()
+    }
+
+    /// Registers a sprite material from a WGSL asset written against
+    /// `shaders/materials/common.wgsl`. Options are listed on `Material::from_lua_table`.
+    /// The material is kept even if the shader fails, its entities then draw as plain sprites.
+    fn create_material(
+        &mut self,
+        name: String,
+        path: String,
+        options: Option<mlua::Table>,
+    ) -> bool {
+        let material = Material::from_lua_table(options.as_ref());
+        if let (Some(texture), true) = (material.texture.clone(), self.graphics.is_some()) {
+            self.get_texture(texture);
+        }
+        self.world.materials.insert(name.clone(), material);
+        let path = format!("./src/assets/{}", path);
+        match self.graphics.as_mut() {
+            Some(graphics) => graphics.load_material(&name, &path),
+            None => false,
+        }
+    }
+
+    /// Changes a parameter for every entity using the material, except where they set their own.
+    fn set_material_param(&mut self, name: String, param: String, value: mlua::Value) {
+        let Some(material) = self.world.materials.get_mut(&name) else {
+            return;
+        };
+        if let (Some(slot), Some(value)) = (material.slot(&param), param_value(value)) {
+            material.params[slot] = value;
+        }
+    }
+
+    fn set_material(&mut self, id: Entity, name: String) {
+        let keeps_overrides = self
+            .world
+            .entity_materials
+            .get(&id)
+            .is_some_and(|component| component.material == name);
+        if !keeps_overrides {
+            self.world
+                .entity_materials
+                .insert(id, MaterialComponent::new(name));
+        }
+    }
+
+    fn clear_material(&mut self, id: Entity) {
+        self.world.entity_materials.remove(&id);
+    }
+
+    /// Sets a parameter for one entity, on top of its material's value.
+    fn set_entity_material_param(&mut self, id: Entity, param: String, value: mlua::Value) {
+        let Some(component) = self.world.entity_materials.get_mut(&id) else {
+            return;
+        };
+        let slot = self
+            .world
+            .materials
+            .get(&component.material)
+            .and_then(|material| material.slot(&param));
+        if let (Some(slot), Some(value)) = (slot, param_value(value)) {
+            component.set_param(slot, value);
+        }
+    }
+
+    /// Loads a palette from one asset path or a list of them, returns how many variants it has.
+    /// PNG strips give a row per line, `.gpl` and `.hex` files one row each. The first row is
+    /// the colors of the sheets it recolors, every row after it a variant.
+    fn load_palette(&mut self, name: String, paths: mlua::Value) -> u32 {
+        let paths = match paths {
+            mlua::Value::String(path) => vec![path.to_string_lossy()],
+            mlua::Value::Table(paths) => paths.sequence_values::<String>().flatten().collect(),
+            _ => Vec::new(),
+        };
+        let mut rows = Vec::new();
+        for path in paths.iter() {
+            match load_palette_rows(&format!("./src/assets/{}", path)) {
+                Ok(file_rows) => rows.extend(file_rows),
+                Err(e) => {
+                    println!("Failed to load palette {} from {}: {}", name, path, e);
+                    return 0;
+                }
+            }
+        }
+        let entry = match self.palettes.add(&name, rows) {
+            Ok(entry) => entry,
+            Err(e) => {
+                println!("Failed to load palette {}: {}", name, e);
+                return 0;
+            }
+        };
+
+        if let Some(graphics) = self.graphics.as_mut() {
+            graphics.load_data_texture(PALETTE_ATLAS_TEXTURE, &self.palettes.image());
+        }
+        self.world
+            .materials
+            .entry(PALETTE_SWAP_MATERIAL.to_string())
+            .or_insert_with(|| {
+                let mut material = Material::from_lua_table(None);
+                material.param_names = vec!["palette".to_string()];
+                material.texture = Some(PALETTE_ATLAS_TEXTURE.to_string());
+                material
+            });
+        entry.variants
+    }
+
+    /// Draws the entity with a variant of a loaded palette, 0 is the sheet's own colors.
+    /// Uses the entity's material slot, so it replaces any other material.
+    fn set_palette(&mut self, id: Entity, name: String, variant: u32) {
+        let Some(entry) = self.palettes.get(&name).copied() else {
+            println!("Palette {} is not loaded", name);
+            return;
+        };
+        let mut component = MaterialComponent::new(PALETTE_SWAP_MATERIAL.to_string());
+        component.set_param(
+            0,
+            [
+                entry.source_row as f32,
+                entry.row(variant) as f32,
+                entry.colors as f32,
+                0.0,
+            ],
+        );
+        self.world.entity_materials.insert(id, component);
+    }
+
+    fn clear_palette(&mut self, id: Entity) {
+        if self
+            .world
+            .entity_materials
+            .get(&id)
+            .is_some_and(|component| component.material == PALETTE_SWAP_MATERIAL)
+        {
+            self.world.entity_materials.remove(&id);
+        }
+    }
+
+    fn get_state(&mut self, id: Entity) -> Option<u8> {
+        self.world
+            .action_states
+            .get(&id)
+            .map(|action_state| match action_state.state {
+                ActionState::Custom(state) => state,
+            })
+    }
+
+    fn set_animation_graph(&mut self, id: Entity, graph: mlua::Table) {
+        self.world
+            .animation_graphs
+            .insert(id, AnimationGraph::from_lua_table(graph));
+    }
+
+    // booleans are stored as 1 / 0 so they compare like any other number
+    fn set_animation_param(&mut self, id: Entity, name: String, value: mlua::Value) {
+        let value = match value {
+            mlua::Value::Boolean(b) => b as i32 as f32,
+            other => other.as_f32().unwrap_or(0.0),
+        };
+        if let Some(graph) = self.world.animation_graphs.get_mut(&id) {
+            graph.set_param(&name, value);
+        }
+    }
+
+    fn fire_animation_trigger(&mut self, id: Entity, name: String) {
+        if let Some(graph) = self.world.animation_graphs.get_mut(&id) {
+            graph.fire_trigger(&name);
+        }
+    }
+
+    fn create_ui_scene(&mut self, lua_scene: mlua::Table) -> [u32; 1] {
+        let entity = self.canvas.new_entity();
+        let scene = parse_scene_from_lua(lua_scene, &mut self.canvas, &self.fonts);
+        for sprite_sheet in scene.0.sprite_sheets() {
+            self.get_texture(sprite_sheet);
+        }
+        self.canvas.add_scene(entity.clone(), scene);
+        [entity.into()]
+    }
+
+    fn show_ui_scene(&mut self, id: Entity) -> bool {
+        self.canvas.set_scene_active(id, true)
+    }
+
+    fn hide_ui_scene(&mut self, id: Entity) -> bool {
+        self.canvas.set_scene_active(id, false)
+    }
+
+    fn set_ui_element_active(&mut self, id: Entity, active: bool) -> bool {
+        self.canvas.set_element_active(id, active)
+    }
+
+    fn set_ui_text(&mut self, id: Entity, text: String) -> bool {
+        self.canvas.set_text(id, &text, &self.fonts)
+    }
+
+    fn set_ui_progress(&mut self, id: Entity, value: f32) -> bool {
+        self.canvas.set_progress(id, value)
+    }
+
+    fn bind_ui_health(&mut self, id: Entity, entity: Entity) -> bool {
+        self.canvas.bind_health(id, Some(entity))
+    }
+
+    fn focus_ui_element(&mut self, id: Entity) -> bool {
+        self.canvas.focus(Some(id))
+    }
+
+    fn ui_navigate(&mut self, direction: String) -> bool {
+        let direction = match direction.as_str() {
+            "up" => [0.0, 1.0],
+            "down" => [0.0, -1.0],
+            "left" => [-1.0, 0.0],
+            "right" => [1.0, 0.0],
+            _ => return false,
+        };
+        self.canvas.navigate(direction)
+    }
+
+    fn ui_activate(&mut self) -> bool {
+        match self.canvas.activate_focused() {
+            Some(click) => {
+                self.call_lua_ui_click(click);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn call_lua_ui_click(&self, click: UiClick) {
+        if let Some(on_click) = click.on_click {
+            if let Err(e) = on_click.call::<()>(click.id) {
+                println!("Error in UI callback: {}", e);
+            }
+        }
+    }
+
+    /// Whether navigation presses go to the canvas: a menu or ui input context is active and
+    /// there is a button to move between.
+    fn ui_has_input_focus(&self) -> bool {
+        self.input_map
+            .active_context()
+            .is_some_and(|context| UI_INPUT_CONTEXTS.contains(&context.as_str()))
+            && self.canvas.has_focusable()
+    }
+
+    /// Arrow keys move focus and enter activates while the canvas has input focus.
+    /// Returns true when the press was used by the UI, releases are always left to the game.
+    fn ui_key_input(&mut self, code: KeyCode, is_pressed: bool) -> bool {
+        if !is_pressed || !self.ui_has_input_focus() {
+            return false;
+        }
+        let direction = match code {
+            KeyCode::ArrowUp => "up",
+            KeyCode::ArrowDown => "down",
+            KeyCode::ArrowLeft => "left",
+            KeyCode::ArrowRight => "right",
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                self.ui_activate();
+                return true;
+            }
+            _ => return false,
+        };
+        self.ui_navigate(direction.to_string());
+        true
+    }
+
+    /// D-pad, left stick and A drive focused canvas elements like the arrows and Enter do.
+    fn ui_gamepad_input(&mut self, input: &str, is_pressed: bool) -> bool {
+        if !is_pressed || !self.ui_has_input_focus() {
+            return false;
+        }
+        let direction = match input {
+            "gamepad_up" | "gamepad_leftstick_up" => "up",
+            "gamepad_down" | "gamepad_leftstick_down" => "down",
+            "gamepad_left" | "gamepad_leftstick_left" => "left",
+            "gamepad_right" | "gamepad_leftstick_right" => "right",
+            "gamepad_a" => {
+                self.ui_activate();
+                return true;
+            }
+            _ => return false,
+        };
+        self.ui_navigate(direction.to_string());
+        true
+    }
+
+    /// Parses an animation table and registers its sprite sheet.
+    fn load_animation(&mut self, tbl: mlua::Table) -> Animation {
+        let normal_map: Option<String> = tbl.get("normal_map").ok();
+        let (mut animation, sprite_path) = Animation::from_lua_table(tbl);
+        let sprite_id: Entity = self.world.new_entity();
+        let texture = self.get_texture(sprite_path.clone());
+        animation.sprite_sheet_id = sprite_id;
+        if let Some(normal_map) = normal_map {
+            self.load_normal_map(sprite_path.clone(), normal_map);
         }
-        self.canvas.add_scene(entity.clone(), scene);
-        [entity.into()]
+
+        self.world.sprite_sheets.insert(
+            sprite_id.clone(),
+            SpriteSheetComponent {
+                texture_id: sprite_path,
+                texture,
+            },
+        );
+        animation
     }
 
     fn create_body(&mut self, lua_element: mlua::Table) -> [u32; 2] {
@@ -339,11 +1249,15 @@ impl Engine {
         let is_pc: bool = lua_element.get("is_pc").unwrap_or(false).into();
         let x: f32 = lua_element.get("x").unwrap_or(0.0);
         let y: f32 = lua_element.get("y").unwrap_or(0.0);
-        let _z: f32 = lua_element.get("z").unwrap_or(0.0);
+        let z: f32 = lua_element.get("z").unwrap_or(0.0);
         let width: f32 = lua_element.get("width").unwrap_or(1.0);
         let height: f32 = lua_element.get("height").unwrap_or(1.0);
         let _depth: f32 = lua_element.get("depth").unwrap_or(1.0);
         let health: u16 = lua_element.get("total_health").unwrap_or(10);
+        let layer: u8 = lua_element
+            .get("layer")
+            .unwrap_or(RenderLayer::Actors as u8);
+        let sort_offset: f32 = lua_element.get("sort_offset").unwrap_or(0.0);
         let collision_box: mlua::Table = lua_element
             .get("collision_box")
             .unwrap_or(self.lua_context.create_table());
@@ -377,35 +1291,65 @@ impl Engine {
                 let numeric_key =
                     key.as_u32()
                         .expect("Numeric key required for Action States") as u8;
-                let (mut animation, sprite_path) = Animation::from_lua_table(tbl);
                 let action_state = ActionState::from(numeric_key);
+                animations_map.insert(action_state, self.load_animation(tbl));
+            }
+        }
 
-                let sprite_id: Entity = self.world.new_entity();
-                let texture = self.get_texture(sprite_path.clone());
-                animation.sprite_sheet_id = sprite_id;
-
-                self.world.sprite_sheets.insert(
-                    sprite_id.clone(),
-                    SpriteSheetComponent {
-                        texture_id: sprite_path,
-                        texture,
-                    },
-                );
-                animations_map.insert(action_state, animation);
+        // { [action] = { n = animation, e = animation, ... } }
+        let mut directional_map = HashMap::new();
+        if let Ok(directional) = lua_element.get::<mlua::Table>("directional_animations") {
+            for pair in directional.pairs::<u8, mlua::Table>() {
+                if let Ok((key, facings)) = pair {
+                    let mut clips = HashMap::new();
+                    for pair in facings.pairs::<String, mlua::Table>() {
+                        if let Ok((name, tbl)) = pair {
+                            match Facing::from_name(&name) {
+                                Some(facing) => {
+                                    clips.insert(facing, self.load_animation(tbl));
+                                }
+                                None => println!("Unknown facing '{}' in animations", name),
+                            }
+                        }
+                    }
+                    directional_map.insert(ActionState::from(key), clips);
+                }
             }
         }
 
-        let current_frame = animations_map.get(&state).unwrap().frames[0].clone();
+        // { directions = 8, from_velocity = true, initial = "s" }
+        let facing = lua_element
+            .get::<mlua::Table>("facing")
+            .ok()
+            .map(|tbl| FacingComponent {
+                facing: tbl
+                    .get::<String>("initial")
+                    .ok()
+                    .and_then(|name| Facing::from_name(&name))
+                    .unwrap_or_default(),
+                directions: if tbl.get("directions").unwrap_or(8) == 4 {
+                    4
+                } else {
+                    8
+                },
+                from_velocity: tbl.get("from_velocity").unwrap_or(true),
+            });
+
+        if let Ok(graph) = lua_element.get::<mlua::Table>("animation_graph") {
+            self.world
+                .animation_graphs
+                .insert(entity.clone(), AnimationGraph::from_lua_table(graph));
+        }
 
         if self.dimensions == Dimensions::Two {
             self.world.animations.insert(
                 entity.clone(),
-                AnimationComponent {
-                    animations: animations_map,
-                    current_frame_index: 0,
-                    current_frame,
-                    frame_timer: 0.0,
-                },
+                AnimationComponent::new_directional(
+                    animations_map,
+                    directional_map,
+                    facing.map(|f| f.facing).unwrap_or_default(),
+                    &state,
+                ),
             );
             self.world.transforms_2d.insert(
                 entity.clone(),
@@ -419,6 +1363,9 @@ impl Engine {
                     rotation_radians: 0.0,
                 },
             );
+            if let Some(facing) = facing {
+                self.world.facings.insert(entity.clone(), facing);
+            }
             self.world.health_bars.insert(
                 entity.clone(),
                 HealthComponent {
@@ -429,6 +1376,14 @@ impl Engine {
             self.world
                 .action_states
                 .insert(entity.clone(), ActionStateComponent { state });
+            self.world.render_layers.insert(
+                entity,
+                RenderLayerComponent {
+                    layer: RenderLayer::from(layer),
+                    z,
+                    pivot_offset_y: sort_offset,
+                },
+            );
 
             self.physics.add_body(
                 entity.clone(),
@@ -458,23 +1413,30 @@ impl Engine {
                 },
             );
         }
+        // { name = "skelly", variant = 2 }
+        if let Ok(palette) = lua_element.get::<mlua::Table>("palette") {
+            if let Ok(name) = palette.get::<String>("name") {
+                self.set_palette(entity, name, palette.get("variant").unwrap_or(0));
+            }
+        }
         [entity.into(), 0]
     }
 
     pub fn screen_to_world(&self, loc: [f32; 2]) -> [f32; 2] {
-        let size = self.get_window_size();
-        let half_screen = [size[0] as f32 * 0.5, size[1] as f32 * 0.5];
-        // Pixel offset from screen center
-        let offset = [loc[0] - half_screen[0], loc[1] - half_screen[1]];
-
         let graphics = match &self.graphics {
             Some(canvas) => canvas,
             None => return [0.0, 0.0],
         };
 
         let camera = graphics.get_camera_info();
+        // the world may be letterboxed, so measure from the middle of where it is drawn
+        let [x, y, width, height] = camera.viewport;
+        let center = [x + width * 0.5, y + height * 0.5];
+        // Pixel offset from the world's center
+        let offset = [loc[0] - center[0], loc[1] - center[1]];
+
         // World units per pixel
-        let units_per_pixel = (camera.zoom * 2.0) / size[1] as f32;
+        let units_per_pixel = (camera.zoom * 2.0) / height.max(1.0);
 
         // Scaled world offset
         let world_offset = [offset[0] * units_per_pixel, offset[1] * units_per_pixel];
@@ -486,21 +1448,199 @@ impl Engine {
         ]
     }
 
-    pub fn configure_camera(&mut self, _config: mlua::Table) -> Result<()> {
-        // let config = LuaCameraConfig::from_lua_table(config)?;
-        // Create camera
-        // let camera = Camera3D::new(self.width, self.height, mode.clone());
-        //
-        /*
-        let graphics = match &mut self.graphics {
-            Some(canvas) => canvas,
-            None => return Ok(()),
+    /// Applies camera settings from Lua, the keys are listed on `Camera2D::configure_from_lua`.
+    /// `camera` defaults to the main camera.
+    pub fn configure_camera(
+        &mut self,
+        config: mlua::Table,
+        camera: Option<CameraId>,
+    ) -> Result<()> {
+        match self.camera_2d(camera) {
+            Some(camera) => camera.configure_from_lua(&config),
+            None => Ok(()),
+        }
+    }
+
+    fn camera_2d(&mut self, camera: Option<CameraId>) -> Option<&mut Camera2D> {
+        self.graphics
+            .as_mut()?
+            .camera_2d(camera.unwrap_or(MAIN_CAMERA))
+    }
+
+    /// Adds a camera drawn over the main one, e.g. a minimap. It starts from the main camera's
+    /// startup settings but follows nothing until `config` gives it a target.
+    fn create_camera(&mut self, config: mlua::Table) -> Result<Option<CameraId>> {
+        let mut camera = Camera2D::new(&self.camera2d_config);
+        camera.target = CameraTarget::None;
+        camera.configure_from_lua(&config)?;
+        Ok(self
+            .graphics
+            .as_mut()
+            .and_then(|graphics| graphics.add_camera_2d(camera)))
+    }
+
+    fn remove_camera(&mut self, id: CameraId) -> bool {
+        self.graphics
+            .as_mut()
+            .is_some_and(|graphics| graphics.remove_camera_2d(id))
+    }
+
+    /// Adds trauma, 0 to 1, that shakes the camera and wears off over time.
+    fn shake_camera(&mut self, trauma: f32, camera: Option<CameraId>) {
+        if let Some(camera) = self.camera_2d(camera) {
+            camera.add_trauma(trauma);
+        }
+    }
+
+    /// Eases to `zoom` at the camera's `zoom_speed` unless `smooth` is false.
+    fn set_camera_zoom(&mut self, zoom: f32, smooth: Option<bool>, camera: Option<CameraId>) {
+        if let Some(camera) = self.camera_2d(camera) {
+            camera.set_zoom(zoom, smooth.unwrap_or(true));
+        }
+    }
+
+    /// Glides to a point and stays there. Give the camera a target again to resume following.
+    fn pan_camera(&mut self, x: f32, y: f32, duration: f32, camera: Option<CameraId>) {
+        if let Some(camera) = self.camera_2d(camera) {
+            camera.pan_to([x, y], duration);
+        }
+    }
+
+    /// Splits the window between `targets`, one camera following each, laid out "horizontal"
+    /// (side by side), "vertical" (stacked) or "grid" (2x2). "single" puts the main camera
+    /// back alone. The main camera takes the first viewport, the others copy its settings.
+    /// Returns each target's camera.
+    fn set_split_screen(&mut self, layout: String, targets: Vec<Entity>) -> Result<Vec<CameraId>> {
+        let viewports = match (layout.as_str(), SplitLayout::from_name(&layout)) {
+            ("single", _) => vec![[0.0, 0.0, 1.0, 1.0]],
+            (_, Some(split)) => split.viewports(targets.len()),
+            _ => {
+                return Err(mlua::Error::runtime(format!(
+                    "unknown split screen layout '{}'",
+                    layout
+                )))
+            }
+        };
+        let Some(graphics) = self.graphics.as_mut() else {
+            return Ok(Vec::new());
+        };
+        let Some(template) = graphics.camera_2d(MAIN_CAMERA).map(|camera| camera.clone()) else {
+            return Ok(Vec::new());
+        };
+        while self.split_cameras.len() + 1 > viewports.len() {
+            if let Some(id) = self.split_cameras.pop() {
+                graphics.remove_camera_2d(id);
+            }
+        }
+        while self.split_cameras.len() + 1 < viewports.len() {
+            match graphics.add_camera_2d(template.clone()) {
+                Some(id) => self.split_cameras.push(id),
+                None => break,
+            }
+        }
+
+        let cameras: Vec<CameraId> = std::iter::once(MAIN_CAMERA)
+            .chain(self.split_cameras.iter().copied())
+            .collect();
+        for (index, id) in cameras.iter().enumerate() {
+            let Some(camera) = graphics.camera_2d(*id) else {
+                continue;
+            };
+            camera.set_viewport(viewports[index]);
+            let Some(target) = targets.get(index).copied() else {
+                continue;
+            };
+            camera.target = match target == self.player {
+                true => CameraTarget::Player,
+                false => CameraTarget::Entity(target),
+            };
+            if let Some(transform) = self.world.transforms_2d.get(&target) {
+                camera.jump_to([transform.position.x, transform.position.y]);
+            }
+        }
+        Ok(cameras)
+    }
+
+    /// Lays a canvas scene out within a camera's viewport, e.g. one HUD per split-screen
+    /// player. Nil gives it the whole window back.
+    fn set_ui_scene_camera(&mut self, scene: Entity, camera: Option<CameraId>) {
+        match camera {
+            Some(camera) => {
+                self.ui_scene_cameras.insert(scene, camera);
+            }
+            None => {
+                self.ui_scene_cameras.remove(&scene);
+                self.canvas.set_scene_region(scene, None);
+            }
+        }
+    }
+
+    /// Follows viewports that moved with a split or a resize. Scenes of removed cameras get
+    /// the whole window.
+    fn sync_ui_scene_regions(&mut self) {
+        let Some(graphics) = self.graphics.as_ref() else {
+            return;
+        };
+        for (scene, camera) in self.ui_scene_cameras.iter() {
+            self.canvas
+                .set_scene_region(*scene, graphics.camera_viewport(*camera));
+        }
+    }
+
+    fn get_camera_position(&mut self, camera: Option<CameraId>) -> Option<[f32; 2]> {
+        self.camera_2d(camera)
+            .map(|camera| [camera.position.x, camera.position.y])
+    }
+
+    /// Draws the frame, capturing it when a screenshot or recording wants it. False without
+    /// graphics.
+    fn render_frame(&mut self) -> bool {
+        let Some(graphics) = self.graphics.as_mut() else {
+            return false;
+        };
+        let _ = graphics.update_camera();
+        if let Some(id) = self.frame_capture.begin_frame() {
+            graphics.capture_next_frame(id);
+        }
+        let _ = graphics.render(&self.world, &self.canvas, &self.physics);
+        self.save_captured_frames(false);
+        self.world.clear_texts();
+        self.canvas.clear_texts();
+        true
+    }
+
+    /// Hands the frames back from the GPU to the writer, `wait` blocks for the ones in flight.
+    fn save_captured_frames(&mut self, wait: bool) {
+        let Some(graphics) = self.graphics.as_mut() else {
+            return;
         };
-        */
-        Ok(())
+        for (id, image) in graphics.captured_frames(wait) {
+            self.frame_capture.save(id, image);
+        }
     }
 
     fn setup(&mut self) {
+        self.expose_lua_api();
+
+        let config: mlua::Table = self
+            .lua_context
+            .get_function("ENGINE_load")
+            .call::<mlua::Table>({})
+            .expect("Unable to load initial assets.");
+
+        let assets = config
+            .get::<mlua::Table>("assets")
+            .unwrap_or_else(|_| self.lua_context.create_table());
+        for asset in assets.sequence_values::<String>() {
+            let asset = asset.unwrap_or("".to_string());
+            if asset != "" {
+                let _ = self.get_texture(asset.clone());
+            };
+        }
+    }
+
+    /// Registers the `engine` table Lua scripts call into.
+    fn expose_lua_api(&mut self) {
         macro_rules! expose_fn {
             // Function with return type
             ($lua:expr, $ptr:expr, $table:expr, $name:ident, ($($arg:ident : $typ:ty),*) -> $ret:ty) => {{
@@ -532,6 +1672,59 @@ impl Engine {
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, toggle_area, (id: u32, b: bool));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_velocity_2d, (id: u32, x: f32, y: f32));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_state, (id: u32, state: u8));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_state, (id: u32) -> Option<u8>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_particle_emitter, (config: Table) -> u32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, emit_particles, (id: u32, count: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_particle_emitter_position, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_particle_emitter_active, (id: u32, active: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_particle_emitter, (id: u32, immediate: Option<bool>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ambient_light, (r: f32, g: f32, b: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_light, (config: Table) -> u32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light_position, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light_color, (id: u32, r: f32, g: f32, b: f32, intensity: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light_radius, (id: u32, radius: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light_direction, (id: u32, degrees: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_light_enabled, (id: u32, enabled: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_light, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_normal_map, (texture: String, normal_map: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_post_effect, (name: String, options: Option<Table>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_post_effect_enabled, (name: String, enabled: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_post_effect, (name: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, fade_post_effect, (name: String, strength: f32, duration: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, flash_screen, (r: f32, g: f32, b: f32, strength: f32, duration: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_post_effect, (name: String, path: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, screenshot, (path: Option<String>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, start_recording, (directory: String, every: Option<u32>) -> Option<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_recording, () -> Option<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_recording, () -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_material, (name: String, path: String, options: Option<Table>) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_material_param, (name: String, param: String, value: mlua::Value));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_material, (id: u32, name: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, clear_material, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_entity_material_param, (id: u32, param: String, value: mlua::Value));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_palette, (name: String, paths: mlua::Value) -> u32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_palette, (id: u32, name: String, variant: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, clear_palette, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_facing, (id: u32, name: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_facing_from_velocity, (id: u32, enabled: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_facing, (id: u32) -> Option<String>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_animation_time_scale, (scale: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_animation_speed, (id: u32, scale: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, pause_animation, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, resume_animation, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, seek_animation, (id: u32, frame: usize));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_animation_frame, (id: u32) -> Option<usize>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_animation_graph, (id: u32, graph: Table));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_animation_param, (id: u32, name: String, value: mlua::Value));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, fire_animation_trigger, (id: u32, name: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_tint, (id: u32, r: f32, g: f32, b: f32, a: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_flash, (id: u32, r: f32, g: f32, b: f32, amount: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_opacity, (id: u32, opacity: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, clear_sprite_color, (id: u32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_rotation, (id: u32, radians: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_render_layer, (id: u32, layer: u8, sort_offset: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_render_z, (id: u32, z: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_layer_sort_mode, (layer: u8, mode: u8));
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_window_size, () -> [u32; 2]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_velocity_2d, (id: u32) -> [f32; 2]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_position_2d, (id: u32) -> [f32; 2]);
@@ -539,7 +1732,73 @@ impl Engine {
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_health_table, (id: u32) -> Table);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_body, (data: Table) -> [u32; 2]);
         expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_ui_scene, (data: Table) -> [u32; 1]);
-        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, configure_camera, (data: Table) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, show_ui_scene, (id: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, hide_ui_scene, (id: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ui_element_active, (id: u32, active: bool) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ui_text, (id: u32, text: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ui_progress, (id: u32, value: f32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, bind_ui_health, (id: u32, entity: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, focus_ui_element, (id: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, ui_navigate, (direction: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, ui_activate, () -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_font, (name: String, path: String, size_px: f32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, draw_text, (text: String, x: f32, y: f32, options: Table));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_fps, () -> u32);
+        expose_fn!(
+            self.lua_context.lua,
+            self_ptr,
+            lua_engine,
+            start_text_input,
+            ()
+        );
+        expose_fn!(
+            self.lua_context.lua,
+            self_ptr,
+            lua_engine,
+            stop_text_input,
+            ()
+        );
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_modifiers, () -> Table);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, bind_action, (context: String, action: String, inputs: Table) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, bind_axis, (context: String, axis: String, binding: Table) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, push_input_context, (context: String));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, pop_input_context, () -> Option<String>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_input_context, () -> Option<String>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_action_down, (action: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_action_just_pressed, (action: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_action_just_released, (action: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_action_held_time, (action: String) -> f32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_axis, (axis: String) -> f32);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_down, (input: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, was_pressed_this_frame, (input: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, was_released_this_frame, (input: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, mouse_position_screen, () -> [f32; 2]);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, mouse_position_world, () -> [f32; 2]);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_last_input, () -> Option<String>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, save_input_bindings, (path: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_input_bindings, (path: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, load_sound, (path: String) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, play_sound, (path: String, options: Option<Table>) -> Option<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_sound, (id: u32, fade: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, is_sound_playing, (id: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_sound_position, (id: u32, x: f32, y: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, play_music, (path: String, options: Option<Table>) -> Option<u32>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, stop_music, (fade: Option<f32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_bus_volume, (bus: String, volume: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_master_volume, (volume: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_audio_rolloff, (min_distance: f32, max_distance: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_gamepad_deadzone, (stick: f32, trigger: f32));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, virtual_gamepad_connected, (connected: bool));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, virtual_gamepad_input, (input: String, value: f32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, configure_camera, (config: Table, camera: Option<u32>) -> Result<()>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, create_camera, (config: Table) -> Result<Option<u32>>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, remove_camera, (id: u32) -> bool);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, shake_camera, (trauma: f32, camera: Option<u32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_camera_zoom, (zoom: f32, smooth: Option<bool>, camera: Option<u32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, pan_camera, (x: f32, y: f32, duration: f32, camera: Option<u32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_split_screen, (layout: String, targets: Vec<u32>) -> Result<Vec<u32>>);
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, set_ui_scene_camera, (scene: u32, camera: Option<u32>));
+        expose_fn!(self.lua_context.lua, self_ptr, lua_engine, get_camera_position, (camera: Option<u32>) -> Option<[f32; 2]>);
 
         let now_ns = self
             .lua_context
@@ -561,44 +1820,282 @@ impl Engine {
             .globals()
             .set("engine", lua_engine)
             .expect("Could not define global engine");
+    }
 
-        let config: mlua::Table = self
-            .lua_context
-            .get_function("ENGINE_load")
-            .call::<mlua::Table>({})
-            .expect("Unable to load initial assets.");
-
-        let assets = config
-            .get::<mlua::Table>("assets")
-            .unwrap_or_else(|_| self.lua_context.create_table());
-        for asset in assets.sequence_values::<String>() {
-            let asset = asset.unwrap_or("".to_string());
-            if asset != "" {
-                let _ = self.get_texture(asset.clone());
-            };
+    /// Feeds the input map and the optional raw `ENGINE_input_event` callback.
+    fn call_lua_input(&mut self, input: &str, is_pressed: bool) {
+        self.input_map
+            .record(input, is_pressed, if is_pressed { 1.0 } else { 0.0 });
+        if let Some(on_input) = self.lua_context.get_optional_function("ENGINE_input_event") {
+            let _ = on_input.call::<()>((
+                input,
+                is_pressed,
+                self.screen_to_world(self.mouse_pos),
+                self.get_modifiers(),
+            ));
         }
     }
 
-    fn call_lua_keyboard_input(&self, key: KeyCode, is_pressed: bool) {
-        let _ = self
-            .lua_context
-            .get_function("ENGINE_input_event")
-            .call::<()>((
-                keycode_to_str(key),
+    /// Gamepad inputs reuse `ENGINE_input_event`, with the analog value and pad id after the
+    /// modifiers. Sticks report `gamepad_leftx`... as values and `gamepad_leftstick_up`... as presses.
+    fn call_lua_gamepad_input(&mut self, input: &str, is_pressed: bool, value: f32, id: u32) {
+        self.input_map.record(input, is_pressed, value);
+        if let Some(on_input) = self.lua_context.get_optional_function("ENGINE_input_event") {
+            let _ = on_input.call::<()>((
+                input,
                 is_pressed,
                 self.screen_to_world(self.mouse_pos),
+                self.get_modifiers(),
+                value,
+                id,
             ));
+        }
     }
 
-    fn call_lua_mouse_button_input(&self, button: MouseButton, is_pressed: bool) {
-        let _ = self
-            .lua_context
-            .get_function("ENGINE_input_event")
-            .call::<()>((
-                mousebutton_to_str(button),
-                is_pressed,
+    fn poll_gamepads(&mut self) {
+        for signal in self.gamepads.poll() {
+            match signal {
+                GamepadSignal::Connected { id, name } => {
+                    if let Some(on_connected) = self
+                        .lua_context
+                        .get_optional_function("ENGINE_gamepad_connected")
+                    {
+                        let _ = on_connected.call::<()>((id, name));
+                    }
+                }
+                GamepadSignal::Disconnected { id } => {
+                    if let Some(on_disconnected) = self
+                        .lua_context
+                        .get_optional_function("ENGINE_gamepad_disconnected")
+                    {
+                        let _ = on_disconnected.call::<()>(id);
+                    }
+                }
+                GamepadSignal::Input {
+                    id,
+                    name,
+                    pressed,
+                    value,
+                } => {
+                    if !self.ui_gamepad_input(name, pressed) {
+                        self.call_lua_gamepad_input(name, pressed, value, id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `inputs` is a list of input names, e.g. `{ "space", "gamepad_a" }`.
+    fn bind_action(&mut self, context: String, action: String, inputs: Table) -> bool {
+        let inputs = inputs.sequence_values::<String>().flatten().collect();
+        self.input_map.bind_action(&context, &action, inputs)
+    }
+
+    /// `binding` is `{ negative = { ... }, positive = { ... }, analog = { ... } }`.
+    fn bind_axis(&mut self, context: String, axis: String, binding: Table) -> bool {
+        let list = |key: &str| -> Vec<String> {
+            binding
+                .get::<Table>(key)
+                .map(|inputs| inputs.sequence_values::<String>().flatten().collect())
+                .unwrap_or_default()
+        };
+        let binding = AxisBinding {
+            negative: list("negative"),
+            positive: list("positive"),
+            analog: list("analog"),
+        };
+        self.input_map.bind_axis(&context, &axis, binding)
+    }
+
+    fn push_input_context(&mut self, context: String) {
+        self.input_map.push_context(&context);
+    }
+
+    fn pop_input_context(&mut self) -> Option<String> {
+        self.input_map.pop_context()
+    }
+
+    fn get_input_context(&self) -> Option<String> {
+        self.input_map.active_context().cloned()
+    }
+
+    fn is_action_down(&self, action: String) -> bool {
+        self.input_map.action(&action).down
+    }
+
+    fn is_action_just_pressed(&self, action: String) -> bool {
+        self.input_map.action(&action).just_pressed
+    }
+
+    fn is_action_just_released(&self, action: String) -> bool {
+        self.input_map.action(&action).just_released
+    }
+
+    fn get_action_held_time(&self, action: String) -> f32 {
+        self.input_map.action(&action).held_for
+    }
+
+    fn get_axis(&self, axis: String) -> f32 {
+        self.input_map.axis(&axis)
+    }
+
+    /// Polled state, `input` is any name `ENGINE_input_event` reports ("w", "mouseleft"...).
+    fn is_down(&self, input: String) -> bool {
+        self.input_map.is_down(&input)
+    }
+
+    fn was_pressed_this_frame(&self, input: String) -> bool {
+        self.input_map.was_pressed_this_frame(&input)
+    }
+
+    fn was_released_this_frame(&self, input: String) -> bool {
+        self.input_map.was_released_this_frame(&input)
+    }
+
+    fn mouse_position_screen(&self) -> [f32; 2] {
+        self.mouse_pos
+    }
+
+    fn mouse_position_world(&self) -> [f32; 2] {
+        self.screen_to_world(self.mouse_pos)
+    }
+
+    fn get_last_input(&self) -> Option<String> {
+        self.input_map.last_pressed()
+    }
+
+    fn save_input_bindings(&self, path: String) -> bool {
+        match std::fs::write(&path, self.input_map.to_config_string()) {
+            Ok(_) => true,
+            Err(e) => {
+                println!("Could not save input bindings to {}: {}", path, e);
+                false
+            }
+        }
+    }
+
+    /// Saved bindings override the defaults scripts bound, a missing file is not an error.
+    fn load_input_bindings(&mut self, path: String) -> bool {
+        let config = match std::fs::read_to_string(&path) {
+            Ok(config) => config,
+            Err(_) => return false,
+        };
+        for line in self.input_map.apply_config_string(&config) {
+            println!("Skipping input binding in {}: {}", path, line);
+        }
+        true
+    }
+
+    fn set_gamepad_deadzone(&mut self, stick: f32, trigger: f32) {
+        self.gamepads.stick_deadzone = stick.clamp(0.0, 0.95);
+        self.gamepads.trigger_deadzone = trigger.clamp(0.0, 0.95);
+    }
+
+    /// Scripted pad for demos and automated playtests, `connected` plugs it in or out.
+    fn virtual_gamepad_connected(&mut self, connected: bool) {
+        if connected {
+            self.virtual_gamepad.connect("Virtual Gamepad");
+        } else {
+            self.virtual_gamepad.disconnect();
+        }
+    }
+
+    /// `input` is a button (`gamepad_a`, 0..1) or stick axis (`gamepad_leftx`, -1..1) name.
+    fn virtual_gamepad_input(&mut self, input: String, value: f32) -> bool {
+        if let Some(button) = GamepadButton::from_name(&input) {
+            self.virtual_gamepad.set_button(button, value);
+        } else if let Some(axis) = GamepadAxis::from_name(&input) {
+            self.virtual_gamepad.set_axis(axis, value);
+        } else {
+            println!("Unknown gamepad input: {}", input);
+            return false;
+        }
+        true
+    }
+
+    fn call_lua_keyboard_input(&mut self, key: KeyCode, is_pressed: bool) {
+        if let Some(name) = keycode_to_str(key) {
+            self.call_lua_input(name, is_pressed);
+        }
+    }
+
+    fn call_lua_mouse_button_input(&mut self, button: MouseButton, is_pressed: bool) {
+        self.call_lua_input(&mousebutton_to_str(button), is_pressed);
+    }
+
+    /// Scrolling is reported twice: as `ENGINE_mouse_wheel(dx, dy, ...)` when the script defines it,
+    /// and as wheelup/wheeldown/wheelleft/wheelright taps so it can be bound like a key.
+    fn call_lua_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let (dx, dy) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x, y),
+            MouseScrollDelta::PixelDelta(p) => (
+                p.x as f32 / PIXELS_PER_SCROLL_LINE,
+                p.y as f32 / PIXELS_PER_SCROLL_LINE,
+            ),
+        };
+
+        if let Some(on_wheel) = self.lua_context.get_optional_function("ENGINE_mouse_wheel") {
+            let _ = on_wheel.call::<()>((
+                dx,
+                dy,
                 self.screen_to_world(self.mouse_pos),
+                self.get_modifiers(),
             ));
+        }
+
+        let taps = [
+            (dy > 0.0, "wheelup"),
+            (dy < 0.0, "wheeldown"),
+            (dx < 0.0, "wheelleft"),
+            (dx > 0.0, "wheelright"),
+        ];
+        for (_, name) in taps.iter().filter(|(scrolled, _)| *scrolled) {
+            self.call_lua_input(name, true);
+            self.call_lua_input(name, false);
+        }
+    }
+
+    /// Typed text goes to `ENGINE_text_input(text)` while text input is on.
+    fn call_lua_text_input(&self, text: &str) {
+        // backspace, enter, tab... arrive as key events already
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return;
+        }
+        if let Some(on_text) = self.lua_context.get_optional_function("ENGINE_text_input") {
+            let _ = on_text.call::<()>(text);
+        }
+    }
+
+    /// IME composition that has not been committed yet, empty when it is cleared.
+    fn call_lua_text_preedit(&self, text: &str, cursor: Option<(usize, usize)>) {
+        if let Some(on_preedit) = self
+            .lua_context
+            .get_optional_function("ENGINE_text_preedit")
+        {
+            let (start, end) = cursor.unwrap_or((text.len(), text.len()));
+            let _ = on_preedit.call::<()>((text.to_string(), start, end));
+        }
+    }
+
+    /// Turns on IME and `ENGINE_text_input` for name entry fields.
+    fn start_text_input(&mut self) {
+        self.text_input_active = true;
+        if let Some(window) = &self.window {
+            window.set_ime_allowed(true);
+        }
+    }
+
+    fn stop_text_input(&mut self) {
+        self.text_input_active = false;
+        if let Some(window) = &self.window {
+            window.set_ime_allowed(false);
+        }
+    }
+
+    fn get_modifiers(&self) -> Table {
+        modifiers_to_lua(&self.lua_context.lua, self.modifiers)
+            .unwrap_or_else(|_| self.lua_context.create_table())
     }
 }
 
@@ -610,7 +2107,7 @@ impl ApplicationHandler<Graphics3D> for Engine {
         self.fps.frame_count += 1;
         self.fps.time_accum += dt.as_secs_f32();
         if self.fps.time_accum > 1.0 {
-            println!("FPS: {:?}", self.fps.frame_count);
+            self.fps.last = self.fps.frame_count;
             self.fps.time_accum = 0.0;
             self.fps.frame_count = 0;
         }
@@ -626,16 +2123,16 @@ impl ApplicationHandler<Graphics3D> for Engine {
         self.last_frame = now;
         let bp = Instant::now();
         let _ = self.update(dt);
+        self.update_cameras(dt.as_secs_f32());
+        // last, input callbacks and cameras also move things after the update
+        self.world.update_sprite_index();
 
         //println!("Physics: {:?}", bp.elapsed().as_secs_f64());
 
-        let graphics = match &mut self.graphics {
-            Some(canvas) => canvas,
-            None => return,
-        };
-        let _ = graphics.update_camera();
         let bg = Instant::now();
-        let _ = graphics.render(&self.world, &self.canvas, &self.physics);
+        if !self.render_frame() {
+            return;
+        }
         //println!("Render: {:?}", bg.elapsed().as_secs_f64());
 
         self.count += 1;
@@ -675,6 +2172,7 @@ impl ApplicationHandler<Graphics3D> for Engine {
             WindowEvent::Resized(size) => {
                 self.width = size.width;
                 self.height = size.height;
+                self.canvas.resize(size.width, size.height);
                 let graphics = match &mut self.graphics {
                     Some(canvas) => canvas,
                     None => return,
@@ -686,11 +2184,25 @@ impl ApplicationHandler<Graphics3D> for Engine {
                 state,
                 button,
             } => {
-                self.call_lua_mouse_button_input(button, state.is_pressed());
+                // clicks on widgets stay in the UI
+                let consumed = match (button, state.is_pressed()) {
+                    (MouseButton::Left, true) => self.canvas.pointer_pressed(self.mouse_pos),
+                    (MouseButton::Left, false) if self.canvas.is_pressing() => {
+                        if let Some(click) = self.canvas.pointer_released(self.mouse_pos) {
+                            self.call_lua_ui_click(click);
+                        }
+                        true
+                    }
+                    _ => false,
+                };
+                if !consumed {
+                    self.call_lua_mouse_button_input(button, state.is_pressed());
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
                 // Update mouse position
                 self.mouse_pos = [position.x as f32, position.y as f32];
+                self.canvas.pointer_moved(self.mouse_pos);
             }
             WindowEvent::RedrawRequested => {
                 let graphics = match &mut self.graphics {
@@ -706,6 +2218,7 @@ impl ApplicationHandler<Graphics3D> for Engine {
                     KeyEvent {
                         physical_key: PhysicalKey::Code(code),
                         state,
+                        ref text,
                         ..
                     },
                 ..
@@ -714,8 +2227,25 @@ impl ApplicationHandler<Graphics3D> for Engine {
                     (KeyCode::Escape, true) => event_loop.exit(),
                     _ => {}
                 };
-                self.call_lua_keyboard_input(code, state.is_pressed());
+                if !self.ui_key_input(code, state.is_pressed()) {
+                    self.call_lua_keyboard_input(code, state.is_pressed());
+                }
+                if let (true, true, Some(text)) = (self.text_input_active, state.is_pressed(), text)
+                {
+                    self.call_lua_text_input(text);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.call_lua_mouse_wheel(delta);
             }
+            WindowEvent::Ime(ref ime) if self.text_input_active => match ime {
+                Ime::Commit(text) => self.call_lua_text_input(text),
+                Ime::Preedit(text, cursor) => self.call_lua_text_preedit(text, *cursor),
+                Ime::Enabled | Ime::Disabled => {}
+            },
             _ => {}
         }
 
@@ -729,14 +2259,16 @@ impl ApplicationHandler<Graphics3D> for Engine {
     }
 
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // frames still being read back would be lost with the device
+        self.save_captured_frames(true);
         self.cleanup()
     }
 }
 
 #[derive(Debug)]
 struct LuaCameraKeyBinding {
-    key: String,
-    action: String,
+    key: KeyCode,
+    action: CameraAction,
 }
 
 #[derive(Debug)]
@@ -759,7 +2291,11 @@ impl LuaCameraConfig {
             let key: String = key_binding_table.get("key")?;
             let action: String = key_binding_table.get("action")?;
 
-            keys.push(LuaCameraKeyBinding { key, action });
+            keys.push(LuaCameraKeyBinding {
+                key: str_to_keycode(&key)
+                    .ok_or_else(|| mlua::Error::RuntimeError(format!("Unknown key: {}", key)))?,
+                action: Self::parse_camera_action(&action)?,
+            });
         }
 
         Ok(LuaCameraConfig {
@@ -769,24 +2305,6 @@ impl LuaCameraConfig {
         })
     }
 
-    fn parse_keycode(s: &str) -> Result<KeyCode> {
-        use KeyCode::*;
-        let code = match s {
-            "W" => KeyW,
-            "A" => KeyA,
-            "S" => KeyS,
-            "D" => KeyD,
-            "Q" => KeyQ,
-            "E" => KeyE,
-            "Up" => ArrowUp,
-            "Down" => ArrowDown,
-            "Left" => ArrowLeft,
-            "Right" => ArrowRight,
-            other => return Err(mlua::Error::RuntimeError(format!("Unknown key: {}", other))),
-        };
-        Ok(code)
-    }
-
     fn parse_camera_action(s: &str) -> Result<CameraAction> {
         use CameraAction::*;
         let action = match s {
@@ -816,4 +2334,119 @@ impl LuaCameraConfig {
 struct FPS {
     pub frame_count: u32,
     pub time_accum: f32,
+    pub last: u32, // frames counted over the last full second
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 48;
+    const DT: f32 = 0.016;
+
+    fn camera_config() -> Camera2DConfig {
+        Camera2DConfig {
+            zoom: 10.0,
+            initial_position: [0.0, 0.0],
+            look_ahead_smooth_factor: 0.0,
+            look_ahead_distance: 0.0,
+            look_ahead_lerp_speed: 0.0,
+            screen_width: WIDTH as f32,
+            screen_height: HEIGHT as f32,
+            pixel_perfect: None,
+        }
+    }
+
+    // boxed, the Lua api holds a pointer to the engine
+    fn engine() -> Box<Engine> {
+        let lua = mlua::Lua::new();
+        lua.load(r#"package.path = package.path .. ";./src/scripts/?.lua""#)
+            .exec()
+            .unwrap();
+        let config = EngineConfig {
+            fps: "auto".to_string(),
+            debug_enabled: false,
+            width: WIDTH,
+            height: HEIGHT,
+            dimensions: Dimensions::Two,
+            camera: CameraOption::Independent,
+            camera2d_config: camera_config(),
+        };
+        let mut engine = Box::new(Engine::new(config, LuaExtendedExecutor { lua }));
+        engine.expose_lua_api();
+        engine
+    }
+
+    fn lua(engine: &Engine, code: &str) {
+        engine.lua_context.lua.load(code).exec().unwrap();
+    }
+
+    // one frame of the input and the capture system, as `update` runs them
+    fn frame(engine: &mut Engine, events: &[(&str, bool)]) {
+        for (input, is_pressed) in events {
+            engine.call_lua_input(input, *is_pressed);
+        }
+        engine.input_map.update(DT);
+        lua(engine, r#"require("systems.capture").update()"#);
+        engine.input_map.end_frame();
+    }
+
+    // held down for a frame, the way a key usually is
+    fn tap(engine: &mut Engine, input: &str) {
+        frame(engine, &[(input, true)]);
+        frame(engine, &[(input, false)]);
+    }
+
+    #[test]
+    fn shipped_capture_keys_work_in_every_context() {
+        let mut engine = engine();
+        lua(&engine, r#"require("systems.input_bindings").setup()"#);
+        for context in ["gameplay", "menu"] {
+            engine.push_input_context(context.to_string());
+
+            tap(&mut engine, "f12");
+            assert!(engine.frame_capture.begin_frame().is_some(), "{}", context);
+
+            tap(&mut engine, "f9");
+            assert!(engine.frame_capture.is_recording(), "{}", context);
+            tap(&mut engine, "f9");
+            assert!(!engine.frame_capture.is_recording(), "{}", context);
+        }
+    }
+
+    #[test]
+    fn lua_screenshot_is_saved_from_the_next_frame() {
+        let graphics = match pollster::block_on(Graphics2D::new_headless(
+            WIDTH,
+            HEIGHT,
+            Camera2D::new(&camera_config()),
+        )) {
+            Ok(graphics) => graphics,
+            Err(e) => {
+                println!("skipping, no software adapter: {}", e);
+                return;
+            }
+        };
+        let mut engine = engine();
+        engine.graphics = Some(Box::new(graphics));
+
+        let path = std::env::temp_dir().join(format!("screenshot_{}.png", std::process::id()));
+        engine
+            .lua_context
+            .lua
+            .globals()
+            .set("path", path.to_string_lossy().to_string())
+            .unwrap();
+        lua(&engine, "engine.screenshot(path)");
+
+        assert!(engine.render_frame());
+        engine.save_captured_frames(true);
+        // the writer finishes the frames it was handed before the engine is gone
+        drop(engine);
+
+        let image = image::open(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (WIDTH, HEIGHT));
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::JoinHandle;
+
+use image::RgbaImage;
+
+// frames waiting for the writer, past this the game waits for the disk
+const MAX_QUEUED_FRAMES: usize = 8;
+
+struct Recording {
+    directory: PathBuf,
+    every: u32, // keeps one frame in `every`
+    frames_seen: u32,
+    frames_saved: u32,
+}
+
+/// Screenshots and frame sequences requested from Lua. Frames are encoded to PNG on a
+/// writer thread so recording doesn't stall the game on every frame.
+pub struct FrameCapture {
+    screenshots: Vec<PathBuf>, // taken after the next frame
+    recording: Option<Recording>,
+    pending: HashMap<u64, Vec<PathBuf>>, // paths of frames still on their way back from the GPU
+    next_id: u64,
+    sender: Option<SyncSender<(PathBuf, RgbaImage)>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        let (sender, receiver) = sync_channel::<(PathBuf, RgbaImage)>(MAX_QUEUED_FRAMES);
+        let writer = std::thread::spawn(move || {
+            for (path, image) in receiver {
+                if let Some(directory) = path.parent() {
+                    let _ = std::fs::create_dir_all(directory);
+                }
+                if let Err(e) = image.save(&path) {
+                    println!("Failed to save frame {}: {}", path.display(), e);
+                }
+            }
+        });
+        Self {
+            screenshots: Vec::new(),
+            recording: None,
+            pending: HashMap::new(),
+            next_id: 0,
+            sender: Some(sender),
+            writer: Some(writer),
+        }
+    }
+
+    pub fn screenshot(&mut self, path: PathBuf) {
+        self.screenshots.push(path);
+    }
+
+    /// Replaces a recording already running, returning how many frames that one saved.
+    pub fn start_recording(&mut self, directory: PathBuf, every: u32) -> Option<u32> {
+        let previous = self.stop_recording();
+        self.recording = Some(Recording {
+            directory,
+            every: every.max(1),
+            frames_seen: 0,
+            frames_saved: 0,
+        });
+        previous
+    }
+
+    pub fn stop_recording(&mut self) -> Option<u32> {
+        self.recording
+            .take()
+            .map(|recording| recording.frames_saved)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Counts the frame about to be rendered toward the recording. Returns the id it has to
+    /// be captured as when a screenshot or the recording wants it.
+    pub fn begin_frame(&mut self) -> Option<u64> {
+        let mut paths: Vec<PathBuf> = self.screenshots.drain(..).collect();
+        if let Some(recording) = self.recording.as_mut() {
+            recording.frames_seen += 1;
+            if (recording.frames_seen - 1) % recording.every == 0 {
+                paths.push(
+                    recording
+                        .directory
+                        .join(format!("frame_{:05}.png", recording.frames_saved)),
+                );
+                recording.frames_saved += 1;
+            }
+        }
+        if paths.is_empty() {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, paths);
+        Some(id)
+    }
+
+    /// Queues captured frame `id` for the paths it was requested under.
+    pub fn save(&mut self, id: u64, image: RgbaImage) {
+        let Some(paths) = self.pending.remove(&id) else {
+            return;
+        };
+        let Some(sender) = self.sender.as_ref() else {
+            return;
+        };
+        for path in paths {
+            let _ = sender.send((path, image.clone()));
+        }
+    }
+}
+
+impl Drop for FrameCapture {
+    /// Lets the writer finish the frames already queued.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Default screenshot path, named after the time it was taken.
+pub fn screenshot_path(directory: &Path) -> PathBuf {
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    directory.join(format!("screenshot_{}.png", time))
+}
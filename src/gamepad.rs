@@ -0,0 +1,652 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+/// Raw values as a backend reports them, before deadzones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected {
+        id: u32,
+        name: String,
+    },
+    Disconnected {
+        id: u32,
+    },
+    Button {
+        id: u32,
+        button: GamepadButton,
+        value: f32,
+    },
+    Axis {
+        id: u32,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// What the engine hands to Lua, one per changed input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadSignal {
+    Connected {
+        id: u32,
+        name: String,
+    },
+    Disconnected {
+        id: u32,
+    },
+    Input {
+        id: u32,
+        name: &'static str,
+        pressed: bool,
+        value: f32,
+    },
+}
+
+/// Anything that produces gamepad events: real devices or a scripted pad.
+pub trait GamepadSource {
+    fn poll(&mut self) -> Vec<GamepadEvent>;
+}
+
+// analog values past this count as pressed, also for stick directions
+const PRESS_THRESHOLD: f32 = 0.5;
+
+impl GamepadButton {
+    pub fn name(self) -> &'static str {
+        use GamepadButton::*;
+        match self {
+            South => "gamepad_a",
+            East => "gamepad_b",
+            West => "gamepad_x",
+            North => "gamepad_y",
+            LeftBumper => "gamepad_lb",
+            RightBumper => "gamepad_rb",
+            LeftTrigger => "gamepad_lt",
+            RightTrigger => "gamepad_rt",
+            Select => "gamepad_select",
+            Start => "gamepad_start",
+            Mode => "gamepad_mode",
+            LeftStick => "gamepad_ls",
+            RightStick => "gamepad_rs",
+            DPadUp => "gamepad_up",
+            DPadDown => "gamepad_down",
+            DPadLeft => "gamepad_left",
+            DPadRight => "gamepad_right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        use GamepadButton::*;
+        [
+            South,
+            East,
+            West,
+            North,
+            LeftBumper,
+            RightBumper,
+            LeftTrigger,
+            RightTrigger,
+            Select,
+            Start,
+            Mode,
+            LeftStick,
+            RightStick,
+            DPadUp,
+            DPadDown,
+            DPadLeft,
+            DPadRight,
+        ]
+        .into_iter()
+        .find(|button| button.name() == name.to_lowercase())
+    }
+
+    fn is_trigger(self) -> bool {
+        matches!(
+            self,
+            GamepadButton::LeftTrigger | GamepadButton::RightTrigger
+        )
+    }
+}
+
+impl GamepadAxis {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "gamepad_leftx" => Some(GamepadAxis::LeftStickX),
+            "gamepad_lefty" => Some(GamepadAxis::LeftStickY),
+            "gamepad_rightx" => Some(GamepadAxis::RightStickX),
+            "gamepad_righty" => Some(GamepadAxis::RightStickY),
+            _ => None,
+        }
+    }
+
+    // (stick index, component index)
+    fn slot(self) -> (usize, usize) {
+        match self {
+            GamepadAxis::LeftStickX => (0, 0),
+            GamepadAxis::LeftStickY => (0, 1),
+            GamepadAxis::RightStickX => (1, 0),
+            GamepadAxis::RightStickY => (1, 1),
+        }
+    }
+}
+
+// [stick][x, y] names for the analog value and for the digital directions
+const STICK_AXIS_NAMES: [[&str; 2]; 2] = [
+    ["gamepad_leftx", "gamepad_lefty"],
+    ["gamepad_rightx", "gamepad_righty"],
+];
+// [stick][left, right, down, up]
+const STICK_DIRECTION_NAMES: [[&str; 4]; 2] = [
+    [
+        "gamepad_leftstick_left",
+        "gamepad_leftstick_right",
+        "gamepad_leftstick_down",
+        "gamepad_leftstick_up",
+    ],
+    [
+        "gamepad_rightstick_left",
+        "gamepad_rightstick_right",
+        "gamepad_rightstick_down",
+        "gamepad_rightstick_up",
+    ],
+];
+
+pub fn is_gamepad_input(name: &str) -> bool {
+    GamepadButton::from_name(name).is_some()
+        || GamepadAxis::from_name(name).is_some()
+        || STICK_DIRECTION_NAMES
+            .iter()
+            .flatten()
+            .any(|direction| direction.eq_ignore_ascii_case(name))
+}
+
+/// A pad driven from code instead of a device. Clones share the same queue, so a test or
+/// script can keep one handle while the engine polls the other.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualGamepad {
+    id: u32,
+    queue: Rc<RefCell<VecDeque<GamepadEvent>>>,
+}
+
+impl VirtualGamepad {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            queue: Rc::default(),
+        }
+    }
+
+    fn push(&self, event: GamepadEvent) {
+        self.queue.borrow_mut().push_back(event);
+    }
+
+    pub fn connect(&self, name: &str) {
+        self.push(GamepadEvent::Connected {
+            id: self.id,
+            name: name.to_string(),
+        });
+    }
+
+    pub fn disconnect(&self) {
+        self.push(GamepadEvent::Disconnected { id: self.id });
+    }
+
+    pub fn set_button(&self, button: GamepadButton, value: f32) {
+        self.push(GamepadEvent::Button {
+            id: self.id,
+            button,
+            value,
+        });
+    }
+
+    pub fn set_axis(&self, axis: GamepadAxis, value: f32) {
+        self.push(GamepadEvent::Axis {
+            id: self.id,
+            axis,
+            value,
+        });
+    }
+}
+
+impl GamepadSource for VirtualGamepad {
+    fn poll(&mut self) -> Vec<GamepadEvent> {
+        self.queue.borrow_mut().drain(..).collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct PadState {
+    buttons: HashMap<GamepadButton, f32>, // after deadzone
+    raw_sticks: [[f32; 2]; 2],
+    sticks: [[f32; 2]; 2], // after deadzone
+}
+
+/// Applies deadzones and turns raw backend values into per-input changes.
+pub struct GamepadInput {
+    sources: Vec<Box<dyn GamepadSource>>,
+    pads: HashMap<u32, PadState>,
+    pub stick_deadzone: f32,
+    pub trigger_deadzone: f32,
+}
+
+impl GamepadInput {
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            pads: HashMap::new(),
+            stick_deadzone: 0.15,
+            trigger_deadzone: 0.05,
+        }
+    }
+
+    pub fn add_source(&mut self, source: Box<dyn GamepadSource>) {
+        self.sources.push(source);
+    }
+
+    pub fn poll(&mut self) -> Vec<GamepadSignal> {
+        let events: Vec<GamepadEvent> = self
+            .sources
+            .iter_mut()
+            .flat_map(|source| source.poll())
+            .collect();
+        let mut signals = Vec::new();
+        for event in events {
+            self.process(event, &mut signals);
+        }
+        signals
+    }
+
+    fn process(&mut self, event: GamepadEvent, signals: &mut Vec<GamepadSignal>) {
+        match event {
+            GamepadEvent::Connected { id, name } => {
+                self.pads.insert(id, PadState::default());
+                signals.push(GamepadSignal::Connected { id, name });
+            }
+            GamepadEvent::Disconnected { id } => {
+                // let go of everything the pad was holding so nothing stays stuck
+                if let Some(pad) = self.pads.remove(&id) {
+                    for (button, value) in pad.buttons {
+                        if value > 0.0 {
+                            signals.push(input(id, button.name(), 0.0));
+                        }
+                    }
+                    for stick in 0..2 {
+                        push_stick_changes(id, stick, pad.sticks[stick], [0.0, 0.0], signals);
+                    }
+                }
+                signals.push(GamepadSignal::Disconnected { id });
+            }
+            GamepadEvent::Button { id, button, value } => {
+                let value = if button.is_trigger() {
+                    apply_deadzone(value, self.trigger_deadzone)
+                } else {
+                    value.clamp(0.0, 1.0)
+                };
+                let pad = self.pads.entry(id).or_default();
+                let previous = pad.buttons.insert(button, value).unwrap_or(0.0);
+                let was_pressed = previous >= PRESS_THRESHOLD;
+                let is_pressed = value >= PRESS_THRESHOLD;
+                // triggers report every analog change, digital buttons only flips
+                if was_pressed != is_pressed || (button.is_trigger() && previous != value) {
+                    signals.push(input(id, button.name(), value));
+                }
+            }
+            GamepadEvent::Axis { id, axis, value } => {
+                let deadzone = self.stick_deadzone;
+                let pad = self.pads.entry(id).or_default();
+                let (stick, component) = axis.slot();
+                pad.raw_sticks[stick][component] = value.clamp(-1.0, 1.0);
+                let next = apply_radial_deadzone(pad.raw_sticks[stick], deadzone);
+                let previous = std::mem::replace(&mut pad.sticks[stick], next);
+                push_stick_changes(id, stick, previous, next, signals);
+            }
+        }
+    }
+}
+
+fn input(id: u32, name: &'static str, value: f32) -> GamepadSignal {
+    GamepadSignal::Input {
+        id,
+        name,
+        pressed: value.abs() >= PRESS_THRESHOLD,
+        value,
+    }
+}
+
+fn push_stick_changes(
+    id: u32,
+    stick: usize,
+    previous: [f32; 2],
+    next: [f32; 2],
+    signals: &mut Vec<GamepadSignal>,
+) {
+    for component in 0..2 {
+        if previous[component] != next[component] {
+            signals.push(GamepadSignal::Input {
+                id,
+                name: STICK_AXIS_NAMES[stick][component],
+                pressed: next[component] != 0.0,
+                value: next[component],
+            });
+        }
+    }
+
+    // digital directions so sticks can be bound like the d-pad
+    let directions = [
+        (-previous[0], -next[0]),
+        (previous[0], next[0]),
+        (-previous[1], -next[1]),
+        (previous[1], next[1]),
+    ];
+    for (direction, (before, after)) in directions.into_iter().enumerate() {
+        let was_pressed = before >= PRESS_THRESHOLD;
+        let is_pressed = after >= PRESS_THRESHOLD;
+        if was_pressed != is_pressed {
+            signals.push(GamepadSignal::Input {
+                id,
+                name: STICK_DIRECTION_NAMES[stick][direction],
+                pressed: is_pressed,
+                value: after.max(0.0),
+            });
+        }
+    }
+}
+
+// rescales so the output still covers 0..1 past the deadzone
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= deadzone {
+        0.0
+    } else {
+        (value - deadzone) / (1.0 - deadzone)
+    }
+}
+
+// on the stick's length so diagonals don't snap to the axes
+fn apply_radial_deadzone(stick: [f32; 2], deadzone: f32) -> [f32; 2] {
+    let length = (stick[0] * stick[0] + stick[1] * stick[1]).sqrt();
+    if length <= deadzone {
+        return [0.0, 0.0];
+    }
+    let scaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    [stick[0] / length * scaled, stick[1] / length * scaled]
+}
+
+#[cfg(feature = "gamepad")]
+pub use gilrs_source::GilrsSource;
+
+#[cfg(feature = "gamepad")]
+mod gilrs_source {
+    use super::{GamepadAxis, GamepadButton, GamepadEvent, GamepadSource};
+    use gilrs::{Axis, Button, EventType, Gilrs};
+
+    /// Physical controllers through gilrs.
+    pub struct GilrsSource {
+        gilrs: Gilrs,
+        pending: Vec<GamepadEvent>,
+    }
+
+    impl GilrsSource {
+        pub fn new() -> Option<Self> {
+            match Gilrs::new() {
+                Ok(gilrs) => {
+                    // pads plugged in before the engine started never send Connected
+                    let pending = gilrs
+                        .gamepads()
+                        .map(|(id, pad)| GamepadEvent::Connected {
+                            id: usize::from(id) as u32,
+                            name: pad.name().to_string(),
+                        })
+                        .collect();
+                    Some(Self { gilrs, pending })
+                }
+                Err(e) => {
+                    println!("Gamepads unavailable: {}", e);
+                    None
+                }
+            }
+        }
+    }
+
+    fn map_button(button: Button) -> Option<GamepadButton> {
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::West => GamepadButton::West,
+            Button::North => GamepadButton::North,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::Mode => GamepadButton::Mode,
+            Button::LeftThumb => GamepadButton::LeftStick,
+            Button::RightThumb => GamepadButton::RightStick,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+
+    fn map_axis(axis: Axis) -> Option<GamepadAxis> {
+        Some(match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            _ => return None,
+        })
+    }
+
+    impl GamepadSource for GilrsSource {
+        fn poll(&mut self) -> Vec<GamepadEvent> {
+            let mut events = std::mem::take(&mut self.pending);
+            while let Some(gilrs::Event {
+                id: pad_id, event, ..
+            }) = self.gilrs.next_event()
+            {
+                let id = usize::from(pad_id) as u32;
+                match event {
+                    EventType::Connected => {
+                        let name = self.gilrs.gamepad(pad_id).name().to_string();
+                        events.push(GamepadEvent::Connected { id, name });
+                    }
+                    EventType::Disconnected => events.push(GamepadEvent::Disconnected { id }),
+                    EventType::ButtonChanged(button, value, _) => {
+                        if let Some(button) = map_button(button) {
+                            events.push(GamepadEvent::Button { id, button, value });
+                        }
+                    }
+                    EventType::AxisChanged(axis, value, _) => {
+                        if let Some(axis) = map_axis(axis) {
+                            events.push(GamepadEvent::Axis { id, axis, value });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            events
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad() -> (GamepadInput, VirtualGamepad) {
+        let virtual_gamepad = VirtualGamepad::new(7);
+        let mut gamepads = GamepadInput::new();
+        gamepads.add_source(Box::new(virtual_gamepad.clone()));
+        virtual_gamepad.connect("test pad");
+        gamepads.poll();
+        (gamepads, virtual_gamepad)
+    }
+
+    fn inputs(signals: &[GamepadSignal]) -> Vec<(&'static str, bool, f32)> {
+        signals
+            .iter()
+            .filter_map(|signal| match signal {
+                GamepadSignal::Input {
+                    name,
+                    pressed,
+                    value,
+                    ..
+                } => Some((*name, *pressed, *value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn connect_is_reported_with_the_pad_name() {
+        let virtual_gamepad = VirtualGamepad::new(3);
+        let mut gamepads = GamepadInput::new();
+        gamepads.add_source(Box::new(virtual_gamepad.clone()));
+        virtual_gamepad.connect("test pad");
+        assert_eq!(
+            gamepads.poll(),
+            vec![GamepadSignal::Connected {
+                id: 3,
+                name: "test pad".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn stick_inside_the_radial_deadzone_is_silent() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        // 0.1, 0.1 is ~0.14 long, under the 0.15 deadzone even though neither axis alone is
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, 0.1);
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickY, 0.1);
+        assert!(gamepads.poll().is_empty());
+    }
+
+    #[test]
+    fn radial_deadzone_rescales_along_the_stick_direction() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, 0.6);
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickY, 0.8);
+        let signals = inputs(&gamepads.poll());
+
+        // length 1 stays 1 after rescaling, the direction is kept
+        let last = |name: &str| {
+            signals
+                .iter()
+                .rev()
+                .find(|(n, _, _)| *n == name)
+                .map(|(_, _, value)| *value)
+                .unwrap()
+        };
+        assert_close(last("gamepad_leftx"), 0.6);
+        assert_close(last("gamepad_lefty"), 0.8);
+
+        let [x, y] = apply_radial_deadzone([0.3, 0.0], 0.15);
+        assert_close(x, 0.15 / 0.85);
+        assert_close(y, 0.0);
+    }
+
+    #[test]
+    fn stick_directions_press_past_the_threshold() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, 0.5);
+        let signals = inputs(&gamepads.poll());
+        assert!(!signals
+            .iter()
+            .any(|(name, _, _)| *name == "gamepad_leftstick_right"));
+
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, 0.9);
+        let signals = inputs(&gamepads.poll());
+        assert!(signals
+            .iter()
+            .any(|(name, pressed, _)| *name == "gamepad_leftstick_right" && *pressed));
+
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, 0.0);
+        let signals = inputs(&gamepads.poll());
+        assert!(signals
+            .iter()
+            .any(|(name, pressed, _)| *name == "gamepad_leftstick_right" && !*pressed));
+    }
+
+    #[test]
+    fn buttons_only_report_crossing_the_press_threshold() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        virtual_gamepad.set_button(GamepadButton::South, 0.4);
+        assert!(gamepads.poll().is_empty());
+
+        virtual_gamepad.set_button(GamepadButton::South, 1.0);
+        virtual_gamepad.set_button(GamepadButton::South, 0.9);
+        assert_eq!(inputs(&gamepads.poll()), vec![("gamepad_a", true, 1.0)]);
+
+        virtual_gamepad.set_button(GamepadButton::South, 0.0);
+        assert_eq!(inputs(&gamepads.poll()), vec![("gamepad_a", false, 0.0)]);
+    }
+
+    #[test]
+    fn triggers_report_analog_changes_past_their_deadzone() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        virtual_gamepad.set_button(GamepadButton::RightTrigger, 0.04);
+        assert!(gamepads.poll().is_empty());
+
+        virtual_gamepad.set_button(GamepadButton::RightTrigger, 0.3);
+        let signals = inputs(&gamepads.poll());
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].0, "gamepad_rt");
+        assert!(!signals[0].1);
+        assert_close(signals[0].2, 0.25 / 0.95);
+    }
+
+    #[test]
+    fn disconnect_releases_everything_held() {
+        let (mut gamepads, virtual_gamepad) = pad();
+        virtual_gamepad.set_button(GamepadButton::South, 1.0);
+        virtual_gamepad.set_axis(GamepadAxis::LeftStickX, -1.0);
+        gamepads.poll();
+
+        virtual_gamepad.disconnect();
+        let signals = gamepads.poll();
+        assert_eq!(signals.last(), Some(&GamepadSignal::Disconnected { id: 7 }));
+        let released = inputs(&signals);
+        assert!(released.contains(&("gamepad_a", false, 0.0)));
+        assert!(released.contains(&("gamepad_leftx", false, 0.0)));
+        assert!(released.contains(&("gamepad_leftstick_left", false, 0.0)));
+        assert!(released.iter().all(|(_, pressed, _)| !pressed));
+    }
+}
@@ -1,27 +1,48 @@
 use winit::event::WindowEvent;
 
 use crate::{
-    components_systems::physics2d::PhysicsWorld, texture::Texture, ui_canvas::Canvas, world::World,
+    camera_2d::{Camera2D, CameraId},
+    components_systems::physics2d::PhysicsWorld,
+    texture::Texture,
+    ui_canvas::Canvas,
+    world::World,
 };
 
 pub struct CameraInfo {
     pub zoom: f32,
     pub position: [f32; 3],
+    pub viewport: [f32; 4], // where the world is drawn in the window, x y width height in pixels
 }
 
 pub trait Graphics {
     fn render(&mut self, world: &World, canvas: &Canvas, physics: &PhysicsWorld);
+    /// Also copies the next rendered frame back to the CPU, handed out by `captured_frames`.
+    fn capture_next_frame(&mut self, id: u64);
+    /// Captured frames that have reached the CPU, in the order they were rendered.
+    /// `wait` blocks until every requested frame is back.
+    fn captured_frames(&mut self, wait: bool) -> Vec<(u64, image::RgbaImage)>;
     fn resize(&mut self, width: u32, height: u32);
     fn process_camera_event(&mut self, event: &WindowEvent);
     fn set_background(&mut self, color: wgpu::Color);
     fn update_camera(&mut self);
     fn load_texture_from_path(&mut self, id: &str, path: &str) -> Texture;
+    fn load_texture_from_image(&mut self, id: &str, image: &image::DynamicImage) -> Texture;
+    /// Like `load_texture_from_image`, but shaders read the bytes as stored instead of decoded
+    /// from sRGB. For lookup tables whose texels are compared, not drawn.
+    fn load_data_texture(&mut self, id: &str, image: &image::DynamicImage) -> Texture;
+    /// Pairs an already loaded texture with a normal map for lighting, false if unsupported or unreadable.
+    fn load_normal_map(&mut self, texture: &Texture, path: &str) -> bool;
+    /// Compiles a sprite material's WGSL file, false if unsupported or invalid.
+    fn load_material(&mut self, name: &str, path: &str) -> bool;
+    /// Compiles a custom full-screen effect from a WGSL file, false if unsupported or invalid.
+    fn load_post_effect(&mut self, name: &str, path: &str) -> bool;
     fn get_camera_info(&self) -> CameraInfo;
-    fn move_camera_for_follow(
-        &mut self,
-        position: [f32; 3],
-        velocity: [f32; 3],
-        acceleration: [f32; 3],
-        offset: [f32; 3],
-    );
+    /// A 2D camera to move, zoom or shake, None if unsupported or there's no such camera.
+    fn camera_2d(&mut self, id: CameraId) -> Option<&mut Camera2D>;
+    fn camera_ids(&self) -> Vec<CameraId>;
+    /// Where a camera shows up in the window, `[x, y, width, height]` in window pixels.
+    fn camera_viewport(&self, id: CameraId) -> Option<[f32; 4]>;
+    /// Adds a camera drawing over the others within its viewport, None if unsupported.
+    fn add_camera_2d(&mut self, camera: Camera2D) -> Option<CameraId>;
+    fn remove_camera_2d(&mut self, id: CameraId) -> bool;
 }
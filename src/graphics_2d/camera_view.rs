@@ -0,0 +1,45 @@
+use wgpu::util::DeviceExt;
+
+use crate::{camera_2d::Camera2D, graphics_2d::CameraUniform2D};
+
+/// A camera with the uniform buffer it is drawn through.
+pub struct CameraView {
+    pub camera: Camera2D,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl CameraView {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, camera: Camera2D) -> Self {
+        let mut uniform = CameraUniform2D::new();
+        uniform.update(&camera);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera 2D Buffer"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("2D Camera Bind Group"),
+        });
+        Self {
+            camera,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn write_uniform(&self, queue: &wgpu::Queue) {
+        let mut uniform = CameraUniform2D::new();
+        uniform.update(&self.camera);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+}
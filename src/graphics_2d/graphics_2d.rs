@@ -1,25 +1,33 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Instant;
 
 use cgmath::{ElementWise, Vector2};
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use wgpu::util::DeviceExt;
 use wgpu::wgc::device;
 use wgpu::*;
 use winit::window::Window;
 
-use crate::camera_2d::Camera2D;
+use crate::camera_2d::{Camera2D, CameraId, MAIN_CAMERA};
 use crate::components_systems::physics2d::PhysicsWorld;
 use crate::components_systems::physics_2d::Shape2D;
+use crate::components_systems::{Entity, RenderLayer, ResolvedMaterial};
 use crate::graphics::Graphics;
 use crate::graphics_2d::debug_render_batch::ShapeType;
-use crate::graphics_2d::shape_pipelines::create_2d_pipeline;
+use crate::graphics_2d::light_render_batch::NORMAL_FORMAT;
+use crate::graphics_2d::shape_pipelines::{create_2d_pipeline, create_2d_pipeline_with_targets};
 use crate::graphics_2d::space::Space;
-use crate::graphics_2d::vertex::{DebugInstanceVertex, Vertex};
+use crate::graphics_2d::vertex::{DebugInstanceVertex, ParticleInstanceVertex, Vertex};
 use crate::graphics_2d::world_render_batch::WorldRenderBatch;
 use crate::graphics_2d::DebugRenderBatch;
-use crate::graphics_2d::{CameraUniform2D, ColorVertex, TextureVertex};
+use crate::graphics_2d::LightRenderBatch;
+use crate::graphics_2d::MaterialPipelines;
+use crate::graphics_2d::ParticleRenderBatch;
+use crate::graphics_2d::PixelUpscaler;
+use crate::graphics_2d::PostProcessor;
+use crate::graphics_2d::{CameraUniform2D, CameraView, ColorVertex, TextureVertex};
+use crate::graphics_2d::{FrameReadback, RenderTarget};
 
 use crate::texture::Texture;
 use crate::ui_canvas::Canvas;
@@ -28,15 +36,15 @@ use crate::world::World;
 pub type TextureId = u32;
 
 pub struct Graphics2D {
-    surface: Surface<'static>,
+    surface: Option<Surface<'static>>, // None when headless
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
     is_surface_configured: bool,
     background_color: Color,
-    pub camera: Camera2D,
-    camera_buffer: Buffer,
-    camera_bind_group: BindGroup,
+    cameras: BTreeMap<CameraId, CameraView>, // drawn in id order, the main one first
+    next_camera_id: CameraId,
+    camera_bind_group_layout: BindGroupLayout,
     static_camera_buffer: Buffer,
     static_camera_bind_group: BindGroup,
     vertex_buffer: Buffer,
@@ -50,6 +58,16 @@ pub struct Graphics2D {
     color_shapes_pipeline: RenderPipeline,
     test_pipe: RenderPipeline,
     debug_render_batch: DebugRenderBatch,
+    particle_render_batch: ParticleRenderBatch,
+    light_render_batch: LightRenderBatch,
+    material_pipelines: MaterialPipelines,
+    flat_normal_map: Texture, // bound for sheets without a normal map
+    post_processor: PostProcessor,
+    pixel_upscaler: Option<PixelUpscaler>, // set in pixel-perfect mode
+    capture_target: Option<RenderTarget>,  // captured frames are drawn here, then copied out
+    capture_request: Option<u64>,          // id of the next frame to capture
+    frame_readback: FrameReadback,
+    textures: HashMap<String, Texture>,
     texture_lookup: HashMap<TextureId, String>,
     next_texture_id: TextureId,
 }
@@ -72,8 +90,10 @@ impl Graphics2D {
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats[0];
 
+        // captured frames are copied onto the window instead of drawn twice
+        let copy_dst = surface_caps.usages & TextureUsages::COPY_DST;
         let config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage: TextureUsages::RENDER_ATTACHMENT | copy_dst,
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -82,7 +102,44 @@ impl Graphics2D {
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
+        Self::with_device(Some(surface), device, queue, config, camera)
+    }
+
+    /// Renders without a window, only through `render_to_target` and frame captures.
+    /// Runs on the software adapter so golden images don't depend on the GPU.
+    #[allow(dead_code)]
+    pub async fn new_headless(width: u32, height: u32, camera: Camera2D) -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::None,
+                compatible_surface: None,
+                force_fallback_adapter: true,
+            })
+            .await?;
+        let (device, queue) = adapter.request_device(&DeviceDescriptor::default()).await?;
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let mut graphics = Self::with_device(None, device, queue, config, camera)?;
+        graphics.resize_targets(width, height);
+        Ok(graphics)
+    }
 
+    fn with_device(
+        surface: Option<Surface<'static>>,
+        device: Device,
+        queue: Queue,
+        config: SurfaceConfiguration,
+        camera: Camera2D,
+    ) -> anyhow::Result<Self> {
         let texture_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 entries: &[
@@ -102,6 +159,16 @@ impl Graphics2D {
                         ty: BindingType::Sampler(SamplerBindingType::Filtering),
                         count: None,
                     },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: TextureViewDimension::D2,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("texture_bind_group_layout"),
             });
@@ -130,12 +197,34 @@ impl Graphics2D {
         let debug_shader_instanced = device
             .create_shader_module(include_wgsl!("shaders/2d_camera_and_color_instanced.wgsl"));
 
-        let depth_texture = Texture::create_depth_texture(&device, &config, "2d_depth_texture");
+        // in pixel-perfect mode the world is drawn at the virtual resolution and scaled up
+        let pixel_upscaler = camera
+            .pixel_perfect()
+            .map(|pixel_perfect| PixelUpscaler::new(&device, config.format, *pixel_perfect));
+        let frame_config = match &pixel_upscaler {
+            Some(upscaler) => upscaler.frame_config(&config),
+            None => config.clone(),
+        };
+
+        let depth_texture =
+            Texture::create_depth_texture(&device, &frame_config, "2d_depth_texture");
 
-        let render_pipeline = create_2d_pipeline(
+        // sprites also write their normals for the light pass
+        let render_pipeline = create_2d_pipeline_with_targets(
             "Texture Pipeline",
             &device,
-            config.format,
+            &[
+                Some(ColorTargetState {
+                    format: config.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                }),
+                Some(ColorTargetState {
+                    format: NORMAL_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                }),
+            ],
             &shader,
             &[TextureVertex::desc()],
             &Vec::from([&texture_bind_group_layout, &camera_bind_group_layout]),
@@ -154,7 +243,7 @@ impl Graphics2D {
             config.format,
             &canvas_shader,
             &[TextureVertex::desc()],
-            &Vec::from([&texture_bind_group_layout]),
+            &Vec::from([&texture_bind_group_layout, &camera_bind_group_layout]),
             None,
         );
 
@@ -178,24 +267,8 @@ impl Graphics2D {
             None,
         );
 
-        let mut camera_uniform = CameraUniform2D::new();
-        camera_uniform.update(&camera);
-
-        let camera_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: Some("Camera 2D Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-        });
-        let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("2D Camera Bind Group"),
-        });
-
-        let static_camera_uniform = CameraUniform2D::new();
+        let mut static_camera_uniform = CameraUniform2D::new();
+        static_camera_uniform.static_update(&camera);
         let static_camera_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
             label: Some("Staic Camera 2D Buffer"),
             contents: bytemuck::cast_slice(&[static_camera_uniform]),
@@ -237,6 +310,33 @@ impl Graphics2D {
 
         let debug_render_batch =
             DebugRenderBatch::new(&device, &camera_bind_group_layout, config.format);
+        let particle_render_batch = ParticleRenderBatch::new(
+            &device,
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            config.format,
+        );
+        let light_render_batch =
+            LightRenderBatch::new(&device, &frame_config, &camera_bind_group_layout);
+        let material_pipelines = MaterialPipelines::new(
+            &device,
+            &queue,
+            config.format,
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+        );
+        let flat_normal_map = Texture::from_image_with_format(
+            "flat_normal_map".to_string(),
+            &device,
+            &queue,
+            &DynamicImage::new_rgba8(1, 1),
+            Some("flat_normal_map"),
+            NORMAL_FORMAT,
+        )
+        .unwrap();
+        let post_processor = PostProcessor::new(&device, &queue, &frame_config);
+
+        let main_camera = CameraView::new(&device, &camera_bind_group_layout, camera);
 
         Ok(Self {
             surface,
@@ -250,9 +350,9 @@ impl Graphics2D {
                 g: 57.0 / 255.0,
                 a: 255.0 / 255.0,
             },
-            camera,
-            camera_buffer,
-            camera_bind_group,
+            cameras: BTreeMap::from([(MAIN_CAMERA, main_camera)]),
+            next_camera_id: MAIN_CAMERA + 1,
+            camera_bind_group_layout,
             static_camera_buffer,
             static_camera_bind_group,
             vertex_buffer,
@@ -267,6 +367,16 @@ impl Graphics2D {
             texture_lookup: HashMap::new(),
             color_shapes_pipeline,
             debug_render_batch,
+            particle_render_batch,
+            light_render_batch,
+            material_pipelines,
+            flat_normal_map,
+            post_processor,
+            pixel_upscaler,
+            capture_target: None,
+            capture_request: None,
+            frame_readback: FrameReadback::new(),
+            textures: HashMap::new(),
             next_texture_id: 0,
         })
     }
@@ -275,26 +385,73 @@ impl Graphics2D {
         Texture::from_image(id, &self.device, &self.queue, image, Some(label)).unwrap()
     }
 
+    // makes an uploaded texture drawable under `id`, replacing one loaded before
+    fn register_texture(&mut self, id: &str, texture: Texture) -> Texture {
+        self.texture_batch_context.add_texture(
+            id.to_string(),
+            texture.clone(),
+            &self.flat_normal_map,
+            &mut self.device,
+            &self.texture_bind_group_layout,
+        );
+        self.textures.insert(id.to_string(), texture.clone());
+        self.material_pipelines.forget_texture(id);
+        let texture_id = self.next_texture_id;
+        self.next_texture_id += 1;
+        self.texture_lookup.insert(texture_id, id.to_string());
+        return texture.clone();
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
+        self.resize_targets(width, height);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// Everything sized by the window except the surface, also used for targets of other sizes.
+    fn resize_targets(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
-        self.surface.configure(&self.device, &self.config);
-        self.depth_texture =
-            Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
-        self.camera.update_aspect_ratio(width, height);
+        // the virtual resolution doesn't change with the window, only its scale does
+        if self.pixel_upscaler.is_none() {
+            self.depth_texture =
+                Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+            self.light_render_batch.resize(&self.device, &self.config);
+            self.post_processor.resize(&self.device, &self.config);
+        }
+        for view in self.cameras.values_mut() {
+            view.camera.update_aspect_ratio(width, height);
+        }
+        self.write_camera_uniforms();
     }
 
-    pub fn update_camera(&mut self, target: [f32; 2], velocity: [f32; 2]) {
-        self.camera.update_follow(
-            cgmath::vec2(target[0], target[1]),
-            cgmath::vec2(velocity[0], velocity[1]),
-        );
-        let mut uniform = CameraUniform2D::new();
+    /// The main camera, the one screen positions and debug shapes go through.
+    pub fn camera(&self) -> &Camera2D {
+        &self.cameras[&MAIN_CAMERA].camera
+    }
+
+    /// Adds a camera drawing over the ones before it, within its viewport.
+    pub fn add_camera(&mut self, mut camera: Camera2D) -> CameraId {
+        camera.update_aspect_ratio(self.config.width, self.config.height);
+        let id = self.next_camera_id;
+        self.next_camera_id += 1;
+        let view = CameraView::new(&self.device, &self.camera_bind_group_layout, camera);
+        self.cameras.insert(id, view);
+        id
+    }
+
+    /// The main camera can't be removed.
+    pub fn remove_camera(&mut self, id: CameraId) -> bool {
+        id != MAIN_CAMERA && self.cameras.remove(&id).is_some()
+    }
+
+    fn write_camera_uniforms(&mut self) {
         let mut static_uniform = CameraUniform2D::new();
-        uniform.update(&self.camera);
-        static_uniform.static_update(&self.camera);
-        self.queue
-            .write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        static_uniform.static_update(self.camera());
+        for view in self.cameras.values() {
+            view.write_uniform(&self.queue);
+        }
         self.queue.write_buffer(
             &self.static_camera_buffer,
             0,
@@ -308,28 +465,225 @@ impl Graphics2D {
         canvas: &Canvas,
         physics: &PhysicsWorld,
     ) -> Result<(), SurfaceError> {
+        let Some(surface) = &self.surface else {
+            // headless, only frames someone asked to capture are drawn
+            if let Some(id) = self.capture_request.take() {
+                let target = self.take_capture_target();
+                self.draw_frame(world, canvas, physics, target.view());
+                self.read_back(&target, id, None);
+                self.capture_target = Some(target);
+            }
+            return Ok(());
+        };
         if !self.is_surface_configured {
-            self.surface.configure(&self.device, &self.config);
+            surface.configure(&self.device, &self.config);
             self.is_surface_configured = true;
         }
 
-        let output = self.surface.get_current_texture()?;
+        let output = surface.get_current_texture()?;
         let view = output
             .texture
             .create_view(&TextureViewDescriptor::default());
+        match self.capture_request.take() {
+            // drawn once offscreen, then copied to the window and to the CPU
+            Some(id) if self.config.usage.contains(TextureUsages::COPY_DST) => {
+                let target = self.take_capture_target();
+                self.draw_frame(world, canvas, physics, target.view());
+                self.read_back(&target, id, Some(&output.texture));
+                self.capture_target = Some(target);
+            }
+            // windows that can't be copied to get the frame drawn a second time
+            Some(id) => {
+                self.draw_frame(world, canvas, physics, &view);
+                let target = self.take_capture_target();
+                self.draw_frame(world, canvas, physics, target.view());
+                self.read_back(&target, id, None);
+                self.capture_target = Some(target);
+            }
+            None => self.draw_frame(world, canvas, physics, &view),
+        }
+        output.present();
+        Ok(())
+    }
+
+    /// Draws the frame into `target` instead of the window. Targets of another size than the
+    /// window get the view a window of that size would, the window's is restored after.
+    #[allow(dead_code)]
+    pub fn render_to_target(
+        &mut self,
+        world: &World,
+        canvas: &Canvas,
+        physics: &PhysicsWorld,
+        target: &RenderTarget,
+    ) {
+        let window_size = [self.config.width, self.config.height];
+        let resized = target.size() != window_size;
+        if resized {
+            self.resize_targets(target.size()[0], target.size()[1]);
+        }
+        self.draw_frame(world, canvas, physics, target.view());
+        if resized {
+            self.resize_targets(window_size[0], window_size[1]);
+        }
+    }
+
+    /// The capture target, recreated when the window's size or format changed.
+    fn take_capture_target(&mut self) -> RenderTarget {
+        let size = [self.config.width.max(1), self.config.height.max(1)];
+        match self.capture_target.take() {
+            Some(target) if target.size() == size && target.format() == self.config.format => {
+                target
+            }
+            _ => RenderTarget::new(&self.device, size[0], size[1], self.config.format),
+        }
+    }
+
+    // copies a drawn capture target to the window texture, if any, and starts reading it back
+    fn read_back(&mut self, target: &RenderTarget, id: u64, window: Option<&wgpu::Texture>) {
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("2D Render Encoder"),
+                label: Some("Frame Capture Encoder"),
             });
+        if let Some(window) = window {
+            target.copy_to_texture(&mut encoder, window);
+        }
+        if let Err(e) = self
+            .frame_readback
+            .copy(&self.device, &mut encoder, target, id)
+        {
+            println!("Failed to capture frame: {}", e);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        self.frame_readback.map_copied();
+    }
+
+    fn draw_frame(
+        &mut self,
+        world: &World,
+        canvas: &Canvas,
+        physics: &PhysicsWorld,
+        view: &TextureView,
+    ) {
+        let world_view = match &self.pixel_upscaler {
+            Some(upscaler) => upscaler.frame_view().clone(),
+            None => view.clone(),
+        };
+        let post_processing = self.post_processor.is_active(&world.post_effects);
+        let composite_view = match post_processing {
+            true => self.post_processor.input_view().clone(),
+            false => world_view.clone(),
+        };
+
+        // the world is lit offscreen, then composited before anything that should stay unlit.
+        // Each camera reuses the offscreen targets, so each is submitted before the next draws.
+        let scene_view = self.light_render_batch.scene_view().clone();
+        let normal_view = self.light_render_batch.normal_view().clone();
+        let occluders = physics.static_collider_bounds();
+        let [frame_width, frame_height] = self.frame_size();
+        let mut camera_ids: Vec<CameraId> = self.cameras.keys().copied().collect();
+        camera_ids.sort_by_key(|id| self.cameras[id].camera.order);
+        let mut cleared = false;
+        for id in camera_ids {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("2D Camera Encoder"),
+                });
+            let camera_view = &self.cameras[&id];
+            let viewport = camera_view.camera.viewport_rect(frame_width, frame_height);
+            let camera_bind_group = camera_view.bind_group().clone();
+            let visible = camera_view.camera.visible_rect();
+            if viewport[2] < 1.0 || viewport[3] < 1.0 {
+                continue;
+            }
+            self.draw_game(
+                world,
+                &mut encoder,
+                &scene_view,
+                &normal_view,
+                &camera_bind_group,
+                viewport,
+                visible,
+            );
+            self.light_render_batch.draw_lights(
+                &mut self.queue,
+                &mut encoder,
+                &camera_bind_group,
+                viewport,
+                world,
+                &occluders,
+            );
+            self.light_render_batch
+                .composite(&mut encoder, &composite_view, viewport, !cleared);
+            cleared = true;
+            self.queue.submit(Some(encoder.finish()));
+            self.texture_batch_context.reset_context();
+            self.particle_render_batch.reset_context();
+            self.material_pipelines.reset();
+        }
 
-        self.draw_game(world, &mut encoder, &view);
-        // self.draw_canvas(canvas, &mut encoder, &view);
-        self.draw_debug_batch(world, physics, &mut encoder, &view);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("2D Render Encoder"),
+            });
+        if post_processing {
+            self.post_processor.draw(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &world.post_effects,
+                &self.textures,
+                &world_view,
+            );
+        }
+        if let Some(upscaler) = &self.pixel_upscaler {
+            upscaler.draw(&mut encoder, view, [self.config.width, self.config.height]);
+        }
+        self.draw_canvas(canvas, &mut encoder, view);
+        self.draw_debug_batch(world, physics, &mut encoder, view);
         self.queue.submit(Some(encoder.finish()));
         self.texture_batch_context.reset_context();
-        output.present();
-        Ok(())
+        self.particle_render_batch.reset_context();
+        self.material_pipelines.reset();
+    }
+
+    /// Size of the targets the cameras draw the world into, the virtual resolution in
+    /// pixel-perfect mode.
+    fn frame_size(&self) -> [u32; 2] {
+        match self.camera().pixel_perfect() {
+            Some(pixel_perfect) => [pixel_perfect.width, pixel_perfect.height],
+            None => [self.config.width, self.config.height],
+        }
+    }
+
+    /// Where the main camera shows up in the window, `[x, y, width, height]` in window pixels.
+    pub fn world_viewport(&self) -> [f32; 4] {
+        self.camera_viewport(self.camera())
+    }
+
+    /// Where a camera shows up in the window, `[x, y, width, height]` in window pixels.
+    fn camera_viewport(&self, camera: &Camera2D) -> [f32; 4] {
+        let [x, y, width, height] = match self.camera().pixel_perfect() {
+            Some(pixel_perfect) => pixel_perfect.viewport(self.config.width, self.config.height),
+            None => [
+                0.0,
+                0.0,
+                self.config.width as f32,
+                self.config.height as f32,
+            ],
+        };
+        let [frame_width, frame_height] = self.frame_size();
+        let [camera_x, camera_y, camera_width, camera_height] =
+            camera.viewport_rect(frame_width, frame_height);
+        let scale = [width / frame_width as f32, height / frame_height as f32];
+        [
+            x + camera_x * scale[0],
+            y + camera_y * scale[1],
+            camera_width * scale[0],
+            camera_height * scale[1],
+        ]
     }
 
     fn draw_game(
@@ -337,17 +691,31 @@ impl Graphics2D {
         world: &World,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
+        normal_view: &wgpu::TextureView,
+        camera_bind_group: &wgpu::BindGroup,
+        viewport: [f32; 4],
+        visible: [f32; 4], // world rect the camera sees, sprites outside it aren't extracted
     ) {
         let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("2D Render Pass"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: view,
-                resolve_target: None,
-                ops: Operations {
-                    load: LoadOp::Clear(self.background_color),
-                    store: StoreOp::Store,
-                },
-            })],
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.background_color),
+                        store: StoreOp::Store,
+                    },
+                }),
+                Some(RenderPassColorAttachment {
+                    view: normal_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                }),
+            ],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &self.depth_texture.view,
                 depth_ops: Some(wgpu::Operations {
@@ -360,43 +728,69 @@ impl Graphics2D {
             timestamp_writes: None,
         });
 
+        let [x, y, width, height] = viewport;
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
         pass.set_pipeline(&self.render_pipeline);
-        pass.set_bind_group(1, &self.camera_bind_group, &[]);
-
-        let render_queue = world.extract_render_queue_2d();
-        let mut opaque = render_queue.clone().opaque.clone();
-        opaque.sort_by(|a, b| {
-            b.z_order
-                .partial_cmp(&a.z_order)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        pass.set_bind_group(1, camera_bind_group, &[]);
+
+        // layers must interleave opaque and transparent sprites, so draw them as one sorted list
+        let render_queue = world.extract_render_queue_2d(visible);
+        let mut elements: Vec<RenderElement2D> = render_queue
+            .opaque
+            .into_iter()
+            .chain(render_queue.transparent)
+            .collect();
+        elements.sort_by(RenderElement2D::draw_order);
+        if let Some(pixel_perfect) = self.camera().pixel_perfect() {
+            let unit = pixel_perfect.units_per_pixel();
+            for element in elements.iter_mut() {
+                element.snap_to_grid(unit);
+            }
+        }
 
-        for element in opaque.iter() {
+        // a layer's particles go on top of its sprites, under the next layer
+        let mut particles = collect_particles(world).into_iter().peekable();
+        let mut bound_material: Option<Option<ResolvedMaterial>> = None; // None = nothing bound yet
+        for element in elements.iter() {
+            if particles
+                .peek()
+                .is_some_and(|((layer, _), _)| *layer < element.layer)
             {
-                self.texture_batch_context.enqueue_next_texture(
-                    element,
+                self.texture_batch_context.flush_batch(
                     &mut self.queue,
                     &mut pass,
                     &mut self.vertex_buffer,
                     &mut self.index_buffer,
                 );
+                while let Some(((_, texture), instances)) =
+                    particles.next_if(|((layer, _), _)| *layer < element.layer)
+                {
+                    self.draw_particles(&mut pass, &texture, &instances);
+                }
+                bound_material = None;
+            }
+            // sprites with a material break the batch and switch pipelines
+            if bound_material.as_ref() != Some(&element.material) {
+                self.texture_batch_context.flush_batch(
+                    &mut self.queue,
+                    &mut pass,
+                    &mut self.vertex_buffer,
+                    &mut self.index_buffer,
+                );
+                let bound = element.material.as_ref().is_some_and(|material| {
+                    self.material_pipelines.bind(
+                        &self.device,
+                        &self.queue,
+                        &mut pass,
+                        material,
+                        &self.textures,
+                    )
+                });
+                if !bound {
+                    pass.set_pipeline(&self.render_pipeline);
+                }
+                bound_material = Some(element.material.clone());
             }
-        }
-        // flush opaque
-        self.texture_batch_context.flush_batch(
-            &mut self.queue,
-            &mut pass,
-            &mut self.vertex_buffer,
-            &mut self.index_buffer,
-        );
-
-        let mut transparent = render_queue.transparent.clone();
-        transparent.sort_by(|a, b| {
-            a.z_order
-                .partial_cmp(&b.z_order)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        for element in transparent.iter() {
             {
                 self.texture_batch_context.enqueue_next_texture(
                     element,
@@ -414,6 +808,21 @@ impl Graphics2D {
             &mut self.vertex_buffer,
             &mut self.index_buffer,
         );
+        for ((_, texture), instances) in particles {
+            self.draw_particles(&mut pass, &texture, &instances);
+        }
+    }
+
+    fn draw_particles(
+        &mut self,
+        pass: &mut wgpu::RenderPass,
+        texture: &str,
+        instances: &[ParticleInstanceVertex],
+    ) {
+        if let Some(bind_group) = self.texture_batch_context.bind_group(texture) {
+            self.particle_render_batch
+                .draw(&mut self.queue, pass, bind_group, instances);
+        }
     }
 
     fn draw_canvas(
@@ -438,15 +847,12 @@ impl Graphics2D {
         });
 
         pass.set_pipeline(&self.canvas_pipeline);
+        pass.set_bind_group(1, &self.static_camera_bind_group, &[]);
 
         let render_queue = canvas.extract_render_queue_2d();
 
-        let mut transparent = render_queue.transparent.clone();
-        transparent.sort_by(|a, b| {
-            a.z_order
-                .partial_cmp(&b.z_order)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        let mut transparent = render_queue.transparent;
+        transparent.sort_by(RenderElement2D::draw_order);
         for element in transparent.iter() {
             {
                 self.texture_batch_context.enqueue_next_texture(
@@ -593,11 +999,16 @@ impl Graphics2D {
                 timestamp_writes: None,
                 depth_stencil_attachment: None,
             });
+            // debug shapes are drawn at window resolution, lined up with the scaled up world
+            let [x, y, width, height] = self.world_viewport();
+            if width >= 1.0 && height >= 1.0 {
+                render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            }
 
             self.debug_render_batch.flush_batch(
                 &mut self.queue,
                 &mut render_pass,
-                &self.camera_bind_group,
+                self.cameras[&MAIN_CAMERA].bind_group(),
             );
         }
         //println!("Debug {:?}", i.elapsed().as_secs_f64());
@@ -671,13 +1082,36 @@ impl Graphics for Graphics2D {
     }
 
     fn update_camera(&mut self) {
-        // Handled externally for 2D for now
+        self.write_camera_uniforms();
+    }
+
+    fn camera_2d(&mut self, id: CameraId) -> Option<&mut Camera2D> {
+        self.cameras.get_mut(&id).map(|view| &mut view.camera)
+    }
+
+    fn camera_ids(&self) -> Vec<CameraId> {
+        self.cameras.keys().copied().collect()
+    }
+
+    fn camera_viewport(&self, id: CameraId) -> Option<[f32; 4]> {
+        self.cameras
+            .get(&id)
+            .map(|view| self.camera_viewport(&view.camera))
+    }
+
+    fn add_camera_2d(&mut self, camera: Camera2D) -> Option<CameraId> {
+        Some(self.add_camera(camera))
+    }
+
+    fn remove_camera_2d(&mut self, id: CameraId) -> bool {
+        self.remove_camera(id)
     }
 
     fn get_camera_info(&self) -> crate::graphics::CameraInfo {
         return crate::graphics::CameraInfo {
-            zoom: self.camera.zoom.clone(),
-            position: [self.camera.position[0], self.camera.position[1], 0.0],
+            zoom: self.camera().zoom,
+            position: [self.camera().position[0], self.camera().position[1], 0.0],
+            viewport: self.world_viewport(),
         };
     }
 
@@ -689,42 +1123,155 @@ impl Graphics for Graphics2D {
         let _ = self.render(world, canvas, physics);
     }
 
+    fn capture_next_frame(&mut self, id: u64) {
+        self.capture_request = Some(id);
+    }
+
+    fn captured_frames(&mut self, wait: bool) -> Vec<(u64, RgbaImage)> {
+        self.frame_readback
+            .take_ready(&self.device, wait)
+            .into_iter()
+            .filter_map(|(id, image)| match image {
+                Ok(image) => Some((id, image)),
+                Err(e) => {
+                    println!("Failed to capture frame: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
     fn load_texture_from_path(&mut self, id: &str, path: &str) -> Texture {
-        let image = image::open(path).unwrap().flipv();
-        let texture = self.create_gpu_texture(id.to_string(), &image, path);
-        self.texture_batch_context.add_texture(
+        let image = image::open(path).unwrap();
+        self.load_texture_from_image(id, &image)
+    }
+
+    fn load_texture_from_image(&mut self, id: &str, image: &DynamicImage) -> Texture {
+        let image = image.flipv();
+        let texture = self.create_gpu_texture(id.to_string(), &image, id);
+        self.register_texture(id, texture)
+    }
+
+    fn load_data_texture(&mut self, id: &str, image: &DynamicImage) -> Texture {
+        let texture = Texture::from_image_with_format(
             id.to_string(),
+            &self.device,
+            &self.queue,
+            &image.flipv(),
+            Some(id),
+            TextureFormat::Rgba8Unorm,
+        )
+        .unwrap();
+        self.register_texture(id, texture)
+    }
+
+    fn load_normal_map(&mut self, texture: &Texture, path: &str) -> bool {
+        let image = match image::open(path) {
+            Ok(image) => image.flipv(),
+            Err(e) => {
+                println!("Failed to load normal map {}: {}", path, e);
+                return false;
+            }
+        };
+        let label = format!("{}:normal", texture.id);
+        let normal_map = match Texture::from_image_with_format(
+            label.clone(),
+            &self.device,
+            &self.queue,
+            &image,
+            Some(&label),
+            NORMAL_FORMAT,
+        ) {
+            Ok(normal_map) => normal_map,
+            Err(e) => {
+                println!("Failed to load normal map {}: {}", path, e);
+                return false;
+            }
+        };
+        // same texture and sampler, the bind group is rebuilt with the normal map in it
+        self.texture_batch_context.add_texture(
+            texture.id.clone(),
             texture.clone(),
+            &normal_map,
             &mut self.device,
             &self.texture_bind_group_layout,
         );
-        let texture_id = self.next_texture_id;
-        self.next_texture_id += 1;
-        self.texture_lookup.insert(texture_id, id.to_string());
-        return texture.clone();
+        true
     }
 
-    fn process_camera_event(&mut self, _event: &winit::event::WindowEvent) {}
+    fn load_material(&mut self, name: &str, path: &str) -> bool {
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| self.material_pipelines.load(&self.device, name, &source));
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                println!("Failed to load material {} from {}: {}", name, path, e);
+                false
+            }
+        }
+    }
 
-    fn move_camera_for_follow(
-        &mut self,
-        p: [f32; 3],
-        v: [f32; 3],
-        _acceleration: [f32; 3],
-        _offset: [f32; 3],
-    ) {
-        self.update_camera(p[0..2].try_into().unwrap(), v[0..2].try_into().unwrap());
+    fn load_post_effect(&mut self, name: &str, path: &str) -> bool {
+        let result = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| self.post_processor.load_effect(&self.device, name, &source));
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                println!("Failed to load post effect {} from {}: {}", name, path, e);
+                false
+            }
+        }
     }
+
+    fn process_camera_event(&mut self, _event: &winit::event::WindowEvent) {}
 }
 
+/// Shared unit quad for anything that is not backed by a transform (glyphs, widget slices).
+pub static UNIT_RECTANGLE: Shape2D = Shape2D::Rectangle {
+    half_extents: Vector2 { x: 0.5, y: 0.5 },
+};
+
 #[derive(Debug, Clone)]
 pub struct RenderElement2D<'a> {
     pub shape: &'a Shape2D,
     pub position: [f32; 2],
     pub size: [f32; 2],
-    pub z_order: f32, // for Y-based sorting (e.g., lower y = drawn on top)
+    pub layer: RenderLayer,
+    pub z_order: f32, // sort key within the layer, ascending = drawn first
+    pub entity: Entity,
     pub texture_id: String,
     pub uv_coords: [[f32; 2]; 4],
+    pub rotation: f32,
+    pub tint: [f32; 4],
+    pub flash: [f32; 4],
+    pub opacity: f32,
+    pub material: Option<ResolvedMaterial>, // None draws with the default sprite shader
+}
+
+impl<'a> RenderElement2D<'a> {
+    /// Moves the sprite so its corner sits on a texel boundary of the virtual resolution,
+    /// sprites an odd number of texels wide would otherwise straddle two.
+    pub fn snap_to_grid(&mut self, unit: f32) {
+        for axis in 0..2 {
+            let half_size = self.size[axis] * 0.5;
+            let corner = ((self.position[axis] - half_size) / unit).round() * unit;
+            self.position[axis] = corner + half_size;
+        }
+    }
+
+    /// Back to front: layer, then the layer's sort key, then creation order for stability.
+    pub fn draw_order(a: &RenderElement2D, b: &RenderElement2D) -> std::cmp::Ordering {
+        a.layer
+            .cmp(&b.layer)
+            .then(
+                a.z_order
+                    .partial_cmp(&b.z_order)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+            .then(a.entity.cmp(&b.entity))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -732,3 +1279,119 @@ pub struct RenderQueue2D<'a> {
     pub transparent: Vec<RenderElement2D<'a>>,
     pub opaque: Vec<RenderElement2D<'a>>,
 }
+
+/// Every live particle, grouped by layer then texture so each group is one instanced draw.
+fn collect_particles(
+    world: &World,
+) -> BTreeMap<(RenderLayer, String), Vec<ParticleInstanceVertex>> {
+    let mut groups: BTreeMap<(RenderLayer, String), Vec<ParticleInstanceVertex>> = BTreeMap::new();
+    for emitter in world.particle_emitters.values() {
+        if emitter.particles.is_empty() {
+            continue;
+        }
+        let group = groups
+            .entry((emitter.config.layer, emitter.config.texture.clone()))
+            .or_default();
+        group.extend(emitter.instances().map(|particle| ParticleInstanceVertex {
+            position: particle.position,
+            size: particle.size,
+            rotation: particle.rotation,
+            uv_rect: particle.uv_rect,
+            color: particle.color,
+        }));
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera_2d::camera_2d::Camera2DConfig;
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 48;
+    const GOLDEN_DIR: &str = "src/assets/tests/golden";
+
+    fn headless() -> Option<Graphics2D> {
+        let camera = Camera2D::new(&Camera2DConfig {
+            zoom: 10.0,
+            initial_position: [0.0, 0.0],
+            look_ahead_smooth_factor: 0.0,
+            look_ahead_distance: 0.0,
+            look_ahead_lerp_speed: 0.0,
+            screen_width: WIDTH as f32,
+            screen_height: HEIGHT as f32,
+            pixel_perfect: None,
+        });
+        match pollster::block_on(Graphics2D::new_headless(WIDTH, HEIGHT, camera)) {
+            Ok(graphics) => Some(graphics),
+            Err(e) => {
+                println!("skipping, no software adapter: {}", e);
+                None
+            }
+        }
+    }
+
+    // compared with a little slack, software rasterizers round blending differently.
+    // UPDATE_GOLDEN=1 rewrites the golden images instead
+    fn assert_matches_golden(name: &str, image: &RgbaImage) {
+        let path = std::path::Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+            image.save(&path).unwrap();
+            return;
+        }
+        let golden = image::open(&path)
+            .unwrap_or_else(|e| panic!("missing golden image {}: {}", path.display(), e))
+            .to_rgba8();
+        assert_eq!(golden.dimensions(), image.dimensions());
+        for (x, y, expected) in golden.enumerate_pixels() {
+            let actual = image.get_pixel(x, y);
+            let close = (0..4).all(|c| expected[c].abs_diff(actual[c]) <= 2);
+            assert!(
+                close,
+                "{} differs at {},{}: {:?} vs {:?}",
+                name, x, y, actual, expected
+            );
+        }
+    }
+
+    #[test]
+    fn empty_frame_matches_golden() {
+        let Some(mut graphics) = headless() else {
+            return;
+        };
+        let target = RenderTarget::new(&graphics.device, WIDTH, HEIGHT, graphics.config.format);
+        graphics.render_to_target(
+            &World::new(),
+            &Canvas::new(WIDTH, HEIGHT),
+            &PhysicsWorld::new(),
+            &target,
+        );
+        let image = target
+            .read_image(&graphics.device, &graphics.queue)
+            .unwrap();
+        assert_matches_golden("empty_frame", &image);
+    }
+
+    #[test]
+    fn captured_frames_come_back_in_order() {
+        let Some(mut graphics) = headless() else {
+            return;
+        };
+        let target = RenderTarget::new(&graphics.device, WIDTH, HEIGHT, graphics.config.format);
+        for id in [3, 7] {
+            graphics.render_to_target(
+                &World::new(),
+                &Canvas::new(WIDTH, HEIGHT),
+                &PhysicsWorld::new(),
+                &target,
+            );
+            graphics.read_back(&target, id, None);
+        }
+        let frames = graphics.captured_frames(true);
+        let ids: Vec<u64> = frames.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![3, 7]);
+        assert_matches_golden("empty_frame", &frames[1].1);
+    }
+}
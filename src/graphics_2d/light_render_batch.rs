@@ -0,0 +1,557 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use crate::{
+    components_systems::Light2D,
+    graphics_2d::{
+        shape_pipelines::create_2d_pipeline_with_targets,
+        vertex::{LightInstanceVertex, Vertex},
+    },
+    world::World,
+};
+
+pub const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const LIGHT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+// every light gets its own stencil value, lights past this in one frame are not drawn
+const MAX_LIGHTS: usize = 255;
+// shadows past this in one frame are not drawn
+const SHADOW_VERTEX_CAPACITY: u64 = 64 * 1024;
+// the far side of a shadow is an arc split into pieces no wider than this
+const SHADOW_ARC_STEP: f32 = PI / 6.0;
+
+/// Offscreen targets of the world pass and the light buffer, rebuilt on resize.
+struct LightTargets {
+    scene_view: wgpu::TextureView,
+    normal_view: wgpu::TextureView,
+    light_view: wgpu::TextureView,
+    stencil_view: wgpu::TextureView,
+    normal_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup,
+}
+
+/// The world pass draws into `scene` and `normal` instead of the surface. Lights then add up
+/// in a light buffer that starts at the ambient color, with static colliders stenciled out
+/// per light, and `composite` multiplies the two onto the surface.
+pub struct LightRenderBatch {
+    light_pipeline: wgpu::RenderPipeline,
+    shadow_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    normal_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    targets: LightTargets,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    shadow_buffer: wgpu::Buffer,
+}
+
+impl LightRenderBatch {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let normal_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[texture_layout_entry(0)],
+                label: Some("light_normal_bind_group_layout"),
+            });
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[texture_layout_entry(0), texture_layout_entry(1)],
+                label: Some("light_composite_bind_group_layout"),
+            });
+
+        let light_pipeline = create_2d_pipeline_with_targets(
+            "Light Pipeline",
+            device,
+            &[Some(wgpu::ColorTargetState {
+                format: LIGHT_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            &device.create_shader_module(include_wgsl!("shaders/2d_light.wgsl")),
+            &[Vertex::desc(), LightInstanceVertex::desc()],
+            &Vec::from([&normal_bind_group_layout, camera_bind_group_layout]),
+            // skip whatever the shadows of this light marked
+            Some(stencil_state(wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::NotEqual,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Keep,
+            })),
+        );
+
+        let shadow_pipeline = create_2d_pipeline_with_targets(
+            "Light Shadow Pipeline",
+            device,
+            &[Some(wgpu::ColorTargetState {
+                format: LIGHT_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            })],
+            &device.create_shader_module(include_wgsl!("shaders/2d_light_shadow.wgsl")),
+            &[Vertex::desc()],
+            &Vec::from([&normal_bind_group_layout, camera_bind_group_layout]),
+            Some(stencil_state(wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::Always,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Replace,
+            })),
+        );
+
+        let composite_pipeline = create_2d_pipeline_with_targets(
+            "Light Composite Pipeline",
+            device,
+            &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            &device.create_shader_module(include_wgsl!("shaders/2d_light_composite.wgsl")),
+            &[],
+            &Vec::from([&composite_bind_group_layout]),
+            None,
+        );
+
+        let quad = [
+            Vertex {
+                position: [-1.0, 1.0],
+            },
+            Vertex {
+                position: [1.0, 1.0],
+            },
+            Vertex {
+                position: [1.0, -1.0],
+            },
+            Vertex {
+                position: [-1.0, -1.0],
+            },
+        ];
+        let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(&quad),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Instance Buffer"),
+            size: (MAX_LIGHTS * std::mem::size_of::<LightInstanceVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shadow_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Shadow Vertex Buffer"),
+            size: SHADOW_VERTEX_CAPACITY * std::mem::size_of::<Vertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let targets = LightTargets::new(
+            device,
+            config,
+            &normal_bind_group_layout,
+            &composite_bind_group_layout,
+        );
+
+        Self {
+            light_pipeline,
+            shadow_pipeline,
+            composite_pipeline,
+            normal_bind_group_layout,
+            composite_bind_group_layout,
+            targets,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            shadow_buffer,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.targets = LightTargets::new(
+            device,
+            config,
+            &self.normal_bind_group_layout,
+            &self.composite_bind_group_layout,
+        );
+    }
+
+    /// Where the world pass draws its colors.
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.targets.scene_view
+    }
+
+    /// Where the world pass draws the normals of normal mapped sprites.
+    pub fn normal_view(&self) -> &wgpu::TextureView {
+        &self.targets.normal_view
+    }
+
+    /// Fills the light buffer from the world's ambient color and lights.
+    /// `occluders` are the `(min, max)` boxes that cast shadows.
+    pub fn draw_lights(
+        &mut self,
+        queue: &mut wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        viewport: [f32; 4],
+        world: &World,
+        occluders: &[([f32; 2], [f32; 2])],
+    ) {
+        let lights: Vec<&Light2D> = world
+            .lights
+            .values()
+            .filter(|light| light.enabled && light.radius > 0.0)
+            .take(MAX_LIGHTS)
+            .collect();
+
+        let instances: Vec<LightInstanceVertex> = lights
+            .iter()
+            .map(|light| LightInstanceVertex {
+                position: light.origin(),
+                radius: light.radius,
+                falloff: light.falloff.max(0.0),
+                color: light.color.map(|channel| channel * light.intensity),
+                height: light.height,
+                direction: light.direction(),
+                cone: light.cone(),
+            })
+            .collect();
+
+        let mut shadow_vertices: Vec<Vertex> = Vec::new();
+        let mut shadow_ranges: Vec<Range<u32>> = Vec::new();
+        for light in lights.iter() {
+            let start = shadow_vertices.len() as u32;
+            if light.casts_shadows {
+                for (min, max) in occluders {
+                    push_box_shadow(
+                        &mut shadow_vertices,
+                        light.origin(),
+                        light.radius,
+                        *min,
+                        *max,
+                    );
+                }
+            }
+            let end = (shadow_vertices.len() as u64).min(SHADOW_VERTEX_CAPACITY) as u32;
+            shadow_ranges.push(start.min(end)..end);
+        }
+        shadow_vertices.truncate(SHADOW_VERTEX_CAPACITY as usize);
+
+        if !instances.is_empty() {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+        if !shadow_vertices.is_empty() {
+            queue.write_buffer(
+                &self.shadow_buffer,
+                0,
+                bytemuck::cast_slice(&shadow_vertices),
+            );
+        }
+
+        let [r, g, b] = world.ambient_light;
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Light Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.targets.light_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: r as f64,
+                        g: g as f64,
+                        b: b as f64,
+                        a: 1.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.targets.stencil_view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        let [x, y, width, height] = viewport;
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        pass.set_bind_group(0, &self.targets.normal_bind_group, &[]);
+        pass.set_bind_group(1, camera_bind_group, &[]);
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+        for (index, shadows) in shadow_ranges.into_iter().enumerate() {
+            // values never repeat within the pass, so the stencil is never cleared between lights
+            pass.set_stencil_reference(index as u32 + 1);
+            if !shadows.is_empty() {
+                pass.set_pipeline(&self.shadow_pipeline);
+                pass.set_vertex_buffer(0, self.shadow_buffer.slice(..));
+                pass.draw(shadows, 0..1);
+            }
+            pass.set_pipeline(&self.light_pipeline);
+            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            let instance = index as u32;
+            pass.draw_indexed(0..6, 0, instance..instance + 1);
+        }
+    }
+
+    /// Writes the lit scene within `viewport` to `view`, the rest of it is cleared to black
+    /// with `clear` or else kept.
+    pub fn composite(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        viewport: [f32; 4],
+        clear: bool,
+    ) {
+        let load = match clear {
+            true => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            false => wgpu::LoadOp::Load,
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Light Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        let [x, y, width, height] = viewport;
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        pass.set_pipeline(&self.composite_pipeline);
+        pass.set_bind_group(0, &self.targets.composite_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+impl LightTargets {
+    fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        normal_bind_group_layout: &wgpu::BindGroupLayout,
+        composite_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let sampled = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        let scene_view = create_target(device, config, config.format, sampled, "2d_scene_texture");
+        let normal_view =
+            create_target(device, config, NORMAL_FORMAT, sampled, "2d_normal_texture");
+        let light_view = create_target(device, config, LIGHT_FORMAT, sampled, "2d_light_texture");
+        let stencil_view = create_target(
+            device,
+            config,
+            STENCIL_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+            "2d_light_stencil_texture",
+        );
+
+        let normal_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: normal_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&normal_view),
+            }],
+            label: Some("Light Normal Bind Group"),
+        });
+        let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: composite_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&light_view),
+                },
+            ],
+            label: Some("Light Composite Bind Group"),
+        });
+
+        Self {
+            scene_view,
+            normal_view,
+            light_view,
+            stencil_view,
+            normal_bind_group,
+            composite_bind_group,
+        }
+    }
+}
+
+fn create_target(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    label: &str,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// read with textureLoad, one texel per pixel, so no sampler
+fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    }
+}
+
+fn stencil_state(face: wgpu::StencilFaceState) -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: STENCIL_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        },
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
+
+/// Adds the shadow a box casts from a light as triangles.
+/// Boxes out of reach of the light, or with the light inside them, cast nothing.
+fn push_box_shadow(
+    vertices: &mut Vec<Vertex>,
+    light: [f32; 2],
+    radius: f32,
+    min: [f32; 2],
+    max: [f32; 2],
+) {
+    let closest = [
+        light[0].clamp(min[0], max[0]),
+        light[1].clamp(min[1], max[1]),
+    ];
+    let (dx, dy) = (closest[0] - light[0], closest[1] - light[1]);
+    if dx * dx + dy * dy >= radius * radius || (dx == 0.0 && dy == 0.0) {
+        return;
+    }
+
+    // counter-clockwise edges with their outward normals
+    let edges = [
+        ([min[0], min[1]], [max[0], min[1]], [0.0, -1.0]),
+        ([max[0], min[1]], [max[0], max[1]], [1.0, 0.0]),
+        ([max[0], max[1]], [min[0], max[1]], [0.0, 1.0]),
+        ([min[0], max[1]], [min[0], min[1]], [-1.0, 0.0]),
+    ];
+    for (a, b, normal) in edges {
+        // edges facing away from the light outline the shadow, so the box itself stays lit
+        let facing_away = normal[0] * (a[0] - light[0]) + normal[1] * (a[1] - light[1]) > 0.0;
+        if facing_away {
+            push_edge_shadow(vertices, light, radius, a, b);
+        }
+    }
+}
+
+/// Covers everything behind the edge `a`-`b` up to past the light's radius. The far side
+/// follows an arc so it never cuts back into the lit area, however close the light is.
+fn push_edge_shadow(
+    vertices: &mut Vec<Vertex>,
+    light: [f32; 2],
+    radius: f32,
+    a: [f32; 2],
+    b: [f32; 2],
+) {
+    let reach = radius * 2.0;
+    let angle_a = (a[1] - light[1]).atan2(a[0] - light[0]);
+    let angle_b = (b[1] - light[1]).atan2(b[0] - light[0]);
+    let sweep = (angle_b - angle_a + PI).rem_euclid(2.0 * PI) - PI;
+    let steps = (sweep.abs() / SHADOW_ARC_STEP).ceil().max(1.0) as usize;
+
+    let edge = [b[0] - a[0], b[1] - a[1]];
+    let to_a = [a[0] - light[0], a[1] - light[1]];
+    let cross = |u: [f32; 2], v: [f32; 2]| u[0] * v[1] - u[1] * v[0];
+
+    // where the ray at `angle` leaves the edge, and where it reaches the far arc
+    let point_at = |step: usize| -> ([f32; 2], [f32; 2]) {
+        if step == 0 {
+            let far = [
+                light[0] + angle_a.cos() * reach,
+                light[1] + angle_a.sin() * reach,
+            ];
+            return (a, far);
+        }
+        if step == steps {
+            let far = [
+                light[0] + angle_b.cos() * reach,
+                light[1] + angle_b.sin() * reach,
+            ];
+            return (b, far);
+        }
+        let angle = angle_a + sweep * step as f32 / steps as f32;
+        let direction = [angle.cos(), angle.sin()];
+        let distance = cross(to_a, edge) / cross(direction, edge);
+        (
+            [
+                light[0] + direction[0] * distance,
+                light[1] + direction[1] * distance,
+            ],
+            [
+                light[0] + direction[0] * reach,
+                light[1] + direction[1] * reach,
+            ],
+        )
+    };
+
+    let mut previous = point_at(0);
+    for step in 1..=steps {
+        let next = point_at(step);
+        let (near_a, far_a) = previous;
+        let (near_b, far_b) = next;
+        for position in [near_a, near_b, far_b, far_b, far_a, near_a] {
+            vertices.push(Vertex { position });
+        }
+        previous = next;
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use bytemuck::{Pod, Zeroable};
+use image::DynamicImage;
+
+use crate::{
+    components_systems::{MaterialParams, ResolvedMaterial},
+    graphics_2d::{
+        light_render_batch::NORMAL_FORMAT, shape_pipelines::create_2d_pipeline_with_targets,
+        TextureVertex,
+    },
+    palette::PALETTE_SWAP_MATERIAL,
+    texture::Texture,
+};
+
+const MATERIAL_COMMON: &str = include_str!("shaders/materials/common.wgsl");
+// distinct material values drawn in one frame, sprites past this use the default shader
+const MAX_MATERIAL_DRAWS: u64 = 512;
+const UNIFORM_STRIDE: u64 = 256; // min_uniform_buffer_offset_alignment
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct MaterialUniform {
+    time: f32,
+    _padding: [f32; 3],
+    params: MaterialParams,
+}
+
+/// Pipelines of the materials registered from Lua, cached by material name, and the
+/// uniforms of the sprites drawn with them this frame.
+pub struct MaterialPipelines {
+    bind_group_layout: wgpu::BindGroupLayout,
+    sprite_layouts: [wgpu::BindGroupLayout; 2], // texture and camera, shared with the sprite pipeline
+    format: wgpu::TextureFormat,
+    pipelines: HashMap<String, wgpu::RenderPipeline>,
+    bind_groups: HashMap<String, wgpu::BindGroup>, // by material texture, "" for none
+    uniform_buffer: wgpu::Buffer,
+    white: Texture, // bound for materials without a texture
+    next_slot: u64,
+    frame_slots: HashMap<[u32; 32], u64>, // sprites with equal params share this frame's slot
+    started: Instant,
+}
+
+impl MaterialPipelines {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        texture_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<MaterialUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("material_bind_group_layout"),
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Material Uniform Buffer"),
+            size: MAX_MATERIAL_DRAWS * UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut white_image = image::RgbaImage::new(1, 1);
+        white_image.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+        let white = Texture::from_image(
+            "material_white".to_string(),
+            device,
+            queue,
+            &DynamicImage::ImageRgba8(white_image),
+            Some("material_white"),
+        )
+        .unwrap();
+
+        let mut material_pipelines = Self {
+            bind_group_layout,
+            sprite_layouts: [texture_layout.clone(), camera_layout.clone()],
+            format,
+            pipelines: HashMap::new(),
+            bind_groups: HashMap::new(),
+            uniform_buffer,
+            white,
+            next_slot: 0,
+            frame_slots: HashMap::new(),
+            started: Instant::now(),
+        };
+        material_pipelines
+            .load(
+                device,
+                PALETTE_SWAP_MATERIAL,
+                include_str!("shaders/materials/palette_swap.wgsl"),
+            )
+            .expect("built in palette swap material failed to compile");
+        material_pipelines
+    }
+
+    /// Compiles `source`, a fragment shader written against `shaders/materials/common.wgsl`.
+    /// Reports shader errors instead of letting wgpu abort on them.
+    pub fn load(&mut self, device: &wgpu::Device, name: &str, source: &str) -> Result<(), String> {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", MATERIAL_COMMON, source).into()),
+        });
+        // same targets and depth as the sprite pipeline, so materials sort with other sprites
+        let pipeline = create_2d_pipeline_with_targets(
+            name,
+            device,
+            &[
+                Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: NORMAL_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ],
+            &shader,
+            &[TextureVertex::desc()],
+            &Vec::from([
+                &self.sprite_layouts[0],
+                &self.sprite_layouts[1],
+                &self.bind_group_layout,
+            ]),
+            Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            return Err(error.to_string());
+        }
+        self.pipelines.insert(name.to_string(), pipeline);
+        Ok(())
+    }
+
+    /// Sets the material's pipeline and uniforms on `pass`.
+    /// False when it isn't loaded or this frame ran out of uniform slots.
+    pub fn bind(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass,
+        material: &ResolvedMaterial,
+        textures: &HashMap<String, Texture>,
+    ) -> bool {
+        let Some(pipeline) = self.pipelines.get(&material.name) else {
+            return false;
+        };
+        let key: [u32; 32] = bytemuck::cast(material.params);
+        let offset = match self.frame_slots.get(&key) {
+            Some(offset) => *offset,
+            None => {
+                if self.next_slot >= MAX_MATERIAL_DRAWS {
+                    return false;
+                }
+                let offset = self.next_slot * UNIFORM_STRIDE;
+                self.next_slot += 1;
+                let uniform = MaterialUniform {
+                    time: self.started.elapsed().as_secs_f32(),
+                    _padding: [0.0; 3],
+                    params: material.params,
+                };
+                queue.write_buffer(
+                    &self.uniform_buffer,
+                    offset,
+                    bytemuck::cast_slice(&[uniform]),
+                );
+                self.frame_slots.insert(key, offset);
+                offset
+            }
+        };
+
+        // textures that haven't loaded get white, without caching it under their name
+        let texture = material
+            .texture
+            .as_ref()
+            .and_then(|path| textures.get(path).map(|texture| (path.clone(), texture)));
+        let texture_key = texture
+            .as_ref()
+            .map(|(path, _)| path.clone())
+            .unwrap_or_default();
+        let bind_group = self.bind_groups.entry(texture_key).or_insert_with(|| {
+            let texture = texture.map_or(&self.white, |(_, texture)| texture);
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &self.uniform_buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(
+                                std::mem::size_of::<MaterialUniform>() as u64
+                            ),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                ],
+                label: Some("Material Bind Group"),
+            })
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(2, &*bind_group, &[offset as u32]);
+        true
+    }
+
+    /// Frees this frame's uniform slots.
+    pub fn reset(&mut self) {
+        self.next_slot = 0;
+        self.frame_slots.clear();
+    }
+
+    /// Drops the bind group of a texture that was uploaded again.
+    pub fn forget_texture(&mut self, id: &str) {
+        self.bind_groups.remove(id);
+    }
+}
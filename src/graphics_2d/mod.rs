@@ -1,6 +1,13 @@
 mod camera_uniform;
+mod camera_view;
 mod debug_render_batch;
 mod graphics_2d;
+mod light_render_batch;
+mod material_pipelines;
+mod particle_render_batch;
+mod pixel_upscaler;
+mod post_process;
+mod render_target;
 mod shape_pipelines;
 mod shape_tesselation;
 mod space;
@@ -8,8 +15,16 @@ mod vertex;
 mod world_render_batch;
 
 use camera_uniform::CameraUniform2D;
+use camera_view::CameraView;
 use debug_render_batch::DebugRenderBatch;
+use light_render_batch::LightRenderBatch;
+use material_pipelines::MaterialPipelines;
+use particle_render_batch::ParticleRenderBatch;
+use pixel_upscaler::PixelUpscaler;
+use post_process::PostProcessor;
 use vertex::{ColorVertex, TextureVertex};
 use world_render_batch::WorldRenderBatch;
 
-pub use graphics_2d::{Graphics2D, RenderElement2D, RenderQueue2D, TextureId};
+pub use graphics_2d::{Graphics2D, RenderElement2D, RenderQueue2D, TextureId, UNIT_RECTANGLE};
+pub use render_target::{FrameReadback, RenderTarget};
+pub use space::Space;
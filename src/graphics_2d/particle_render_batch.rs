@@ -0,0 +1,138 @@
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use crate::{
+    graphics_2d::{
+        light_render_batch::NORMAL_FORMAT,
+        shape_pipelines::create_2d_pipeline_with_targets,
+        vertex::{ParticleInstanceVertex, Vertex},
+    },
+    texture::Texture,
+};
+
+// particles past this in one frame are not drawn
+const PARTICLE_INSTANCE_CAPACITY: u64 = 64 * 1024;
+
+/// Draws particles as instances of one unit quad, one draw call per texture and layer.
+pub struct ParticleRenderBatch {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    instance_offset: u64, // instances written this frame
+}
+
+impl ParticleRenderBatch {
+    pub fn new(
+        device: &wgpu::Device,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let quad = [
+            Vertex {
+                position: [-0.5, 0.5],
+            },
+            Vertex {
+                position: [0.5, 0.5],
+            },
+            Vertex {
+                position: [0.5, -0.5],
+            },
+            Vertex {
+                position: [-0.5, -0.5],
+            },
+        ];
+        let indices: [u16; 6] = [0, 1, 2, 2, 3, 0];
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(&quad),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Instance Buffer"),
+            size: PARTICLE_INSTANCE_CAPACITY * std::mem::size_of::<ParticleInstanceVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline = create_2d_pipeline_with_targets(
+            "Particle Pipeline",
+            device,
+            // particles aren't normal mapped, leave the normals of the sprites under them
+            &[
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: NORMAL_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::empty(),
+                }),
+            ],
+            &device.create_shader_module(include_wgsl!(
+                "shaders/2d_camera_and_particle_instanced.wgsl"
+            )),
+            &[Vertex::desc(), ParticleInstanceVertex::desc()],
+            &Vec::from([texture_bind_group_layout, camera_bind_group_layout]),
+            // drawn inside the world pass, so it has to match the sprites' depth attachment
+            Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+        );
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            instance_offset: 0,
+        }
+    }
+
+    /// Leaves the particle pipeline bound, callers switch back before drawing sprites.
+    pub fn draw(
+        &mut self,
+        queue: &mut wgpu::Queue,
+        pass: &mut wgpu::RenderPass,
+        texture_bind_group: &wgpu::BindGroup,
+        instances: &[ParticleInstanceVertex],
+    ) {
+        let room = (PARTICLE_INSTANCE_CAPACITY - self.instance_offset) as usize;
+        let instances = &instances[..instances.len().min(room)];
+        if instances.is_empty() {
+            return;
+        }
+        let stride = std::mem::size_of::<ParticleInstanceVertex>() as u64;
+        let start = self.instance_offset * stride;
+        let end = start + instances.len() as u64 * stride;
+        queue.write_buffer(
+            &self.instance_buffer,
+            start,
+            bytemuck::cast_slice(instances),
+        );
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, texture_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, self.instance_buffer.slice(start..end));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        pass.draw_indexed(0..6, 0, 0..instances.len() as u32);
+        self.instance_offset += instances.len() as u64;
+    }
+
+    pub fn reset_context(&mut self) {
+        self.instance_offset = 0;
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    camera_2d::camera_2d::PixelPerfectConfig, graphics_2d::shape_pipelines::create_2d_pipeline,
+};
+
+/// Holds the low-res frame of pixel-perfect mode and scales it onto the window by a whole
+/// number, clearing the margins around it to black.
+pub struct PixelUpscaler {
+    config: PixelPerfectConfig,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    frame_view: wgpu::TextureView,
+}
+
+impl PixelUpscaler {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        config: PixelPerfectConfig,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("upscale_bind_group_layout"),
+        });
+
+        let frame_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("2d_pixel_frame_texture"),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&frame_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("Upscale Bind Group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/2d_upscale.wgsl"));
+        let pipeline = create_2d_pipeline(
+            "2D Upscale Pipeline",
+            device,
+            format,
+            &shader,
+            &[],
+            &Vec::from([&bind_group_layout]),
+            None,
+        );
+
+        Self {
+            config,
+            pipeline,
+            bind_group,
+            frame_view,
+        }
+    }
+
+    /// The surface config the world, light and post passes render with.
+    pub fn frame_config(&self, surface: &wgpu::SurfaceConfiguration) -> wgpu::SurfaceConfiguration {
+        wgpu::SurfaceConfiguration {
+            width: self.config.width,
+            height: self.config.height,
+            ..surface.clone()
+        }
+    }
+
+    /// Where the world goes before it is scaled up.
+    pub fn frame_view(&self) -> &wgpu::TextureView {
+        &self.frame_view
+    }
+
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        window_size: [u32; 2],
+    ) {
+        let [x, y, width, height] = self.config.viewport(window_size[0], window_size[1]);
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        if width < 1.0 || height < 1.0 {
+            return;
+        }
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use bytemuck::{Pod, Zeroable};
+use image::DynamicImage;
+
+use crate::{
+    components_systems::{PostEffect, PostEffectKind},
+    graphics_2d::shape_pipelines::create_2d_pipeline_with_targets,
+    texture::Texture,
+};
+
+const POST_COMMON: &str = include_str!("shaders/post/common.wgsl");
+// uniforms of one frame's chain share a buffer, effects past this are not drawn
+const MAX_POST_EFFECTS: u64 = 16;
+const UNIFORM_STRIDE: u64 = 256; // min_uniform_buffer_offset_alignment
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct PostUniform {
+    resolution: [f32; 2],
+    time: f32,
+    strength: f32,
+    params: [[f32; 4]; 4],
+}
+
+/// Runs the world's post effects as a chain of full-screen passes, ping-ponging between two
+/// offscreen targets and writing the last one to the surface.
+pub struct PostProcessor {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: HashMap<PostEffectKind, wgpu::RenderPipeline>,
+    format: wgpu::TextureFormat,
+    targets: [wgpu::TextureView; 2],
+    resolution: [f32; 2],
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    white: Texture, // bound for effects without a texture
+    started: Instant,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(3),
+            ],
+            label: Some("post_bind_group_layout"),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Uniform Buffer"),
+            size: MAX_POST_EFFECTS * UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut white_image = image::RgbaImage::new(1, 1);
+        white_image.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+        let white = Texture::from_image(
+            "post_white".to_string(),
+            device,
+            queue,
+            &DynamicImage::ImageRgba8(white_image),
+            Some("post_white"),
+        )
+        .unwrap();
+
+        let mut post_processor = Self {
+            bind_group_layout,
+            pipelines: HashMap::new(),
+            format: config.format,
+            targets: create_targets(device, config),
+            resolution: [config.width as f32, config.height as f32],
+            sampler,
+            uniform_buffer,
+            white,
+            started: Instant::now(),
+        };
+
+        for kind in PostEffectKind::BUILT_IN {
+            let source = match kind {
+                PostEffectKind::ColorGrade => include_str!("shaders/post/color_grade.wgsl"),
+                PostEffectKind::Vignette => include_str!("shaders/post/vignette.wgsl"),
+                PostEffectKind::Bloom => include_str!("shaders/post/bloom.wgsl"),
+                PostEffectKind::Crt => include_str!("shaders/post/crt.wgsl"),
+                PostEffectKind::Flash => include_str!("shaders/post/flash.wgsl"),
+                PostEffectKind::ChromaticAberration => {
+                    include_str!("shaders/post/chromatic_aberration.wgsl")
+                }
+                PostEffectKind::Custom(_) => continue,
+            };
+            let pipeline = post_processor.create_pipeline(device, kind.name(), source);
+            post_processor.pipelines.insert(kind, pipeline);
+        }
+        post_processor
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.targets = create_targets(device, config);
+        self.resolution = [config.width as f32, config.height as f32];
+    }
+
+    /// Compiles `source`, a fragment shader written against `shaders/post/common.wgsl`.
+    /// Reports shader errors instead of letting wgpu abort on them.
+    pub fn load_effect(
+        &mut self,
+        device: &wgpu::Device,
+        name: &str,
+        source: &str,
+    ) -> Result<(), String> {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = self.create_pipeline(device, name, source);
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            return Err(error.to_string());
+        }
+        self.pipelines
+            .insert(PostEffectKind::from_name(name), pipeline);
+        Ok(())
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        name: &str,
+        source: &str,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(format!("{}\n{}", POST_COMMON, source).into()),
+        });
+        create_2d_pipeline_with_targets(
+            name,
+            device,
+            &[Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            &shader,
+            &[],
+            &Vec::from([&self.bind_group_layout]),
+            None,
+        )
+    }
+
+    /// The effects that will run this frame, in order.
+    fn active<'a>(&self, effects: &'a [PostEffect]) -> Vec<&'a PostEffect> {
+        effects
+            .iter()
+            .filter(|effect| effect.enabled && self.pipelines.contains_key(&effect.kind))
+            .take(MAX_POST_EFFECTS as usize)
+            .collect()
+    }
+
+    pub fn is_active(&self, effects: &[PostEffect]) -> bool {
+        !self.active(effects).is_empty()
+    }
+
+    /// Where the frame goes before the chain runs.
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        &self.targets[0]
+    }
+
+    /// `textures` holds the loaded textures effects can name by asset path.
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        effects: &[PostEffect],
+        textures: &HashMap<String, Texture>,
+        output: &wgpu::TextureView,
+    ) {
+        let active = self.active(effects);
+        let time = self.started.elapsed().as_secs_f32();
+
+        for (index, effect) in active.iter().enumerate() {
+            let uniform = PostUniform {
+                resolution: self.resolution,
+                time,
+                strength: effect.strength,
+                params: effect.params,
+            };
+            let offset = index as u64 * UNIFORM_STRIDE;
+            queue.write_buffer(
+                &self.uniform_buffer,
+                offset,
+                bytemuck::cast_slice(&[uniform]),
+            );
+
+            let effect_texture = effect
+                .texture
+                .as_ref()
+                .and_then(|texture| textures.get(texture))
+                .unwrap_or(&self.white);
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&self.targets[index % 2]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: &self.uniform_buffer,
+                            offset,
+                            size: wgpu::BufferSize::new(std::mem::size_of::<PostUniform>() as u64),
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&effect_texture.view),
+                    },
+                ],
+                label: Some("Post Bind Group"),
+            });
+
+            let target = if index + 1 == active.len() {
+                output
+            } else {
+                &self.targets[(index + 1) % 2]
+            };
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(effect.kind.name()),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipelines[&effect.kind]);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+fn create_targets(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> [wgpu::TextureView; 2] {
+    ["post_ping_texture", "post_pong_texture"].map(|label| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: config.width.max(1),
+                    height: config.height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    })
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{channel, Receiver, TryRecvError},
+};
+
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+
+/// An offscreen texture a frame can be drawn into instead of the window, and read back.
+pub struct RenderTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl RenderTarget {
+    /// `format` should be the surface format, the pipelines are built for it.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("2d_render_target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            width: width.max(1),
+            height: height.max(1),
+            format,
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    fn extent(&self) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    // rows of a texture to buffer copy are padded to 256 bytes
+    fn padded_row_bytes(&self) -> u32 {
+        (self.width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+    }
+
+    fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder, buffer: &wgpu::Buffer) {
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_row_bytes()),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            self.extent(),
+        );
+    }
+
+    /// Copies the whole target onto a texture of the same size and format, e.g. the window's.
+    pub fn copy_to_texture(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            self.extent(),
+        );
+    }
+
+    /// Copies the texture back to the CPU, blocking until the GPU is done with it.
+    /// Frames captured while the game runs go through `FrameReadback` instead.
+    #[allow(dead_code)]
+    pub fn read_image(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<RgbaImage> {
+        let mut readback = FrameReadback::new();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Target Readback Encoder"),
+        });
+        readback.copy(device, &mut encoder, self, 0)?;
+        queue.submit(Some(encoder.finish()));
+        readback.map_copied();
+        readback
+            .take_ready(device, true)
+            .pop()
+            .map(|(_, image)| image)
+            .unwrap_or_else(|| Err(anyhow!("render target readback never finished")))
+    }
+}
+
+// a frame copied into `buffer`, waiting for its mapping
+struct Readback {
+    id: u64,
+    buffer: wgpu::Buffer,
+    size: [u32; 2],
+    padded_row_bytes: u32,
+    swap_red_blue: bool,
+    mapped: Option<Receiver<Result<(), wgpu::BufferAsyncError>>>, // None until submitted
+}
+
+/// Frames on their way from a render target back to the CPU. Buffers are mapped without
+/// waiting for the GPU and reused once read, so capturing doesn't stall the frame it's on.
+pub struct FrameReadback {
+    in_flight: VecDeque<Readback>, // in the order they were copied
+    free: Vec<wgpu::Buffer>,
+}
+
+impl FrameReadback {
+    pub fn new() -> Self {
+        Self {
+            in_flight: VecDeque::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Records a copy of `target` as frame `id`. `map_copied` has to follow once the
+    /// encoder has been submitted.
+    pub fn copy(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &RenderTarget,
+        id: u64,
+    ) -> Result<()> {
+        let swap_red_blue = match target.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(anyhow!("can't read back {:?} render targets", format)),
+        };
+        let padded_row_bytes = target.padded_row_bytes();
+        let size = (padded_row_bytes * target.height) as u64;
+        // buffers of another size were for a window size that's gone
+        self.free.retain(|buffer| buffer.size() == size);
+        let buffer = self.free.pop().unwrap_or_else(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Frame Readback Buffer"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            })
+        });
+        target.copy_to_buffer(encoder, &buffer);
+        self.in_flight.push_back(Readback {
+            id,
+            buffer,
+            size: target.size(),
+            padded_row_bytes,
+            swap_red_blue,
+            mapped: None,
+        });
+        Ok(())
+    }
+
+    /// Starts mapping every copy recorded since the last call, after their submit.
+    pub fn map_copied(&mut self) {
+        for readback in self.in_flight.iter_mut() {
+            if readback.mapped.is_none() {
+                let (sender, receiver) = channel();
+                readback
+                    .buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        let _ = sender.send(result);
+                    });
+                readback.mapped = Some(receiver);
+            }
+        }
+    }
+
+    /// The frames whose mapping finished, oldest first. A frame still in flight holds back the
+    /// ones after it so recordings stay in order. `wait` blocks until all of them are back.
+    pub fn take_ready(
+        &mut self,
+        device: &wgpu::Device,
+        wait: bool,
+    ) -> Vec<(u64, Result<RgbaImage>)> {
+        if self.in_flight.is_empty() {
+            return Vec::new();
+        }
+        let poll = match wait {
+            true => wgpu::PollType::Wait,
+            false => wgpu::PollType::Poll,
+        };
+        if let Err(e) = device.poll(poll) {
+            println!("Frame readback poll failed: {}", e);
+        }
+
+        let mut ready = Vec::new();
+        while let Some(readback) = self.in_flight.front() {
+            let result = match readback.mapped.as_ref().map(|mapped| mapped.try_recv()) {
+                Some(Ok(result)) => result.map_err(|e| anyhow!(e)),
+                Some(Err(TryRecvError::Empty)) | None => break,
+                Some(Err(TryRecvError::Disconnected)) => Err(anyhow!("frame readback was dropped")),
+            };
+            let readback = self.in_flight.pop_front().unwrap();
+            let image = match result {
+                Ok(()) => {
+                    let image = readback.read();
+                    readback.buffer.unmap();
+                    self.free.push(readback.buffer);
+                    image
+                }
+                // failed buffers are dropped rather than reused
+                Err(e) => Err(e),
+            };
+            ready.push((readback.id, image));
+        }
+        ready
+    }
+}
+
+impl Readback {
+    fn read(&self) -> Result<RgbaImage> {
+        let [width, height] = self.size;
+        let row_bytes = (width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks(self.padded_row_bytes as usize) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        if self.swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("render target readback has the wrong size"))
+    }
+}
@@ -0,0 +1,50 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // unit quad, -0.5..0.5
+    @location(1) instance_pos: vec2<f32>,
+    @location(2) instance_size: f32,
+    @location(3) instance_rotation: f32,
+    @location(4) instance_uv_rect: vec4<f32>, // u0 v0 u1 v1
+    @location(5) instance_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let c = cos(input.instance_rotation);
+    let s = sin(input.instance_rotation);
+    let local = input.position * input.instance_size;
+    let rotated = vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+    out.clip_position = camera.view_proj * vec4<f32>(rotated + input.instance_pos, 0.0, 1.0);
+
+    // same orientation as the sprite quads: the top of the quad samples v1
+    let t = input.position + vec2<f32>(0.5, 0.5);
+    out.tex_coords = mix(input.instance_uv_rect.xy, input.instance_uv_rect.zw, t);
+    out.color = input.instance_color;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords) * in.color;
+    if (color.a < .01) {
+        discard;
+    }
+    return color;
+}
@@ -8,11 +8,15 @@ var<uniform> camera: CameraUniform;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) tint: vec4<f32>,
+    @location(3) flash: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) flash: vec4<f32>,
 }
 
 @vertex
@@ -21,6 +25,8 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.tint = model.tint;
+    out.flash = model.flash;
     //Multiplication order is important when it comes to matrices. The vector goes on the right, and the matrices go on the left in order of importance.
     out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
 
@@ -32,12 +38,26 @@ fn vs_main(
 var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
+// sheets without a normal map get a transparent 1x1, which the light pass reads as "not normal mapped"
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+}
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-   if (color.a < .01) {
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // tint multiplies, flash blends toward a solid color while keeping the sprite silhouette
+    var color = texel * in.tint;
+    color = vec4<f32>(mix(color.rgb, in.flash.rgb, in.flash.a), color.a);
+    if (color.a < .01) {
         discard;
     }
-    return color;
+    var out: FragmentOutput;
+    out.color = color;
+    out.normal = textureSample(t_normal, s_diffuse, in.tex_coords);
+    return out;
 }
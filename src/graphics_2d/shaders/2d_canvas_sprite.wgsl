@@ -1,12 +1,22 @@
 // Vertex shader
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) tint: vec4<f32>,
+    @location(3) flash: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) flash: vec4<f32>,
 }
 
 @vertex
@@ -15,8 +25,11 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.tint = model.tint;
+    out.flash = model.flash;
     //Multiplication order is important when it comes to matrices. The vector goes on the right, and the matrices go on the left in order of importance.
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    // canvas positions are in pixels around the screen center; the static camera maps them to clip space
+    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
 
     return out;
 }
@@ -29,8 +42,11 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-let color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-   if (color.a < .01) {
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // tint multiplies, flash blends toward a solid color while keeping the sprite silhouette
+    var color = texel * in.tint;
+    color = vec4<f32>(mix(color.rgb, in.flash.rgb, in.flash.a), color.a);
+    if (color.a < .01) {
         discard;
     }
     return color;
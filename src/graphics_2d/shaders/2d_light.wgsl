@@ -0,0 +1,65 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>, // unit quad, -1..1
+    @location(1) light_pos: vec2<f32>,
+    @location(2) radius: f32,
+    @location(3) falloff: f32,
+    @location(4) color: vec3<f32>,
+    @location(5) height: f32,
+    @location(6) direction: vec2<f32>,
+    @location(7) cone: vec2<f32>, // cos outer, cos inner
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) to_pixel: vec2<f32>, // world space, from the light
+    @location(1) radius: f32,
+    @location(2) falloff: f32,
+    @location(3) color: vec3<f32>,
+    @location(4) height: f32,
+    @location(5) direction: vec2<f32>,
+    @location(6) cone: vec2<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.to_pixel = input.position * input.radius;
+    out.clip_position = camera.view_proj * vec4<f32>(input.light_pos + out.to_pixel, 0.0, 1.0);
+    out.radius = input.radius;
+    out.falloff = input.falloff;
+    out.color = input.color;
+    out.height = input.height;
+    out.direction = input.direction;
+    out.cone = input.cone;
+    return out;
+}
+
+// written by the sprites in the world pass, rgb is the normal and alpha is 0 where there is none
+@group(0) @binding(0)
+var t_normal: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.to_pixel);
+    var strength = pow(clamp(1.0 - distance / in.radius, 0.0, 1.0), in.falloff);
+
+    if (distance > 0.0001) {
+        let to_pixel = in.to_pixel / distance;
+        strength *= smoothstep(in.cone.x, in.cone.y, dot(to_pixel, in.direction));
+    }
+
+    let normal = textureLoad(t_normal, vec2<i32>(in.clip_position.xy), 0);
+    if (normal.a > 0.5) {
+        let n = normalize(normal.rgb * 2.0 - 1.0);
+        let l = normalize(vec3<f32>(-in.to_pixel, in.height));
+        strength *= max(dot(n, l), 0.0);
+    }
+
+    return vec4<f32>(in.color * strength, 1.0);
+}
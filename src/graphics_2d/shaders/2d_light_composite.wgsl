@@ -0,0 +1,25 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// one triangle that covers the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var t_light: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_position.xy);
+    let scene = textureLoad(t_scene, pixel, 0);
+    let light = textureLoad(t_light, pixel, 0);
+    return vec4<f32>(scene.rgb * light.rgb, scene.a);
+}
@@ -0,0 +1,78 @@
+// Prepended to every material shader. A material only writes `fs_main`, usually starting
+// from `sprite_color(in)` and returning `sprite_output(color, in)`.
+
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) tint: vec4<f32>,
+    @location(3) flash: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+    @location(2) flash: vec4<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.tint = model.tint;
+    out.flash = model.flash;
+    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+
+struct MaterialUniform {
+    time: f32, // seconds since startup
+    // the material's named parameters, one vec4 each in the order they were declared
+    params: array<vec4<f32>, 8>,
+}
+@group(2) @binding(0)
+var<uniform> material: MaterialUniform;
+// the material's `texture`, a white 1x1 when it has none
+@group(2) @binding(1)
+var t_material: texture_2d<f32>;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+}
+
+// what the default sprite shader draws: the texel, tinted and flashed
+fn sprite_color(in: VertexOutput) -> vec4<f32> {
+    return shade_texel(textureSample(t_diffuse, s_diffuse, in.tex_coords), in);
+}
+
+// applies the sprite's tint and flash to a texel a material picked itself
+fn shade_texel(texel: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+    let color = texel * in.tint;
+    return vec4<f32>(mix(color.rgb, in.flash.rgb, in.flash.a), color.a);
+}
+
+// the sprite sheet's size in texels, for effects that work on whole texels
+fn sprite_texel_size() -> vec2<f32> {
+    return vec2<f32>(textureDimensions(t_diffuse));
+}
+
+fn sprite_output(color: vec4<f32>, in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = color;
+    out.normal = textureSample(t_normal, s_diffuse, in.tex_coords);
+    return out;
+}
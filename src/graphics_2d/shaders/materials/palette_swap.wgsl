@@ -0,0 +1,42 @@
+// Built in material for palette swaps. `t_material` is the palette atlas: texels matching
+// a color of the source row take the color in the same column of the variant row.
+// params[0]: source row, variant row, color count
+
+// sprites decode from sRGB when sampled, the atlas keeps its bytes as they are. Colors are
+// compared as bytes, linear values of dark shades are too close together to tell apart
+fn srgb_bytes(linear: vec3<f32>) -> vec3<i32> {
+    let c = clamp(linear, vec3<f32>(0.0), vec3<f32>(1.0));
+    let encoded = select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+    return vec3<i32>(round(encoded * 255.0));
+}
+
+fn srgb_to_linear(encoded: vec3<f32>) -> vec3<f32> {
+    return select(pow((encoded + 0.055) / 1.055, vec3<f32>(2.4)), encoded / 12.92, encoded <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let palette = material.params[0];
+    // textures are flipped on load, so atlas rows count up from the bottom
+    let last_row = i32(textureDimensions(t_material).y) - 1;
+    let source_row = last_row - i32(palette.x);
+    let variant_row = last_row - i32(palette.y);
+
+    let bytes = srgb_bytes(texel.rgb);
+    var recolored = texel;
+    for (var i = 0; i < i32(palette.z); i++) {
+        let source = textureLoad(t_material, vec2<i32>(i, source_row), 0);
+        if (all(vec3<i32>(round(source.rgb * 255.0)) == bytes)) {
+            let swapped = textureLoad(t_material, vec2<i32>(i, variant_row), 0);
+            recolored = vec4<f32>(srgb_to_linear(swapped.rgb), texel.a * swapped.a);
+            break;
+        }
+    }
+
+    let color = shade_texel(recolored, in);
+    if (color.a < .01) {
+        discard;
+    }
+    return sprite_output(color, in);
+}
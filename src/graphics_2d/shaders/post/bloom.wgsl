@@ -0,0 +1,22 @@
+// single pass glow: bright neighbours in three rings are averaged and added on top
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(in.uv);
+    let threshold = post.params[0].x;
+    let radius = post.params[0].y / post.resolution;
+
+    var glow = vec3<f32>(0.0);
+    var total = 0.0;
+    for (var ring = 1; ring <= 3; ring++) {
+        let distance = f32(ring) / 3.0;
+        let weight = 1.0 / f32(ring);
+        for (var step = 0; step < 8; step++) {
+            let angle = f32(step) * 0.78539816 + f32(ring) * 0.39269908;
+            let offset = vec2<f32>(cos(angle), sin(angle)) * radius * distance;
+            let neighbour = sample_input(in.uv + offset).rgb;
+            glow += max(neighbour - vec3<f32>(threshold), vec3<f32>(0.0)) * weight;
+            total += weight;
+        }
+    }
+    return vec4<f32>(color.rgb + glow / total * post.strength, color.a);
+}
@@ -0,0 +1,10 @@
+// red and blue pulled apart along the line from the center, none in the middle of the screen
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let from_center = in.uv - vec2<f32>(0.5);
+    let offset = from_center * 2.0 * post.params[0].x / post.resolution * post.strength;
+    let color = sample_input(in.uv);
+    let red = sample_input(in.uv + offset).r;
+    let blue = sample_input(in.uv - offset).b;
+    return vec4<f32>(red, color.g, blue, color.a);
+}
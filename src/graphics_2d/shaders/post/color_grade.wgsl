@@ -0,0 +1,26 @@
+// the LUT is 16 (or any N) slices of NxN laid out left to right, blue picks the slice
+fn lookup(color: vec3<f32>, size: f32, slice: f32) -> vec3<f32> {
+    let x = (slice * size + color.r * (size - 1.0) + 0.5) / (size * size);
+    // textures are flipped on load, so the top row of the image is at v = 1
+    let y = 1.0 - (color.g * (size - 1.0) + 0.5) / size;
+    return textureSampleLevel(effect_texture, input_sampler, vec2<f32>(x, y), 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(in.uv);
+    let size = f32(textureDimensions(effect_texture).y);
+    if (size < 2.0) {
+        return color;
+    }
+    // LUTs are authored on sRGB values, the LUT texture itself decodes back to linear
+    let encoded = pow(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+    let blue = encoded.b * (size - 1.0);
+    let slice = floor(blue);
+    let graded = mix(
+        lookup(encoded, size, slice),
+        lookup(encoded, size, min(slice + 1.0, size - 1.0)),
+        blue - slice,
+    );
+    return vec4<f32>(mix(color.rgb, graded, post.strength), color.a);
+}
@@ -0,0 +1,42 @@
+// Shared by every post effect, custom effects get this prepended and only write `fs_main`:
+//
+//   @fragment
+//   fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+//       let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0);
+//       return mix(color, vec4<f32>(1.0) - color, post.strength);
+//   }
+struct PostUniform {
+    resolution: vec2<f32>, // pixels
+    time: f32,             // seconds since the renderer started
+    strength: f32,
+    params: array<vec4<f32>, 4>,
+};
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+// the effect's `texture`, 1x1 white when it has none
+@group(0) @binding(3)
+var effect_texture: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>, // 0,0 is the top left
+};
+
+// one triangle that covers the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let corner = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+fn sample_input(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(input_texture, input_sampler, uv, 0.0);
+}
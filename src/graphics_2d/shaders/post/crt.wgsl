@@ -0,0 +1,22 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let curvature = post.params[0].x;
+    let scanlines = post.params[0].y;
+    let line_height = max(post.params[0].z, 1.0);
+
+    // barrel distortion, stronger toward the corners
+    var centered = in.uv * 2.0 - 1.0;
+    centered += centered * (centered.yx * centered.yx) * curvature;
+    let uv = centered * 0.5 + 0.5;
+    let outside = any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0));
+
+    var crt = sample_input(uv).rgb;
+    let line = 0.5 + 0.5 * cos(uv.y * post.resolution.y / line_height * 6.2831853);
+    crt *= 1.0 - scanlines * line;
+    if (outside) {
+        crt = vec3<f32>(0.0);
+    }
+
+    let color = sample_input(in.uv);
+    return vec4<f32>(mix(color.rgb, crt, post.strength), color.a);
+}
@@ -0,0 +1,6 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(in.uv);
+    let amount = clamp(post.strength, 0.0, 1.0);
+    return vec4<f32>(mix(color.rgb, post.params[0].rgb, amount), color.a);
+}
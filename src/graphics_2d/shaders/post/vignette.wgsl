@@ -0,0 +1,10 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(in.uv);
+    let radius = post.params[1].x;
+    let softness = post.params[1].y;
+    // 0 in the middle, 1 in the corners
+    let distance = length(in.uv - vec2<f32>(0.5)) * 1.41421356;
+    let amount = smoothstep(radius - softness, radius, distance) * post.strength;
+    return vec4<f32>(mix(color.rgb, post.params[0].rgb, amount), color.a);
+}
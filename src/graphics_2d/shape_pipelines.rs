@@ -8,6 +8,31 @@ pub fn create_2d_pipeline(
     vertex_desc: &[wgpu::VertexBufferLayout],
     bind_group_layouts: &Vec<&wgpu::BindGroupLayout>,
     depth_stencil: Option<wgpu::DepthStencilState>,
+) -> wgpu::RenderPipeline {
+    create_2d_pipeline_with_targets(
+        label,
+        device,
+        &[Some(wgpu::ColorTargetState {
+            format: surface_format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        })],
+        shader,
+        vertex_desc,
+        bind_group_layouts,
+        depth_stencil,
+    )
+}
+
+/// Same as `create_2d_pipeline` for passes that don't draw one alpha blended surface.
+pub fn create_2d_pipeline_with_targets(
+    label: &str,
+    device: &wgpu::Device,
+    targets: &[Option<wgpu::ColorTargetState>],
+    shader: &wgpu::ShaderModule,
+    vertex_desc: &[wgpu::VertexBufferLayout],
+    bind_group_layouts: &Vec<&wgpu::BindGroupLayout>,
+    depth_stencil: Option<wgpu::DepthStencilState>,
 ) -> wgpu::RenderPipeline {
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some(label),
@@ -27,11 +52,7 @@ pub fn create_2d_pipeline(
         fragment: Some(FragmentState {
             module: &shader,
             entry_point: Some("fs_main"),
-            targets: &[Some(wgpu::ColorTargetState {
-                format: surface_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets,
             compilation_options: Default::default(),
         }),
         primitive: wgpu::PrimitiveState::default(),
@@ -154,17 +154,38 @@ impl TessellatedShape2D {
             .collect()
     }
 
-    pub fn into_tex(&self, tex_coords: [[f32; 2]; 4]) -> Vec<TextureVertex> {
+    pub fn into_tex(
+        &self,
+        tex_coords: [[f32; 2]; 4],
+        tint: [f32; 4],
+        flash: [f32; 4],
+    ) -> Vec<TextureVertex> {
         self.vertices
             .iter()
             .zip(tex_coords.iter())
             .map(|(v, &uv)| TextureVertex {
                 position: [v.x, v.y],
                 tex_coords: uv,
+                tint,
+                flash,
             })
             .collect()
     }
 
+    /// Rotates all vertices counter-clockwise around the local origin.
+    /// Call before `recenter` so the shape spins around its own center.
+    pub fn rotate(&mut self, radians: f32) -> &mut Self {
+        if radians == 0.0 {
+            return self;
+        }
+
+        let (sin, cos) = radians.sin_cos();
+        for pos in &mut self.vertices {
+            *pos = Vector2::new(pos.x * cos - pos.y * sin, pos.x * sin + pos.y * cos);
+        }
+        self
+    }
+
     pub fn recenter(&mut self, center: Vector2<f32>) -> &mut Self {
         if self.vertices.is_empty() {
             return self;
@@ -55,6 +55,8 @@ impl ColorVertex {
 pub struct TextureVertex {
     pub position: [f32; 2],
     pub tex_coords: [f32; 2],
+    pub tint: [f32; 4],
+    pub flash: [f32; 4],
 }
 
 impl TextureVertex {
@@ -74,6 +76,17 @@ impl TextureVertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<[f32; 2]>() * 2 + mem::size_of::<[f32; 4]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -116,3 +129,63 @@ impl DebugInstanceVertex {
         }
     }
 }
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct ParticleInstanceVertex {
+    pub position: [f32; 2], // @location(1)
+    pub size: f32,          // @location(2)
+    pub rotation: f32,      // @location(3)
+    pub uv_rect: [f32; 4],  // @location(4), u0 v0 u1 v1
+    pub color: [f32; 4],    // @location(5)
+}
+
+impl ParticleInstanceVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            1 => Float32x2,
+            2 => Float32,
+            3 => Float32,
+            4 => Float32x4,
+            5 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<ParticleInstanceVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LightInstanceVertex {
+    pub position: [f32; 2],  // @location(1)
+    pub radius: f32,         // @location(2)
+    pub falloff: f32,        // @location(3)
+    pub color: [f32; 3],     // @location(4), already scaled by intensity
+    pub height: f32,         // @location(5)
+    pub direction: [f32; 2], // @location(6)
+    pub cone: [f32; 2],      // @location(7), cos of the outer and inner angle
+}
+
+impl LightInstanceVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        const ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+            1 => Float32x2,
+            2 => Float32,
+            3 => Float32,
+            4 => Float32x3,
+            5 => Float32,
+            6 => Float32x2,
+            7 => Float32x2,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<LightInstanceVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
@@ -35,6 +35,7 @@ impl WorldRenderBatch {
         &mut self,
         id: String,
         texture: Texture,
+        normal_map: &Texture,
         device: &mut wgpu::Device,
         texture_bind_group_layout: &wgpu::BindGroupLayout,
     ) {
@@ -51,12 +52,20 @@ impl WorldRenderBatch {
                         binding: 1,
                         resource: wgpu::BindingResource::Sampler(&texture.sampler),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&normal_map.view),
+                    },
                 ],
                 label: Some("Texture Bind Group"),
             }),
         );
     }
 
+    pub fn bind_group(&self, id: &str) -> Option<&wgpu::BindGroup> {
+        self.bind_group_cache.get(id)
+    }
+
     pub fn enqueue_next_texture(
         &mut self,
         element: &RenderElement2D,
@@ -78,12 +87,14 @@ impl WorldRenderBatch {
             }),
             100,
         );
-        shape.recenter(Vector2 {
+        shape.rotate(element.rotation).recenter(Vector2 {
             x: element.position[0],
             y: element.position[1],
         });
 
-        let vertices = shape.into_tex(element.uv_coords);
+        let mut tint = element.tint;
+        tint[3] *= element.opacity;
+        let vertices = shape.into_tex(element.uv_coords, tint, element.flash);
         let indices: Vec<u16> = shape
             .indices
             .iter()
@@ -562,6 +562,7 @@ impl Graphics for Graphics3D {
         return crate::graphics::CameraInfo {
             zoom: 1.0,
             position: [0.0, 0.0, 0.0],
+            viewport: [0.0, 0.0, self.config.width as f32, self.config.height as f32],
         };
     }
 
@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::inputs::is_input_name;
+
+/// Inputs are the names the engine reports ("w", "mouseleft", "gamepad_a", "gamepad_leftx"...).
+/// Digital axes read `positive - negative`; analog inputs win when they are off center.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AxisBinding {
+    pub negative: Vec<String>,
+    pub positive: Vec<String>,
+    pub analog: Vec<String>,
+}
+
+/// A named set of bindings, e.g. "gameplay" or "menu". Only the context on top of the
+/// stack is live, so a pushed menu swallows gameplay actions until it is popped.
+#[derive(Debug, Clone, Default)]
+pub struct InputContext {
+    actions: BTreeMap<String, Vec<String>>,
+    axes: BTreeMap<String, AxisBinding>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RawInput {
+    down: bool,
+    value: f32,
+    pressed_this_frame: bool, // survives a press and release inside one frame
+    released_this_frame: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActionState {
+    pub down: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    pub held_for: f32, // seconds, 0 while up
+}
+
+pub struct InputMap {
+    contexts: HashMap<String, InputContext>,
+    stack: Vec<String>,
+    pending: Vec<(String, bool, f32)>, // events since the last update
+    raw: HashMap<String, RawInput>,
+    actions: HashMap<String, ActionState>,
+    axes: HashMap<String, f32>,
+    last_pressed: Option<String>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            contexts: HashMap::new(),
+            stack: Vec::new(),
+            pending: Vec::new(),
+            raw: HashMap::new(),
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+            last_pressed: None,
+        }
+    }
+
+    /// Replaces the inputs bound to `action`, unknown input names are skipped.
+    pub fn bind_action(&mut self, context: &str, action: &str, inputs: Vec<String>) -> bool {
+        let (inputs, all_valid) = valid_inputs(inputs);
+        self.contexts
+            .entry(context.to_string())
+            .or_default()
+            .actions
+            .insert(action.to_string(), inputs);
+        all_valid
+    }
+
+    pub fn bind_axis(&mut self, context: &str, axis: &str, binding: AxisBinding) -> bool {
+        let (negative, negative_valid) = valid_inputs(binding.negative);
+        let (positive, positive_valid) = valid_inputs(binding.positive);
+        let (analog, analog_valid) = valid_inputs(binding.analog);
+        self.contexts
+            .entry(context.to_string())
+            .or_default()
+            .axes
+            .insert(
+                axis.to_string(),
+                AxisBinding {
+                    negative,
+                    positive,
+                    analog,
+                },
+            );
+        negative_valid && positive_valid && analog_valid
+    }
+
+    pub fn push_context(&mut self, context: &str) {
+        self.stack.push(context.to_string());
+    }
+
+    pub fn pop_context(&mut self) -> Option<String> {
+        self.stack.pop()
+    }
+
+    pub fn active_context(&self) -> Option<&String> {
+        self.stack.last()
+    }
+
+    /// Called for every input event the engine forwards, applied on the next `update` so
+    /// state stays the same for the whole frame.
+    pub fn record(&mut self, input: &str, is_pressed: bool, value: f32) {
+        self.pending.push((input.to_lowercase(), is_pressed, value));
+    }
+
+    fn apply_pending(&mut self) {
+        for (input, is_pressed, value) in self.pending.drain(..) {
+            let raw = self.raw.entry(input.clone()).or_default();
+            if is_pressed && !raw.down {
+                raw.pressed_this_frame = true;
+                self.last_pressed = Some(input);
+            } else if !is_pressed && raw.down {
+                raw.released_this_frame = true;
+            }
+            raw.down = is_pressed;
+            raw.value = value;
+        }
+    }
+
+    /// Resolves actions and axes for this frame, run once before the Lua update.
+    pub fn update(&mut self, dt: f32) {
+        self.apply_pending();
+        let context = self.stack.last().and_then(|name| self.contexts.get(name));
+
+        // once each, an action bound in several contexts must only advance once per frame
+        let names: BTreeSet<String> = self
+            .contexts
+            .values()
+            .flat_map(|context| context.actions.keys().cloned())
+            .collect();
+        for name in names {
+            let inputs = context.and_then(|context| context.actions.get(&name));
+            let (mut down, mut tapped) = (false, false);
+            for input in inputs.into_iter().flatten() {
+                if let Some(raw) = self.raw.get(input) {
+                    down |= raw.down;
+                    tapped |= raw.pressed_this_frame;
+                }
+            }
+
+            let state = self.actions.entry(name).or_default();
+            let was_down = state.down;
+            // a tap pressed and released between updates counts as both
+            state.just_pressed = !was_down && (down || tapped);
+            state.just_released = !down && (was_down || tapped);
+            state.held_for = if down { state.held_for + dt } else { 0.0 };
+            state.down = down;
+        }
+
+        self.axes.clear();
+        if let Some(context) = context {
+            for (name, binding) in &context.axes {
+                let value = self.axis_value(binding);
+                self.axes.insert(name.clone(), value);
+            }
+        }
+    }
+
+    /// Clears per-frame flags, run after Lua has had its chance to read them.
+    pub fn end_frame(&mut self) {
+        for raw in self.raw.values_mut() {
+            raw.pressed_this_frame = false;
+            raw.released_this_frame = false;
+        }
+        self.last_pressed = None;
+    }
+
+    fn axis_value(&self, binding: &AxisBinding) -> f32 {
+        let strongest = binding
+            .analog
+            .iter()
+            .filter_map(|input| self.raw.get(input))
+            .map(|raw| raw.value)
+            .fold(0.0_f32, |a, b| if b.abs() > a.abs() { b } else { a });
+        if strongest != 0.0 {
+            return strongest.clamp(-1.0, 1.0);
+        }
+        let held = |inputs: &Vec<String>| {
+            inputs
+                .iter()
+                .any(|input| self.raw.get(input).is_some_and(|raw| raw.down))
+        };
+        held(&binding.positive) as i32 as f32 - held(&binding.negative) as i32 as f32
+    }
+
+    pub fn action(&self, name: &str) -> ActionState {
+        self.actions.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn axis(&self, name: &str) -> f32 {
+        self.axes.get(name).copied().unwrap_or(0.0)
+    }
+
+    /// The input pressed most recently this frame, for "press a key" rebinding prompts.
+    pub fn last_pressed(&self) -> Option<String> {
+        self.last_pressed.clone()
+    }
+
+    pub fn is_down(&self, input: &str) -> bool {
+        self.raw
+            .get(&input.to_lowercase())
+            .is_some_and(|raw| raw.down)
+    }
+
+    pub fn was_pressed_this_frame(&self, input: &str) -> bool {
+        self.raw
+            .get(&input.to_lowercase())
+            .is_some_and(|raw| raw.pressed_this_frame)
+    }
+
+    pub fn was_released_this_frame(&self, input: &str) -> bool {
+        self.raw
+            .get(&input.to_lowercase())
+            .is_some_and(|raw| raw.released_this_frame)
+    }
+
+    /// One line per binding:
+    ///
+    /// ```text
+    /// [gameplay]
+    /// action Dash = space, gamepad_a
+    /// axis MoveX = a, left | d, right | gamepad_leftx
+    /// ```
+    pub fn to_config_string(&self) -> String {
+        let mut names: Vec<&String> = self.contexts.keys().collect();
+        names.sort();
+
+        let mut out = String::new();
+        for name in names {
+            let context = &self.contexts[name];
+            out.push_str(&format!("[{}]\n", name));
+            for (action, inputs) in &context.actions {
+                out.push_str(&format!("action {} = {}\n", action, inputs.join(", ")));
+            }
+            for (axis, binding) in &context.axes {
+                out.push_str(&format!(
+                    "axis {} = {} | {} | {}\n",
+                    axis,
+                    binding.negative.join(", "),
+                    binding.positive.join(", "),
+                    binding.analog.join(", ")
+                ));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Applies a saved config over the current bindings, returns the lines it could not read.
+    pub fn apply_config_string(&mut self, config: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut context: Option<String> = None;
+
+        for line in config.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                context = Some(name.trim().to_string());
+                continue;
+            }
+
+            let parsed = context.as_ref().and_then(|context| {
+                let (kind, rest) = line.split_once(' ')?;
+                let (name, inputs) = rest.split_once('=')?;
+                let name = name.trim();
+                match kind {
+                    "action" => Some(self.bind_action(context, name, split_inputs(inputs))),
+                    "axis" => {
+                        let mut parts = inputs.split('|');
+                        let binding = AxisBinding {
+                            negative: split_inputs(parts.next()?),
+                            positive: split_inputs(parts.next()?),
+                            analog: split_inputs(parts.next().unwrap_or("")),
+                        };
+                        Some(self.bind_axis(context, name, binding))
+                    }
+                    _ => None,
+                }
+            });
+            if parsed != Some(true) {
+                errors.push(line.to_string());
+            }
+        }
+        errors
+    }
+}
+
+fn split_inputs(inputs: &str) -> Vec<String> {
+    inputs
+        .split(',')
+        .map(|input| input.trim().to_lowercase())
+        .filter(|input| !input.is_empty())
+        .collect()
+}
+
+fn valid_inputs(inputs: Vec<String>) -> (Vec<String>, bool) {
+    let count = inputs.len();
+    let valid: Vec<String> = inputs
+        .into_iter()
+        .map(|input| input.to_lowercase())
+        .filter(|input| {
+            let known = is_input_name(input);
+            if !known {
+                println!("Unknown input name: {}", input);
+            }
+            known
+        })
+        .collect();
+    let all_valid = valid.len() == count;
+    (valid, all_valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.016;
+
+    fn map() -> InputMap {
+        let mut map = InputMap::new();
+        map.bind_action("gameplay", "Jump", vec!["space".to_string()]);
+        map.bind_action("gameplay", "Screenshot", vec!["f12".to_string()]);
+        map.bind_action("menu", "Screenshot", vec!["f12".to_string()]);
+        map.bind_action("menu", "Confirm", vec!["enter".to_string()]);
+        map.push_context("gameplay");
+        map
+    }
+
+    fn frame(map: &mut InputMap, events: &[(&str, bool)]) {
+        for (input, is_pressed) in events {
+            map.record(input, *is_pressed, *is_pressed as i32 as f32);
+        }
+        map.update(DT);
+    }
+
+    fn next_frame(map: &mut InputMap, events: &[(&str, bool)]) {
+        map.end_frame();
+        frame(map, events);
+    }
+
+    #[test]
+    fn press_is_just_pressed_for_one_frame() {
+        let mut map = map();
+        frame(&mut map, &[("space", true)]);
+        let jump = map.action("Jump");
+        assert!(jump.down && jump.just_pressed && !jump.just_released);
+
+        next_frame(&mut map, &[]);
+        let jump = map.action("Jump");
+        assert!(jump.down && !jump.just_pressed);
+    }
+
+    #[test]
+    fn action_in_several_contexts_advances_once() {
+        let mut map = map();
+        frame(&mut map, &[("f12", true)]);
+        let screenshot = map.action("Screenshot");
+        assert!(screenshot.just_pressed);
+        assert_eq!(screenshot.held_for, DT);
+    }
+
+    #[test]
+    fn tap_inside_one_frame_is_pressed_and_released() {
+        let mut map = map();
+        frame(&mut map, &[("space", true), ("space", false)]);
+        let jump = map.action("Jump");
+        assert!(!jump.down && jump.just_pressed && jump.just_released);
+        assert_eq!(jump.held_for, 0.0);
+    }
+
+    #[test]
+    fn held_for_grows_while_down_and_resets_on_release() {
+        let mut map = map();
+        frame(&mut map, &[("space", true)]);
+        next_frame(&mut map, &[]);
+        next_frame(&mut map, &[]);
+        assert!((map.action("Jump").held_for - 3.0 * DT).abs() < 1e-6);
+
+        next_frame(&mut map, &[("space", false)]);
+        let jump = map.action("Jump");
+        assert!(!jump.down && jump.just_released && !jump.just_pressed);
+        assert_eq!(jump.held_for, 0.0);
+
+        next_frame(&mut map, &[]);
+        assert!(!map.action("Jump").just_released);
+    }
+
+    #[test]
+    fn only_the_top_context_is_live() {
+        let mut map = map();
+        map.push_context("menu");
+        frame(&mut map, &[("space", true), ("enter", true)]);
+        assert!(!map.action("Jump").down);
+        assert!(map.action("Confirm").just_pressed);
+
+        // popping back to gameplay picks up the key that is still held
+        assert_eq!(map.pop_context().as_deref(), Some("menu"));
+        next_frame(&mut map, &[]);
+        assert!(map.action("Jump").just_pressed);
+        assert!(!map.action("Confirm").down && map.action("Confirm").just_released);
+    }
+}
@@ -1,54 +1,250 @@
-pub fn mousebutton_to_str(button: winit::event::MouseButton) -> Option<&'static str> {
+use crate::gamepad::is_gamepad_input;
+use winit::event::MouseButton;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+// every winit key with the name Lua sees, also used to parse bindings back
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Backquote, "backquote"),
+    (KeyCode::Backslash, "backslash"),
+    (KeyCode::BracketLeft, "bracketleft"),
+    (KeyCode::BracketRight, "bracketright"),
+    (KeyCode::Comma, "comma"),
+    (KeyCode::Digit0, "0"),
+    (KeyCode::Digit1, "1"),
+    (KeyCode::Digit2, "2"),
+    (KeyCode::Digit3, "3"),
+    (KeyCode::Digit4, "4"),
+    (KeyCode::Digit5, "5"),
+    (KeyCode::Digit6, "6"),
+    (KeyCode::Digit7, "7"),
+    (KeyCode::Digit8, "8"),
+    (KeyCode::Digit9, "9"),
+    (KeyCode::Equal, "equal"),
+    (KeyCode::IntlBackslash, "intlbackslash"),
+    (KeyCode::IntlRo, "intlro"),
+    (KeyCode::IntlYen, "intlyen"),
+    (KeyCode::KeyA, "a"),
+    (KeyCode::KeyB, "b"),
+    (KeyCode::KeyC, "c"),
+    (KeyCode::KeyD, "d"),
+    (KeyCode::KeyE, "e"),
+    (KeyCode::KeyF, "f"),
+    (KeyCode::KeyG, "g"),
+    (KeyCode::KeyH, "h"),
+    (KeyCode::KeyI, "i"),
+    (KeyCode::KeyJ, "j"),
+    (KeyCode::KeyK, "k"),
+    (KeyCode::KeyL, "l"),
+    (KeyCode::KeyM, "m"),
+    (KeyCode::KeyN, "n"),
+    (KeyCode::KeyO, "o"),
+    (KeyCode::KeyP, "p"),
+    (KeyCode::KeyQ, "q"),
+    (KeyCode::KeyR, "r"),
+    (KeyCode::KeyS, "s"),
+    (KeyCode::KeyT, "t"),
+    (KeyCode::KeyU, "u"),
+    (KeyCode::KeyV, "v"),
+    (KeyCode::KeyW, "w"),
+    (KeyCode::KeyX, "x"),
+    (KeyCode::KeyY, "y"),
+    (KeyCode::KeyZ, "z"),
+    (KeyCode::Minus, "minus"),
+    (KeyCode::Period, "period"),
+    (KeyCode::Quote, "quote"),
+    (KeyCode::Semicolon, "semicolon"),
+    (KeyCode::Slash, "slash"),
+    (KeyCode::AltLeft, "altleft"),
+    (KeyCode::AltRight, "altright"),
+    (KeyCode::Backspace, "backspace"),
+    (KeyCode::CapsLock, "capslock"),
+    (KeyCode::ContextMenu, "contextmenu"),
+    (KeyCode::ControlLeft, "controlleft"),
+    (KeyCode::ControlRight, "controlright"),
+    (KeyCode::Enter, "enter"),
+    (KeyCode::SuperLeft, "superleft"),
+    (KeyCode::SuperRight, "superright"),
+    (KeyCode::ShiftLeft, "shiftleft"),
+    (KeyCode::ShiftRight, "shiftright"),
+    (KeyCode::Space, "space"),
+    (KeyCode::Tab, "tab"),
+    (KeyCode::Convert, "convert"),
+    (KeyCode::KanaMode, "kanamode"),
+    (KeyCode::Lang1, "lang1"),
+    (KeyCode::Lang2, "lang2"),
+    (KeyCode::Lang3, "lang3"),
+    (KeyCode::Lang4, "lang4"),
+    (KeyCode::Lang5, "lang5"),
+    (KeyCode::NonConvert, "nonconvert"),
+    (KeyCode::Delete, "delete"),
+    (KeyCode::End, "end"),
+    (KeyCode::Help, "help"),
+    (KeyCode::Home, "home"),
+    (KeyCode::Insert, "insert"),
+    (KeyCode::PageDown, "pagedown"),
+    (KeyCode::PageUp, "pageup"),
+    (KeyCode::ArrowDown, "down"),
+    (KeyCode::ArrowLeft, "left"),
+    (KeyCode::ArrowRight, "right"),
+    (KeyCode::ArrowUp, "up"),
+    (KeyCode::NumLock, "numlock"),
+    (KeyCode::Numpad0, "numpad0"),
+    (KeyCode::Numpad1, "numpad1"),
+    (KeyCode::Numpad2, "numpad2"),
+    (KeyCode::Numpad3, "numpad3"),
+    (KeyCode::Numpad4, "numpad4"),
+    (KeyCode::Numpad5, "numpad5"),
+    (KeyCode::Numpad6, "numpad6"),
+    (KeyCode::Numpad7, "numpad7"),
+    (KeyCode::Numpad8, "numpad8"),
+    (KeyCode::Numpad9, "numpad9"),
+    (KeyCode::NumpadAdd, "numpadadd"),
+    (KeyCode::NumpadBackspace, "numpadbackspace"),
+    (KeyCode::NumpadClear, "numpadclear"),
+    (KeyCode::NumpadClearEntry, "numpadclearentry"),
+    (KeyCode::NumpadComma, "numpadcomma"),
+    (KeyCode::NumpadDecimal, "numpaddecimal"),
+    (KeyCode::NumpadDivide, "numpaddivide"),
+    (KeyCode::NumpadEnter, "numpadenter"),
+    (KeyCode::NumpadEqual, "numpadequal"),
+    (KeyCode::NumpadHash, "numpadhash"),
+    (KeyCode::NumpadMemoryAdd, "numpadmemoryadd"),
+    (KeyCode::NumpadMemoryClear, "numpadmemoryclear"),
+    (KeyCode::NumpadMemoryRecall, "numpadmemoryrecall"),
+    (KeyCode::NumpadMemoryStore, "numpadmemorystore"),
+    (KeyCode::NumpadMemorySubtract, "numpadmemorysubtract"),
+    (KeyCode::NumpadMultiply, "numpadmultiply"),
+    (KeyCode::NumpadParenLeft, "numpadparenleft"),
+    (KeyCode::NumpadParenRight, "numpadparenright"),
+    (KeyCode::NumpadStar, "numpadstar"),
+    (KeyCode::NumpadSubtract, "numpadsubtract"),
+    (KeyCode::Escape, "escape"),
+    (KeyCode::Fn, "fn"),
+    (KeyCode::FnLock, "fnlock"),
+    (KeyCode::PrintScreen, "printscreen"),
+    (KeyCode::ScrollLock, "scrolllock"),
+    (KeyCode::Pause, "pause"),
+    (KeyCode::BrowserBack, "browserback"),
+    (KeyCode::BrowserFavorites, "browserfavorites"),
+    (KeyCode::BrowserForward, "browserforward"),
+    (KeyCode::BrowserHome, "browserhome"),
+    (KeyCode::BrowserRefresh, "browserrefresh"),
+    (KeyCode::BrowserSearch, "browsersearch"),
+    (KeyCode::BrowserStop, "browserstop"),
+    (KeyCode::Eject, "eject"),
+    (KeyCode::LaunchApp1, "launchapp1"),
+    (KeyCode::LaunchApp2, "launchapp2"),
+    (KeyCode::LaunchMail, "launchmail"),
+    (KeyCode::MediaPlayPause, "mediaplaypause"),
+    (KeyCode::MediaSelect, "mediaselect"),
+    (KeyCode::MediaStop, "mediastop"),
+    (KeyCode::MediaTrackNext, "mediatracknext"),
+    (KeyCode::MediaTrackPrevious, "mediatrackprevious"),
+    (KeyCode::Power, "power"),
+    (KeyCode::Sleep, "sleep"),
+    (KeyCode::AudioVolumeDown, "audiovolumedown"),
+    (KeyCode::AudioVolumeMute, "audiovolumemute"),
+    (KeyCode::AudioVolumeUp, "audiovolumeup"),
+    (KeyCode::WakeUp, "wakeup"),
+    (KeyCode::Meta, "meta"),
+    (KeyCode::Hyper, "hyper"),
+    (KeyCode::Turbo, "turbo"),
+    (KeyCode::Abort, "abort"),
+    (KeyCode::Resume, "resume"),
+    (KeyCode::Suspend, "suspend"),
+    (KeyCode::Again, "again"),
+    (KeyCode::Copy, "copy"),
+    (KeyCode::Cut, "cut"),
+    (KeyCode::Find, "find"),
+    (KeyCode::Open, "open"),
+    (KeyCode::Paste, "paste"),
+    (KeyCode::Props, "props"),
+    (KeyCode::Select, "select"),
+    (KeyCode::Undo, "undo"),
+    (KeyCode::Hiragana, "hiragana"),
+    (KeyCode::Katakana, "katakana"),
+    (KeyCode::F1, "f1"),
+    (KeyCode::F2, "f2"),
+    (KeyCode::F3, "f3"),
+    (KeyCode::F4, "f4"),
+    (KeyCode::F5, "f5"),
+    (KeyCode::F6, "f6"),
+    (KeyCode::F7, "f7"),
+    (KeyCode::F8, "f8"),
+    (KeyCode::F9, "f9"),
+    (KeyCode::F10, "f10"),
+    (KeyCode::F11, "f11"),
+    (KeyCode::F12, "f12"),
+    (KeyCode::F13, "f13"),
+    (KeyCode::F14, "f14"),
+    (KeyCode::F15, "f15"),
+    (KeyCode::F16, "f16"),
+    (KeyCode::F17, "f17"),
+    (KeyCode::F18, "f18"),
+    (KeyCode::F19, "f19"),
+    (KeyCode::F20, "f20"),
+    (KeyCode::F21, "f21"),
+    (KeyCode::F22, "f22"),
+    (KeyCode::F23, "f23"),
+    (KeyCode::F24, "f24"),
+    (KeyCode::F25, "f25"),
+    (KeyCode::F26, "f26"),
+    (KeyCode::F27, "f27"),
+    (KeyCode::F28, "f28"),
+    (KeyCode::F29, "f29"),
+    (KeyCode::F30, "f30"),
+    (KeyCode::F31, "f31"),
+    (KeyCode::F32, "f32"),
+    (KeyCode::F33, "f33"),
+    (KeyCode::F34, "f34"),
+    (KeyCode::F35, "f35"),
+];
+
+pub fn mousebutton_to_str(button: MouseButton) -> String {
     use winit::event::MouseButton::*;
-    Some(match button {
-        Left => "mouseleft",
-        Right => "mouseright",
-        Middle => "mousemiddle",
-        _ => return None,
-    })
+    match button {
+        Left => "mouseleft".to_string(),
+        Right => "mouseright".to_string(),
+        Middle => "mousemiddle".to_string(),
+        Back => "mouseback".to_string(),
+        Forward => "mouseforward".to_string(),
+        Other(id) => format!("mouse{}", id),
+    }
+}
+
+pub fn keycode_to_str(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(code, _)| *code == key)
+        .map(|(_, name)| *name)
+}
+
+pub fn str_to_keycode(name: &str) -> Option<KeyCode> {
+    let name = name.to_lowercase();
+    KEY_NAMES
+        .iter()
+        .find(|(_, key_name)| *key_name == name)
+        .map(|(code, _)| *code)
+}
+
+/// Whether `name` is something the engine can report: a key, mouse button, wheel direction or
+/// gamepad input.
+pub fn is_input_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let is_mouse = name.strip_prefix("mouse").is_some_and(|button| {
+        ["left", "right", "middle", "back", "forward"].contains(&button)
+            || button.parse::<u16>().is_ok()
+    });
+    let is_wheel = ["wheelup", "wheeldown", "wheelleft", "wheelright"].contains(&name.as_str());
+    str_to_keycode(&name).is_some() || is_mouse || is_wheel || is_gamepad_input(&name)
 }
 
-pub fn keycode_to_str(key: winit::keyboard::KeyCode) -> Option<&'static str> {
-    use winit::keyboard::KeyCode::*;
-    Some(match key {
-        KeyW => "w",
-        KeyA => "a",
-        KeyS => "s",
-        KeyD => "d",
-        ArrowUp => "up",
-        ArrowDown => "down",
-        ArrowLeft => "left",
-        ArrowRight => "right",
-        Space => "space",
-        Enter => "enter",
-        Escape => "escape",
-        KeyZ => "z",
-        KeyX => "x",
-        KeyC => "c",
-        KeyV => "v",
-        Digit0 => "0",
-        Digit1 => "1",
-        Digit2 => "2",
-        Digit3 => "3",
-        Digit4 => "4",
-        Digit5 => "5",
-        Digit6 => "6",
-        Digit7 => "7",
-        Digit8 => "8",
-        Digit9 => "9",
-        KeyQ => "q",
-        KeyE => "e",
-        KeyR => "r",
-        KeyF => "f",
-        KeyT => "t",
-        KeyY => "y",
-        KeyU => "u",
-        KeyI => "i",
-        KeyO => "o",
-        KeyP => "p",
-        KeyB => "b",
-        KeyN => "n",
-        KeyM => "m",
-        _ => return None, // Unknown or unhandled key
-    })
+/// Shift/ctrl/alt/super as a Lua table, passed along with every input event.
+pub fn modifiers_to_lua(lua: &mlua::Lua, modifiers: ModifiersState) -> mlua::Result<mlua::Table> {
+    let table = lua.create_table()?;
+    table.set("shift", modifiers.shift_key())?;
+    table.set("ctrl", modifiers.control_key())?;
+    table.set("alt", modifiers.alt_key())?;
+    table.set("super", modifiers.super_key())?;
+    Ok(table)
 }
@@ -70,6 +70,15 @@ impl LuaExtendedExecutor {
         return lua_func;
     }
 
+    /// For callbacks scripts may leave out.
+    pub fn get_optional_function(&self, method: &str) -> Option<mlua::Function> {
+        self.lua
+            .globals()
+            .get::<Option<mlua::Function>>(method)
+            .ok()
+            .flatten()
+    }
+
     pub fn rust_collisions_to_lua_2d(
         &self,
         collisions: Vec<CollisionPair>,
@@ -129,6 +138,19 @@ impl LuaExtendedExecutor {
         ]
     }
 
+    /// Reads `key = { r, g, b, a }`, missing channels default to 1.
+    pub fn table_to_color(table: &LuaTable, key: &str, default: [f32; 4]) -> [f32; 4] {
+        match table.get::<LuaTable>(key) {
+            Ok(color) => [
+                color.get("r").unwrap_or(1.0),
+                color.get("g").unwrap_or(1.0),
+                color.get("b").unwrap_or(1.0),
+                color.get("a").unwrap_or(1.0),
+            ],
+            Err(_) => default,
+        }
+    }
+
     #[allow(unused)]
     pub fn pretty_print_table(table: &LuaTable, indent: usize) -> Result<String, mlua::Error> {
         let mut output = String::new();
@@ -1,16 +1,22 @@
 #[macro_use]
 mod debug;
+mod audio;
 mod bitmaps;
 mod camera_2d;
 mod camera_3d;
 mod components_systems;
 mod engine;
+mod frame_capture;
+mod gamepad;
 mod graphics;
 mod graphics_2d;
 mod graphics_3d;
+mod input_map;
 mod inputs;
 mod lua_scriptor;
+mod palette;
 mod scene;
+mod text;
 mod texture;
 mod ui_canvas;
 mod world;
@@ -34,6 +40,14 @@ fn load_engine_config() -> EngineConfig {
         .get("camera_config")
         .unwrap_or(scriptor.lua.create_table().unwrap());
     let debug_enabled: bool = config_table.get("debug_enabled").unwrap_or(false);
+    let pixel_perfect = config_table
+        .get::<mlua::Table>("pixel_perfect")
+        .ok()
+        .map(|table| camera_2d::camera_2d::PixelPerfectConfig {
+            width: table.get::<u32>("width").unwrap_or(320).max(1),
+            height: table.get::<u32>("height").unwrap_or(180).max(1),
+            pixels_per_unit: table.get("pixels_per_unit").unwrap_or(16.0),
+        });
     return EngineConfig {
         fps,
         debug_enabled,
@@ -54,6 +68,7 @@ fn load_engine_config() -> EngineConfig {
             look_ahead_lerp_speed: camera2d_config.get("look_ahead_lerp_speed").unwrap_or(0.1),
             screen_width: width as f32,
             screen_height: height as f32,
+            pixel_perfect,
         },
     };
 }
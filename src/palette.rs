@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Texture id the atlas is uploaded under, the texture of the palette swap material.
+pub const PALETTE_ATLAS_TEXTURE: &str = "palettes";
+/// Built in material that recolors sprites through the atlas.
+pub const PALETTE_SWAP_MATERIAL: &str = "palette_swap";
+const MAX_PALETTE_COLORS: usize = 256;
+
+pub type PaletteRow = Vec<[u8; 4]>;
+
+/// Every row of a PNG strip, or the one row of a GIMP `.gpl` or a `.hex` list.
+pub fn load_palette_rows(path: &str) -> Result<Vec<PaletteRow>> {
+    let extension = path.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "gpl" => Ok(vec![parse_gpl(&std::fs::read_to_string(path)?)?]),
+        "hex" => Ok(vec![parse_hex(&std::fs::read_to_string(path)?)?]),
+        _ => {
+            let image = image::open(path)?.to_rgba8();
+            Ok(image
+                .rows()
+                .map(|row| row.map(|pixel| pixel.0).collect())
+                .collect())
+        }
+    }
+}
+
+/// ```text
+/// GIMP Palette
+/// Name: Bone
+/// #
+/// 255 255 255 White
+/// ```
+fn parse_gpl(source: &str) -> Result<PaletteRow> {
+    let mut lines = source.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err(anyhow!("missing 'GIMP Palette' header"));
+    }
+    let mut row = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.contains(':') {
+            continue;
+        }
+        let channels: Vec<u8> = line
+            .split_whitespace()
+            .take(3)
+            .map(|channel| channel.parse::<u8>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| anyhow!("bad color '{}': {}", line, e))?;
+        if channels.len() < 3 {
+            return Err(anyhow!("bad color '{}'", line));
+        }
+        row.push([channels[0], channels[1], channels[2], 255]);
+    }
+    Ok(row)
+}
+
+/// One `RRGGBB` per line, as exported by Lospec. A leading `#` is allowed.
+fn parse_hex(source: &str) -> Result<PaletteRow> {
+    source
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#'))
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let value = u32::from_str_radix(line, 16)
+                .ok()
+                .filter(|_| line.len() == 6)
+                .ok_or_else(|| anyhow!("bad color '{}'", line))?;
+            Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaletteEntry {
+    pub source_row: u32, // the sheet's own colors, variant 0
+    pub variants: u32,   // rows after the source, 1 to `variants` pick one
+    pub colors: u32,
+}
+
+impl PaletteEntry {
+    /// Atlas row of a variant, out of range ones draw the sheet's own colors.
+    pub fn row(&self, variant: u32) -> u32 {
+        if variant > self.variants {
+            self.source_row
+        } else {
+            self.source_row + variant
+        }
+    }
+}
+
+/// Every loaded palette stacked in one texture, so recolored sprites all share a material.
+/// Each palette's first row holds the colors of the sheets it recolors, the rows after it
+/// the replacements at the same columns.
+#[derive(Debug, Default)]
+pub struct PaletteAtlas {
+    rows: Vec<PaletteRow>,
+    palettes: HashMap<String, PaletteEntry>,
+}
+
+impl PaletteAtlas {
+    /// Loading a name again adds new rows, the old ones are left unused.
+    pub fn add(&mut self, name: &str, mut rows: Vec<PaletteRow>) -> Result<PaletteEntry> {
+        let Some(source) = rows.first_mut() else {
+            return Err(anyhow!("palette '{}' has no colors", name));
+        };
+        // strips are padded with transparent texels after the last color
+        while source.last().is_some_and(|color| color[3] == 0) {
+            source.pop();
+        }
+        source.truncate(MAX_PALETTE_COLORS);
+        let source = source.clone();
+        if source.is_empty() {
+            return Err(anyhow!("palette '{}' has no colors", name));
+        }
+        // empty texels and a short row's missing ones keep the source color
+        for row in rows.iter_mut().skip(1) {
+            row.resize(source.len(), [0; 4]);
+            for (color, original) in row.iter_mut().zip(source.iter()) {
+                if *color == [0; 4] {
+                    *color = *original;
+                }
+            }
+        }
+
+        let entry = PaletteEntry {
+            source_row: self.rows.len() as u32,
+            variants: rows.len() as u32 - 1,
+            colors: source.len() as u32,
+        };
+        self.rows.extend(rows);
+        self.palettes.insert(name.to_string(), entry);
+        Ok(entry)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PaletteEntry> {
+        self.palettes.get(name)
+    }
+
+    pub fn image(&self) -> DynamicImage {
+        let width = self.rows.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        let mut image = RgbaImage::new(width as u32, self.rows.len().max(1) as u32);
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                image.put_pixel(x as u32, y as u32, Rgba(*color));
+            }
+        }
+        DynamicImage::ImageRgba8(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const EMPTY: [u8; 4] = [0; 4];
+
+    #[test]
+    fn gpl_reads_colors_and_skips_metadata() {
+        let source = "GIMP Palette\nName: Bone\nColumns: 4\n#\n255   0   0 Red\n  0 255   0\n\n0 0 255\tBlue\n";
+        assert_eq!(parse_gpl(source).unwrap(), vec![RED, GREEN, BLUE]);
+    }
+
+    #[test]
+    fn gpl_rejects_a_missing_header_and_bad_colors() {
+        assert!(parse_gpl("255 0 0 Red\n").is_err());
+        assert!(parse_gpl("GIMP Palette\n255 0\n").is_err());
+        assert!(parse_gpl("GIMP Palette\n256 0 0\n").is_err());
+    }
+
+    #[test]
+    fn hex_reads_one_color_per_line() {
+        assert_eq!(
+            parse_hex("ff0000\n#00FF00\n\n  0000ff  \n").unwrap(),
+            vec![RED, GREEN, BLUE]
+        );
+        assert!(parse_hex("ff00\n").is_err());
+        assert!(parse_hex("gg0000\n").is_err());
+    }
+
+    #[test]
+    fn trailing_padding_is_trimmed_from_the_source_row() {
+        let mut atlas = PaletteAtlas::default();
+        let entry = atlas
+            .add("strip", vec![vec![RED, EMPTY, GREEN, EMPTY, EMPTY]])
+            .unwrap();
+        // only padding after the last color goes, gaps inside the row are kept
+        assert_eq!(entry.colors, 3);
+        assert_eq!(entry.variants, 0);
+        assert!(atlas.add("padding", vec![vec![EMPTY, EMPTY]]).is_err());
+        assert!(atlas.add("nothing", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn short_and_empty_variant_texels_keep_the_source_color() {
+        let mut atlas = PaletteAtlas::default();
+        atlas
+            .add(
+                "hero",
+                vec![
+                    vec![RED, GREEN, BLUE],
+                    vec![BLUE, EMPTY],
+                    vec![GREEN, RED, RED, RED],
+                ],
+            )
+            .unwrap();
+        let image = atlas.image().to_rgba8();
+        let row = |y: u32| (0..3).map(|x| image.get_pixel(x, y).0).collect::<Vec<_>>();
+        assert_eq!(row(1), vec![BLUE, GREEN, BLUE]);
+        // longer rows are cut to the source's colors
+        assert_eq!(image.width(), 3);
+        assert_eq!(row(2), vec![GREEN, RED, RED]);
+    }
+
+    #[test]
+    fn palettes_stack_in_rows_and_variants_pick_them() {
+        let mut atlas = PaletteAtlas::default();
+        atlas.add("a", vec![vec![RED], vec![GREEN]]).unwrap();
+        let b = atlas
+            .add("b", vec![vec![BLUE], vec![RED], vec![GREEN]])
+            .unwrap();
+        assert_eq!((b.source_row, b.variants, b.colors), (2, 2, 1));
+        assert_eq!(b.row(0), 2);
+        assert_eq!(b.row(2), 4);
+        // out of range variants draw the sheet's own colors
+        assert_eq!(b.row(3), 2);
+        assert_eq!(atlas.get("a").unwrap().row(1), 1);
+        assert_eq!(atlas.image().height(), 5);
+
+        // reloading a name adds rows after the others
+        let a = atlas.add("a", vec![vec![RED]]).unwrap();
+        assert_eq!(a.source_row, 5);
+        assert_eq!(atlas.get("a").unwrap().source_row, 5);
+    }
+}
@@ -10,7 +10,11 @@ local function AnimationBuilder()
 		frames = {},
 		hitboxes = {},
 		hurtboxes = {},
+		-- { [frame] = { "footstep", ... } }, sent to ENGINE_animation_events when the frame starts
+		events = {},
 		looped = true,
+		-- "forward", "reverse", "pingpong" or "pingpong_reverse"
+		direction = "forward",
 		is_transparent = false,
 	}
 
@@ -32,6 +36,11 @@ local function AnimationBuilder()
 		return builder
 	end
 
+	function builder:direction(d)
+		anim.direction = d
+		return builder
+	end
+
 	function builder:add_frame(f)
 		anim.frames[#anim.frames + 1] = f
 		return builder
@@ -54,6 +63,14 @@ local function AnimationBuilder()
 		}
 	end
 
+	function builder:add_event(f, name)
+		if not anim.events[f] then
+			anim.events[f] = {}
+		end
+		table.insert(anim.events[f], name)
+		return builder
+	end
+
 	function builder:transparency(b)
 		anim.is_transparent = b
 		return builder
@@ -66,7 +83,9 @@ local function AnimationBuilder()
 	return builder
 end
 
-local function load_aseprite_animation(animation_name, path, json_file, with_transparency)
+-- `overrides` is optional: { looped = false, direction = "pingpong", events = { [frame] = { "name", ... } } }
+local function load_aseprite_animation(animation_name, path, json_file, with_transparency, overrides)
+	overrides = overrides or {}
 	local json_path = "src/assets/" .. path .. json_file
 	local file, io_err = io.open(json_path, "r")
 	if not file then
@@ -88,11 +107,14 @@ local function load_aseprite_animation(animation_name, path, json_file, with_tra
 	end
 
 	local looped = true
+	local direction = "forward"
 	if data.meta.frameTags and #data.meta.frameTags > 0 then
 		for _, tag in ipairs(data.meta.frameTags) do
 			if tag.name == animation_name then
-				-- Aseprite directions: "forward", "reverse", "pingpong"
-				looped = (tag.direction == "forward")
+				-- Aseprite directions: "forward", "reverse", "pingpong", "pingpong_reverse"
+				direction = tag.direction or direction
+				-- tags only carry "repeat" when they play a fixed number of times
+				looped = tag["repeat"] == nil or tag["repeat"] == "0"
 				break
 			end
 		end
@@ -102,7 +124,8 @@ local function load_aseprite_animation(animation_name, path, json_file, with_tra
 	builder
 			:set_sprite(path .. data.meta.image)
 			:set_layout(data.meta.size.w // fw, data.meta.size.h // fh)
-			:loop(looped)
+			:loop(overrides.looped == nil and looped or overrides.looped)
+			:direction(overrides.direction or direction)
 			:transparency(with_transparency == true)
 
 	for _, fr in ipairs(frames) do
@@ -131,6 +154,21 @@ local function load_aseprite_animation(animation_name, path, json_file, with_tra
 		end
 	end
 
+	-- cel user data becomes an event on that cel's frame
+	for _, layer in ipairs(data.meta.layers or {}) do
+		for _, cel in ipairs(layer.cels or {}) do
+			if cel.data and cel.data ~= "" then
+				builder:add_event(cel.frame + 1, cel.data)
+			end
+		end
+	end
+
+	for f, names in pairs(overrides.events or {}) do
+		for _, name in ipairs(names) do
+			builder:add_event(f, name)
+		end
+	end
+
 	return builder:build()
 end
 
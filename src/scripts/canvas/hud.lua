@@ -0,0 +1,22 @@
+local function hud(player_id)
+	local health_bar = CanvasSceneBuilder()
+			:nine_slice(GLOBALS.UI.Panel)
+			:progress_bar(1)
+			:bar_colors({ r = 0.8, g = 0.15, b = 0.2, a = 1 }, { r = 0.15, g = 0.15, b = 0.18, a = 0.8 })
+			:health_of(player_id)
+			:size(220, 20)
+			:build()
+
+	local hud_layout = CanvasLayout("column", {
+		anchor = "top_right",
+		width = "auto",
+		height = "auto",
+		padding = 16,
+		align_items = "end",
+	})
+	hud_layout.elements = { health_bar }
+
+	return hud_layout
+end
+
+return hud
@@ -1,11 +1,41 @@
 local load_aseprite_animation = require("aseprite_parser")
 
-local function main_menu(w, h)
-	return CanvasSceneBuilder()
+local function main_menu(w, h, on_start)
+	local background = CanvasSceneBuilder()
 			:add_animation(GLOBALS.ACTIONS.Idle, load_aseprite_animation("canvas/", "canvas/", "main_menu.json"))
-			:size(w, h)
-			:position(0, 0)
+			:size("100%", "100%")
 			:build()
+
+	local start_button = CanvasSceneBuilder()
+			:nine_slice(GLOBALS.UI.Panel, 2)
+			:button("Start", "hud", on_start)
+			:button_colors(
+				{ r = 0.55, g = 0.55, b = 0.6, a = 1 },
+				{ r = 0.95, g = 0.8, b = 0.35, a = 1 },
+				{ r = 0.7, g = 0.55, b = 0.2, a = 1 }
+			)
+			:size(200, 48)
+			:build()
+	start_button.z = 1
+	start_button.color = { r = 0.1, g = 0.1, b = 0.12, a = 1 }
+
+	local buttons = CanvasLayout("column", {
+		anchor = "bottom",
+		width = "100%",
+		height = "auto",
+		align_items = "center",
+		padding = { bottom = 36 },
+		gap = 12,
+	})
+	buttons.elements = { start_button }
+
+	-- the menu keeps its size and stays centered when the window is resized
+	local menu = CanvasLayout(nil, { width = w, height = h, anchor = "center" })
+	menu.elements = { background }
+	menu.scenes = { buttons }
+	menu.start_button = start_button
+
+	return menu
 end
 
 return main_menu
@@ -4,15 +4,31 @@ require("globals")
 
 local is_transparent = true
 local idle = load_aseprite_animation("death_idle", "death/", "death_idle.json", is_transparent)
-local running = load_aseprite_animation("death_running", "death/", "death_running.json", is_transparent)
-local dying = load_aseprite_animation("death_dying", "death/", "death_dying.json", is_transparent)
+local running = load_aseprite_animation("death_running", "death/", "death_running.json", is_transparent, {
+	events = { [1] = { "footstep" }, [3] = { "footstep" } },
+})
+local dying = load_aseprite_animation("death_dying", "death/", "death_dying.json", is_transparent, {
+	looped = false,
+})
 local dashing = load_aseprite_animation("death_blinking", "death/", "death_blinking.json", is_transparent)
 
+-- params: moving, dashing (booleans). triggers: dash, die
+local graph = AnimationGraphBuilder()
+		:state(GLOBALS.ACTIONS.Dashing, { speed = 1.5, interrupt_priority = 10 })
+		:state(GLOBALS.ACTIONS.Dying, { interrupt_priority = 100 })
+		:transition(GLOBALS.ACTIONS.Idle, GLOBALS.ACTIONS.Running, { { "moving", "==", true } })
+		:transition(GLOBALS.ACTIONS.Running, GLOBALS.ACTIONS.Idle, { { "moving", "==", false } })
+		:any_transition(GLOBALS.ACTIONS.Dashing, { "dash" }, { priority = 10 })
+		:transition(GLOBALS.ACTIONS.Dashing, GLOBALS.ACTIONS.Idle, { { "dashing", "==", false } }, { priority = 10 })
+		:any_transition(GLOBALS.ACTIONS.Dying, { "die" }, { priority = 100 })
+		:build()
+
 local function summon_death(x, y)
 	return PhysicsBodyBuilder()
 			:position(x, y)
 			:size(2, 2)
 			:collider_size_modifier(0.6, 0.8)
+			:sort_offset(-0.8)
 			:add_layer(GLOBALS.MASKS_AND_LAYERS.Player)
 			:add_mask(GLOBALS.MASKS_AND_LAYERS.Env)
 			:add_mask(GLOBALS.MASKS_AND_LAYERS.Enemy)
@@ -20,6 +36,7 @@ local function summon_death(x, y)
 			:add_animation(GLOBALS.ACTIONS.Dashing, dashing)
 			:add_animation(GLOBALS.ACTIONS.Running, running)
 			:add_animation(GLOBALS.ACTIONS.Dying, dying)
+			:animation_graph(graph)
 			:build()
 end
 
@@ -0,0 +1,30 @@
+local load_lpc_animation = require("lpc_parser")
+require("game_asset_builders")
+require("globals")
+
+-- the walkcycle's first column is the standing pose, skipped since skellies are always chasing
+local walking = load_lpc_animation("walkcycle/BODY_skeleton.png", { frames = { 2, 9 }, frame_time = 0.08 })
+local slashing = load_lpc_animation("slash/BODY_skeleton.png", { frame_time = 0.08 })
+-- the hurt sheet only faces the camera
+local dying = load_lpc_animation("hurt/BODY_skeleton.png", { rows = { "s" }, looped = false })
+
+local function new_lpc_skeleton(x, y)
+	return PhysicsBodyBuilder()
+			:position(x, y)
+			:size(4, 4)
+			:add_layer(GLOBALS.MASKS_AND_LAYERS.Enemy)
+			:add_mask(GLOBALS.MASKS_AND_LAYERS.Env)
+			:add_mask(GLOBALS.MASKS_AND_LAYERS.Enemy)
+			:add_mask(GLOBALS.MASKS_AND_LAYERS.Player)
+			:collider_size_modifier(0.25, 0.4)
+			:collider_offset(0, -0.6)
+			:sort_offset(-1.6)
+			-- shares the skelly AI, which uses Idle while pursuing
+			:add_directional_animation(GLOBALS.ACTIONS.Idle, walking)
+			:add_directional_animation(GLOBALS.ACTIONS.Dashing, slashing)
+			:add_directional_animation(GLOBALS.ACTIONS.Dying, dying)
+			:facing(4, true)
+			:build()
+end
+
+return { new = new_lpc_skeleton }
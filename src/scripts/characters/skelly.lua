@@ -6,15 +6,29 @@ local is_transparent = true
 local idle = load_aseprite_animation("skelly_idle", "skelly/", "skelly_idle.json", is_transparent)
 local dashing = load_aseprite_animation("skelly_lunging", "skelly/", "skelly_leaping.json", is_transparent)
 
+-- variants after the sheets' own colors, set by setup
+local palette_variants = 0
+
+-- one row per recolor, the first holds the colors of both skelly sheets
+local function setup()
+	palette_variants = engine.load_palette("skelly", {
+		"palettes/skelly.png",
+		"palettes/skelly_gilded.gpl",
+		"palettes/skelly_toxic.hex",
+	})
+end
+
 local function new_skelly(x, y)
 	return PhysicsBodyBuilder()
 			:position(x, y)
+			:palette("skelly", math.random(0, palette_variants))
 			:size(4, 4)
 			:add_layer(GLOBALS.MASKS_AND_LAYERS.Enemy)
 			:add_mask(GLOBALS.MASKS_AND_LAYERS.Env)
 			:add_mask(GLOBALS.MASKS_AND_LAYERS.Enemy)
 			:add_mask(GLOBALS.MASKS_AND_LAYERS.Player)
 			:collider_size_modifier(0.3, 0.6)
+			:sort_offset(-1.2)
 			:add_animation(GLOBALS.ACTIONS.Idle, idle)
 			:add_animation(GLOBALS.ACTIONS.Dashing, dashing)
 			:build()
@@ -35,7 +49,10 @@ local function move_skellies(dt)
 		local dx = px - ex
 		local dy = py - ey
 
-		ENGINE_HANDLES.flip_x(key, dx)
+		-- directional sprites face their velocity instead of mirroring
+		if not value.facing then
+			ENGINE_HANDLES.flip_x(key, dx)
+		end
 
 		-- Length (magnitude) of the direction vector
 		local dist = math.sqrt(dx * dx + dy * dy)
@@ -91,4 +108,4 @@ local function move_skellies(dt)
 	end
 end
 
-return { new = new_skelly, move = move_skellies }
+return { setup = setup, new = new_skelly, move = move_skellies }
@@ -0,0 +1,32 @@
+-- Light configs for engine.create_light, see Light2D::from_lua_table for every field.
+local LIGHTS = {}
+
+-- dim blue dark the arena sits in, everything brighter comes from lights
+LIGHTS.dungeon_ambient = { 0.22, 0.22, 0.32 }
+
+-- warm light carried by an entity
+function LIGHTS.torch(entity)
+	return {
+		follow = entity,
+		radius = 9,
+		falloff = 1.5,
+		color = { 1.0, 0.78, 0.5 },
+		intensity = 1.2,
+		height = 1.5,
+	}
+end
+
+-- fixed fire in the level, bright enough to throw long shadows
+function LIGHTS.brazier(x, y)
+	return {
+		x = x,
+		y = y,
+		radius = 14,
+		falloff = 2,
+		color = { 1.0, 0.55, 0.25 },
+		intensity = 1.4,
+		height = 2,
+	}
+end
+
+return LIGHTS
@@ -0,0 +1,43 @@
+-- Sprite materials, see Material::from_lua_table for the options of engine.create_material.
+local MATERIALS = {}
+
+-- entity -> { time, duration } of every running dissolve
+local dissolving = {}
+
+function MATERIALS.setup()
+	engine.create_material("dissolve", "materials/dissolve.wgsl", {
+		params = { "amount", "edge_color", "edge_width" },
+		edge_color = { 1.0, 0.55, 0.2, 1.0 },
+		edge_width = 0.08,
+	})
+	engine.create_material("outline", "materials/outline.wgsl", {
+		params = { "color", "width" },
+		color = { 0.95, 0.25, 0.3, 1.0 },
+		width = 1,
+	})
+end
+
+-- marks the tougher enemies
+function MATERIALS.outline(entity)
+	engine.set_material(entity, "outline")
+end
+
+-- burns the sprite away over `duration` seconds
+function MATERIALS.dissolve(entity, duration)
+	engine.set_material(entity, "dissolve")
+	engine.set_entity_material_param(entity, "amount", 0)
+	dissolving[entity] = { time = 0, duration = duration }
+end
+
+function MATERIALS.update(dt)
+	for entity, burn in pairs(dissolving) do
+		burn.time = burn.time + dt
+		local amount = math.min(burn.time / burn.duration, 1)
+		engine.set_entity_material_param(entity, "amount", amount)
+		if amount >= 1 then
+			dissolving[entity] = nil
+		end
+	end
+end
+
+return MATERIALS
@@ -0,0 +1,54 @@
+require("globals")
+
+-- Emitter configs for engine.create_particle_emitter, see ParticleEmitterConfig for every field.
+local PARTICLES = {}
+
+-- puff left behind where a dash starts, sits under the actors
+function PARTICLES.dash_dust(x, y, dx, dy)
+	return {
+		x = x,
+		y = y - 0.6,
+		layer = GLOBALS.RENDER_LAYERS.Ground,
+		burst = 14,
+		one_shot = true,
+		duration = 0,
+		lifetime = { 0.25, 0.5 },
+		speed = { 1, 4 },
+		-- away from the dash direction
+		direction = math.deg(math.atan(-dy, -dx)),
+		spread = 50,
+		spawn_radius = 0.3,
+		drag = 4,
+		sizes = { 0.5, 0.9 },
+		colors = {
+			{ r = 0.85, g = 0.78, b = 0.66, a = 0.7 },
+			{ r = 0.85, g = 0.78, b = 0.66, a = 0 },
+		},
+	}
+end
+
+-- burst of shrinking sparks when the player gets hit
+function PARTICLES.hit_sparks(x, y)
+	return {
+		x = x,
+		y = y,
+		texture = "particles/spark.png",
+		frames = { columns = 4, rows = 1, count = 4 },
+		burst = 18,
+		one_shot = true,
+		duration = 0,
+		lifetime = { 0.2, 0.45 },
+		speed = { 4, 9 },
+		spread = 180,
+		gravity = { 0, -12 },
+		drag = 2,
+		spin = { -360, 360 },
+		sizes = { 0.35, 0.2 },
+		colors = {
+			{ r = 1, g = 0.25, b = 0.2, a = 1 },
+			{ r = 0.6, g = 0.05, b = 0.05, a = 0.8 },
+		},
+	}
+end
+
+return PARTICLES
@@ -0,0 +1,33 @@
+-- Post effect chain of the arena, see PostEffect::apply_lua_table for every option.
+local POST = {}
+
+local VIGNETTE_CALM = { 0, 0, 0 }
+local VIGNETTE_HURT = { 0.45, 0, 0 }
+
+-- effects run in the order they are first set
+function POST.setup()
+	engine.set_post_effect("color_grade", { lut = "post/dungeon_lut.png", strength = 0.7 })
+	engine.set_post_effect("bloom", { threshold = 0.75, radius = 8, strength = 0.6 })
+	if engine.load_post_effect("desaturate", "post/desaturate.wgsl") then
+		engine.set_post_effect("desaturate", { strength = 0, params = { 1.5 } })
+	end
+	engine.set_post_effect("vignette", { color = VIGNETTE_CALM, strength = 0.35 })
+	engine.set_post_effect("flash", { strength = 0 })
+end
+
+-- edges close in red and the colors drain out below half health
+function POST.update_health(fraction)
+	local hurt = math.max(0, math.min(1, (0.5 - fraction) * 2))
+	local color = {}
+	for i = 1, 3 do
+		color[i] = VIGNETTE_CALM[i] + (VIGNETTE_HURT[i] - VIGNETTE_CALM[i]) * hurt
+	end
+	engine.set_post_effect("vignette", { color = color, strength = 0.35 + 0.45 * hurt, radius = 0.75 - 0.25 * hurt })
+	engine.set_post_effect("desaturate", { strength = 0.6 * hurt })
+end
+
+function POST.hit()
+	engine.flash_screen(1, 0.15, 0.1, 0.45, 0.2)
+end
+
+return POST
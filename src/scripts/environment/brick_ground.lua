@@ -4,6 +4,7 @@ local function new_brick_tile(x, y)
 	return PhysicsBodyBuilder()
 			:add_animation(GLOBALS.ACTIONS.Idle, load_aseprite_animation("bricks_1", "/", "bricks.json"))
 			:size(2, 2)
+			:layer(GLOBALS.RENDER_LAYERS.Ground)
 			:position(x, y)
 			:build()
 end
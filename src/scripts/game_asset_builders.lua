@@ -43,42 +43,6 @@ function CameraBuilder()
 	return builder
 end
 
-function ControllerBuilder()
-	local config = {
-		keys = {},
-		state = {},
-	}
-
-	function config:update(key, is_pressed, mouse_loc)
-		local action = self.keys[key]
-		if action then
-			self.state[action] = { is_pressed = is_pressed, mouse_loc = { x = mouse_loc[1], y = mouse_loc[2] } }
-		end
-	end
-
-	function config:get_state(action)
-		return self.state[action]
-	end
-
-	function config:is_pressed(action)
-		return self.state[action] and self.state[action].is_pressed
-	end
-
-	local builder = {}
-
-	function builder:key(key_str, action_str)
-		config.keys[key_str] = action_str
-		config.state[action_str] = false -- initialize
-		return builder
-	end
-
-	function builder:build()
-		return config
-	end
-
-	return builder
-end
-
 function PhysicsBodyBuilder()
 	local body = {
 		type = GLOBALS.PHYSICS_BODIES.Rigid,
@@ -93,6 +57,9 @@ function PhysicsBodyBuilder()
 		},
 		height = 1,
 		width = 1,
+		layer = GLOBALS.RENDER_LAYERS.Actors,
+		z = 0,
+		sort_offset = 0,
 		health = 0,
 		state = "Idle",
 		base_speed = 20,
@@ -123,18 +90,69 @@ function PhysicsBodyBuilder()
 		return builder
 	end
 
+	-- `set` maps facings to animations, { n = ..., e = ..., s = ..., w = ... } or all 8 ("ne", ...)
+	function builder:add_directional_animation(action, set)
+		body.directional_animations = body.directional_animations or {}
+		body.directional_animations[action] = set
+		return builder
+	end
+
+	-- directions is 4 or 8, from_velocity turns the body toward where it's moving
+	function builder:facing(directions, from_velocity, initial)
+		body.facing = {
+			directions = directions or 8,
+			from_velocity = from_velocity ~= false,
+			initial = initial or "s",
+		}
+		return builder
+	end
+
 	function builder:size(width, height)
 		body.width = width
 		body.height = height
 		return builder
 	end
 
+	-- number for every side or { left, top, right, bottom }
+	function builder:margin(m)
+		body.margin = m
+		return builder
+	end
+
+	function builder:padding(p)
+		body.padding = p
+		return builder
+	end
+
+	-- share of the free space when the parent scene is a row or column
+	function builder:grow(g)
+		body.grow = g
+		return builder
+	end
+
 	function builder:position(x, y)
 		body.x = x
 		body.y = y
 		return builder
 	end
 
+	function builder:layer(layer)
+		body.layer = layer
+		return builder
+	end
+
+	-- draw order within layers using SORT_MODES.ExplicitZ
+	function builder:z(z)
+		body.z = z
+		return builder
+	end
+
+	-- y offset from the body center to the point used for y-sorting (usually the feet)
+	function builder:sort_offset(y)
+		body.sort_offset = y
+		return builder
+	end
+
 	function builder:collider_offset(x, y)
 		body.collision_box.offset_x = x
 		body.collision_box.offset_y = y
@@ -152,6 +170,18 @@ function PhysicsBodyBuilder()
 		return builder
 	end
 
+	-- recolors the sprite with a variant of a palette from engine.load_palette, 0 keeps its colors
+	function builder:palette(name, variant)
+		body.palette = { name = name, variant = variant or 0 }
+		return builder
+	end
+
+	-- state machine that picks the animation from params and triggers, see AnimationGraphBuilder
+	function builder:animation_graph(graph)
+		body.animation_graph = graph
+		return builder
+	end
+
 	function builder:build()
 		return body
 	end
@@ -159,6 +189,43 @@ function PhysicsBodyBuilder()
 	return builder
 end
 
+function AnimationGraphBuilder()
+	local graph = {
+		states = {},
+		transitions = {},
+	}
+
+	local builder = {}
+
+	-- opts = { speed = 1, then_state = nil, interrupt_priority = 0 }
+	-- then_state plays the clip once and moves on when it finishes (needs a non looped clip)
+	function builder:state(state, opts)
+		graph.states[state] = opts or {}
+		return builder
+	end
+
+	-- when = list of trigger names or { param, op, value }, op is one of > >= < <= == ~=
+	-- opts = { priority = 0, wait_for_finish = false, keep_frame = false }
+	function builder:transition(from, to, when, opts)
+		local transition = opts or {}
+		transition.from = from
+		transition.to = to
+		transition.when = when or {}
+		table.insert(graph.transitions, transition)
+		return builder
+	end
+
+	function builder:any_transition(to, when, opts)
+		return builder:transition(nil, to, when, opts)
+	end
+
+	function builder:build()
+		return graph
+	end
+
+	return builder
+end
+
 function MaskAndLayerBuilder()
 	local result = {
 		masks = {},
@@ -184,18 +251,48 @@ function MaskAndLayerBuilder()
 	return builder
 end
 
+-- scene tables can lay out their children:
+--   direction = "row" | "column" (default stacks children on their anchors)
+--   justify = "start" | "center" | "end" | "space_between"
+--   align_items = "start" | "center" | "end" | "stretch"
+--   gap, padding, margin, width, height, anchor, position_x, position_y
+function CanvasLayout(direction, options)
+	local scene = {
+		direction = direction,
+		initially_active = true,
+		elements = {},
+		scenes = {},
+	}
+	for key, value in pairs(options or {}) do
+		scene[key] = value
+	end
+	return scene
+end
+
 function CanvasSceneBuilder()
+	-- canvas sizes and positions are in screen pixels, positions offset from the anchor (y down).
+	-- sizes can also be percentages of the parent ("50%") and default to the element's content.
 	local body = {
-		x = -1000,
-		y = -1000,
-		height = 1,
-		width = 1,
+		position_x = 0,
+		position_y = 0,
+		anchor = "center",
 		state = "Idle",
+		initially_active = true,
 		animations = {},
 	}
 
 	local builder = {}
 
+	function builder:anchor(a)
+		body.anchor = a
+		return builder
+	end
+
+	function builder:active(b)
+		body.initially_active = b
+		return builder
+	end
+
 	function builder:add_animation(action, animation)
 		body.animations[action] = animation
 		return builder
@@ -208,8 +305,79 @@ function CanvasSceneBuilder()
 	end
 
 	function builder:position(x, y)
-		body.x = x
-		body.y = y
+		body.position_x = x
+		body.position_y = y
+		return builder
+	end
+
+	-- turns the element into a text element, its size comes from the laid out text
+	function builder:text(text, font)
+		body.text = text
+		body.font = font
+		return builder
+	end
+
+	function builder:color(r, g, b, a)
+		body.color = { r = r, g = g, b = b, a = a or 1 }
+		return builder
+	end
+
+	function builder:align(a)
+		body.align = a
+		return builder
+	end
+
+	function builder:max_width(w)
+		body.max_width = w
+		return builder
+	end
+
+	-- widgets draw a nine-slice texture, see GLOBALS.UI
+	function builder:nine_slice(slice, border_scale)
+		body.texture = slice.texture
+		body.texture_width = slice.width
+		body.texture_height = slice.height
+		body.border = slice.border
+		body.border_scale = border_scale or 1
+		return builder
+	end
+
+	function builder:panel()
+		body.widget = "panel"
+		return builder
+	end
+
+	-- on_click receives the button's canvas id
+	function builder:button(label, font, on_click)
+		body.widget = "button"
+		body.label = label
+		body.font = font
+		body.on_click = on_click
+		return builder
+	end
+
+	function builder:button_colors(normal, focused, pressed)
+		body.normal_color = normal
+		body.focused_color = focused
+		body.pressed_color = pressed
+		return builder
+	end
+
+	function builder:progress_bar(value)
+		body.widget = "progress_bar"
+		body.value = value
+		return builder
+	end
+
+	function builder:bar_colors(fill, background)
+		body.fill_color = fill
+		body.background_color = background
+		return builder
+	end
+
+	-- keeps a progress bar in sync with an entity's health
+	function builder:health_of(entity_id)
+		body.health_of = entity_id
 		return builder
 	end
 
@@ -12,10 +12,30 @@ GLOBALS = {
 		Dying = 2,
 		Dashing = 3,
 	},
+	RENDER_LAYERS = {
+		Background = 0,
+		Ground = 1,
+		Actors = 2,
+		Overhead = 3,
+		Effects = 4,
+	},
+	SORT_MODES = {
+		YSort = 0,
+		ExplicitZ = 1,
+		None = 2,
+	},
 	MASKS_AND_LAYERS = {
 		Enemy = 0,
 		Env = 1,
 		Player = 2,
 		Boundary = 3,
 	},
+	-- nine-slice textures for canvas widgets
+	UI = {
+		Panel = { texture = "ui/panel.png", width = 16, height = 16, border = 4 },
+	},
+	SOUNDS = {
+		Dash = "sounds/dash.wav",
+		Footstep = "sounds/footstep.wav",
+	},
 }
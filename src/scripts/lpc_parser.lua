@@ -0,0 +1,68 @@
+-- Liberated Pixel Cup sheets: 64x64 frames, one row per facing in the order up, left, down, right.
+local FRAME_SIZE = 64
+local LPC_ROWS = { "n", "w", "s", "e" }
+
+-- width and height from the PNG header, so a sheet's grid never has to be typed in by hand
+local function png_size(path)
+	local file, err = io.open(path, "rb")
+	if not file then
+		return nil, nil, err
+	end
+	local header = file:read(24)
+	file:close()
+	if not header or #header < 24 then
+		return nil, nil, ("“%s” is not a png"):format(path)
+	end
+	local w, h = string.unpack(">I4I4", header, 17)
+	return w, h
+end
+
+-- `sheet` is relative to src/assets/lpc_entry/png, e.g. "walkcycle/BODY_skeleton.png".
+-- `opts` is optional:
+--   frames = { first, last }  columns to use, counted from 1 (default every column)
+--   frame_time = 0.1          seconds per frame
+--   rows = { "s" }            facings of the rows, top to bottom, for sheets that aren't 4 rows
+--   looped, direction, events like the Aseprite loader
+-- Returns { n = animation, w = animation, s = animation, e = animation }.
+local function load_lpc_animation(sheet, opts)
+	opts = opts or {}
+	local sprite = "lpc_entry/png/" .. sheet
+	local w, h, err = png_size("src/assets/" .. sprite)
+	if not w then
+		return nil, err
+	end
+
+	local columns = w // FRAME_SIZE
+	local rows = opts.rows or LPC_ROWS
+	local first = opts.frames and opts.frames[1] or 1
+	local last = opts.frames and opts.frames[2] or columns
+
+	local set = {}
+	for row, facing in ipairs(rows) do
+		local frames = {}
+		for column = first, last do
+			frames[#frames + 1] = {
+				x = column - 1,
+				y = row - 1,
+				duration = opts.frame_time or 0.1,
+			}
+		end
+		set[facing] = {
+			sprite = sprite,
+			tile_width = FRAME_SIZE,
+			tile_height = FRAME_SIZE,
+			sprite_sheet_width = columns,
+			sprite_sheet_height = h // FRAME_SIZE,
+			frames = frames,
+			hitboxes = {},
+			hurtboxes = {},
+			events = opts.events or {},
+			looped = opts.looped ~= false,
+			direction = opts.direction or "forward",
+			is_transparent = true,
+		}
+	end
+	return set
+end
+
+return load_lpc_animation
@@ -4,16 +4,26 @@ PRETTY_PRINT = require("pretty_print")
 local game_math = require("game_math")
 local collisions = require("systems.collisions")
 local physics = require("systems.physics")
+local input_bindings = require("systems.input_bindings")
+local capture = require("systems.capture")
+local camera = require("systems.camera")
+local split_screen = require("systems.split_screen")
+local particles = require("effects.particles")
+local lights = require("effects.lights")
+local post = require("effects.post")
+local materials = require("effects.materials")
 require("game_asset_builders")
 
 -- Game Elements
 local summon_death = require("characters.death")
 local skelly = require("characters.skelly")
+local lpc_skeleton = require("characters.lpc_skeleton")
 local new_fence = require("environment.fence")
 local new_brick_tile = require("environment.brick_ground")
 
 -- Canvas Elements
 local main_menu = require("canvas.main_menu")
+local hud = require("canvas.hud")
 
 math.randomseed(os.time())
 
@@ -24,13 +34,6 @@ CONFIG = {
 	speed = 15.0,
 	player_id = -1,
 	entities = {},
-	controller = ControllerBuilder()
-			:key("SPACE", "Dash")
-			:key("W", "Up")
-			:key("S", "Down")
-			:key("A", "MoveLeft")
-			:key("D", "MoveRight")
-			:build(),
 }
 
 WORLD = {
@@ -43,6 +46,8 @@ WORLD = {
 	-- this should affect the layers/masks for collision somehow as well
 	-- maybe ignoring everything but environment?
 	targetability = {},
+	-- seconds left on a freeze-frame, animations stand still until it runs out
+	hit_stop = 0,
 	kills = 0,
 	time = 0,
 }
@@ -171,7 +176,24 @@ ENGINE_HANDLES = {
 		for _, id in ipairs(to_clear) do
 			WORLD.targetability[id] = nil
 		end
-	end
+	end,
+
+	hit_stop = function(seconds)
+		if WORLD.hit_stop < seconds then
+			WORLD.hit_stop = seconds
+			engine.set_animation_time_scale(0)
+		end
+	end,
+
+	tick_hit_stop = function(dt)
+		if WORLD.hit_stop > 0 then
+			WORLD.hit_stop = WORLD.hit_stop - dt
+			if WORLD.hit_stop <= 0 then
+				WORLD.hit_stop = 0
+				engine.set_animation_time_scale(1)
+			end
+		end
+	end,
 }
 
 
@@ -181,10 +203,27 @@ ENGINE_HANDLES = {
 
 -- Called once per frame, after all physics substeps have run
 function ENGINE_after_physics(dt)
+	-- HUD, text is immediate mode so it has to be queued every frame
+	local hud_text = { font = "hud", color = { r = 1, g = 1, b = 1, a = 0.9 } }
+	engine.draw_text("FPS: " .. engine.get_fps(), 12, 12, hud_text)
+	engine.draw_text("Kills: " .. WORLD.kills, 12, 36, hud_text)
 end
 
-function ENGINE_input_event(input, is_pressed, mouse_position)
-	CONFIG.controller:update(string.upper(input), is_pressed, mouse_position, engine.now_ns())
+-- every animation event of the frame, in order: { entity, state, event, frame }
+function ENGINE_animation_events(events)
+	for _, e in ipairs(events) do
+		if e.event == "footstep" then
+			local position = engine.get_position_2d(e.entity)
+			engine.play_sound(GLOBALS.SOUNDS.Footstep, { x = position[1], y = position[2], volume = 0.4 })
+		elseif e.event == "finished" and e.state == GLOBALS.ACTIONS.Dying and e.entity == WORLD.player_id() then
+			-- the body dissolves once the death clip has played out
+			materials.dissolve(e.entity, 1.5)
+		end
+	end
+end
+
+function ENGINE_gamepad_connected(id, name)
+	print("Gamepad " .. id .. " connected: " .. name)
 end
 
 function ENGINE_on_collision(cols)
@@ -212,10 +251,15 @@ function ENGINE_update(dt)
 		x = x * -1
 	end
 	if (count < 300) then
-		local s = skelly.new(x, y)
+		-- every tenth one uses the 4-way LPC skeleton
+		local elite = count % 10 == 0
+		local s = elite and lpc_skeleton.new(x, y) or skelly.new(x, y)
 		s.is_skelly = true
 		count = count + 1
 		s.id = ENGINE_HANDLES.create_body(s)
+		if elite then
+			materials.outline(s.id)
+		end
 	end
 	-- FPS calculation
 	fps_debug.frame_count = fps_debug.frame_count + 1
@@ -230,6 +274,15 @@ function ENGINE_update(dt)
 		fps_debug.time_accum = 0
 	end
 
+	-- runs through game over so the death animation is never left frozen
+	ENGINE_HANDLES.tick_hit_stop(dt)
+	materials.update(dt)
+	capture.update()
+	split_screen.update()
+	local health = engine.get_health_table(id)
+	if health.total > 0 then
+		post.update_health(health.current / health.total)
+	end
 	if (WORLD.is_game_over()) then return end
 
 	local dx, dy = 0, 0
@@ -241,7 +294,7 @@ function ENGINE_update(dt)
 	if was_dashing then
 		WORLD.tick_activity_state(id, GLOBALS.ACTIONS.Dashing, dt)
 		if WORLD.is_activity_done(id, GLOBALS.ACTIONS.Dashing) then
-			ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Idle)
+			engine.set_animation_param(id, "dashing", false)
 			engine.set_velocity_2d(id, 0, 0)
 		end
 	end
@@ -273,20 +326,11 @@ function ENGINE_update(dt)
 		return
 	end
 
-	if CONFIG.controller:is_pressed("Up") then
-		dy = dy + 1
-	end
-	if CONFIG.controller:is_pressed("Down") then
-		dy = dy - 1
-	end
-	if CONFIG.controller:is_pressed("MoveLeft") then
-		dx = dx - 1
-	end
-	if CONFIG.controller:is_pressed("MoveRight") then
-		dx = dx + 1
-	end
+	dx = engine.get_axis("MoveX")
+	dy = engine.get_axis("MoveY")
 
-	if CONFIG.controller:is_pressed("Dash") then
+	-- one dash per press, holding the button does not queue another when the cooldown ends
+	if engine.is_action_just_pressed("Dash") then
 		if WORLD.is_off_cooldown(id, GLOBALS.ACTIONS.Dashing) then
 			local dash_time = .3
 			local dash_speed = 30
@@ -295,9 +339,17 @@ function ENGINE_update(dt)
 			local x, y = game_math.normalize(dx, dy)
 
 			if not (x == 0 and y == 0) then
-				ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Dashing)
+				engine.fire_animation_trigger(id, "dash")
+				engine.set_animation_param(id, "dashing", true)
 				WORLD.set_activity_state(id, GLOBALS.ACTIONS.Dashing, dash_time, .5)
 				engine.set_velocity_2d(id, x * dash_speed, y * dash_speed)
+				local position = engine.get_position_2d(id)
+				engine.create_particle_emitter(particles.dash_dust(position[1], position[2], x, y))
+				engine.play_sound(GLOBALS.SOUNDS.Dash, {
+					x = position[1],
+					y = position[2],
+					pitch = 0.9 + math.random() * 0.2,
+				})
 				-- leaving this for now as we can implement a "blink" with this if raycasting can prevent
 				-- blinking through impassable terrain
 				-- engine.apply_move_2d(WORLD.player_id(), x * impulse_strength, y * impulse_strength)
@@ -310,12 +362,11 @@ function ENGINE_update(dt)
 			dx = dx / length
 			dy = dy / length
 			engine.set_velocity_2d(id, dx * CONFIG.speed, dy * CONFIG.speed)
-			ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Running)
 			ENGINE_HANDLES.flip_x(id, dx)
 		else
 			engine.set_velocity_2d(id, 0, 0)
-			ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Idle)
 		end
+		engine.set_animation_param(id, "moving", length > 0)
 	end
 end
 
@@ -336,25 +387,40 @@ function ENGINE_load()
 		},
 	})
 	]]
-	engine.create_ui_scene({
-		initially_active = true,
-		elements = {
-			{ initially_active = true, position_x = 0, position_y = 0, scale_x = 1, scale_y = 1, width = 1, height = 1, animations = main_menu(1, 1).animations },
-		},
-		scenes = {
-		},
-	})
+	engine.load_font("hud", "fonts/DejaVuSansMono.ttf", 20)
+	input_bindings.setup()
+	engine.load_sound(GLOBALS.SOUNDS.Dash)
+	engine.load_sound(GLOBALS.SOUNDS.Footstep)
+
+	-- gameplay actions stay quiet until the menu is dismissed
+	engine.push_input_context("menu")
+	local menu = main_menu(640, 360, function()
+		engine.hide_ui_scene(CONFIG.main_menu)
+		CONFIG.main_menu = nil
+		engine.pop_input_context()
+	end)
+	CONFIG.main_menu = engine.create_ui_scene(menu)[1]
+	engine.focus_ui_element(menu.start_button.id)
 
 	local death = summon_death(0, 0)
 	death.on_collision = "bounce"
 	CONFIG.player = death
 	WORLD.player.id = ENGINE_HANDLES.create_body(death)
+	CONFIG.hud = engine.create_ui_scene(hud(WORLD.player_id()))[1]
+
+	engine.set_ambient_light(table.unpack(lights.dungeon_ambient))
+	WORLD.player.torch = engine.create_light(lights.torch(WORLD.player_id()))
+	post.setup()
+	materials.setup()
+	skelly.setup()
+	camera.setup()
 
 	local build_walls = true
 	if build_walls then
 		local fence_thickness = 2
 		local fence_count_per_side = 25
 		local half_length = fence_thickness * fence_count_per_side / 2
+		camera.set_arena(half_length + fence_thickness / 2)
 
 		-- Bottom wall: centered on y = -half_length, full width
 		local bottom_wall = new_fence(0, -half_length, fence_thickness * fence_count_per_side, fence_thickness)
@@ -379,6 +445,11 @@ function ENGINE_load()
 		right_wall.on_player_collision = "block"
 		right_wall.on_collision = ""
 		right_wall.id = ENGINE_HANDLES.create_body(right_wall)
+
+		local brazier_offset = half_length - 4
+		for _, corner in ipairs({ { -1, -1 }, { -1, 1 }, { 1, -1 }, { 1, 1 } }) do
+			engine.create_light(lights.brazier(corner[1] * brazier_offset, corner[2] * brazier_offset))
+		end
 	end
 
 	local build_skellys = true
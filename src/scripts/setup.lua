@@ -5,5 +5,13 @@ function main()
 		fps = "auto", -- Default auto, set as auto or a number for specific frame rate target
 		height = 800,
 		width = 1000,
+		-- Draws the world at a fixed low resolution, scaled up by whole numbers and letterboxed.
+		-- pixels_per_unit is how many texels of art make one world unit; camera zoom is ignored.
+		-- Remove to render at the window's resolution.
+		pixel_perfect = {
+			width = 500,
+			height = 400,
+			pixels_per_unit = 16,
+		},
 	}
 end
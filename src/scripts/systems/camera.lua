@@ -0,0 +1,40 @@
+-- Main camera feel and the minimap camera in the top right corner
+local HIT_TRAUMA = 0.45
+
+local state = {
+	minimap = nil,
+}
+
+local function setup()
+	engine.configure_camera({
+		dead_zone = { 1, 0.75 },
+		zoom_speed = 3,
+		max_shake = 0.6,
+		trauma_decay = 1.5,
+	})
+	-- square on the default 500x400 pixel-perfect frame
+	state.minimap = engine.create_camera({
+		viewport = { 0.78, 0.02, 0.2, 0.25 },
+		position = { 0, 0 },
+		target = false,
+		order = 1, -- stays on top of split-screen viewports
+	})
+end
+
+-- keeps the main camera inside the arena and fits all of it in the minimap
+local function set_arena(half_extent)
+	engine.configure_camera({ bounds = { -half_extent, -half_extent, half_extent, half_extent } })
+	if state.minimap then
+		engine.configure_camera({ zoom = half_extent + 1 }, state.minimap)
+	end
+end
+
+local function hit()
+	engine.shake_camera(HIT_TRAUMA)
+end
+
+return {
+	setup = setup,
+	set_arena = set_arena,
+	hit = hit,
+}
@@ -0,0 +1,20 @@
+-- Screenshots and frame recordings for bug reports, saved under the working directory
+local RECORDINGS_DIR = "recordings"
+
+local function update()
+	if engine.is_action_just_pressed("Screenshot") then
+		engine.screenshot()
+	end
+	if engine.is_action_just_pressed("Record") then
+		if engine.is_recording() then
+			print("Recorded frames: ", engine.stop_recording())
+		else
+			-- every other frame keeps a 60 fps game at 30 fps without filling the disk twice as fast
+			engine.start_recording(RECORDINGS_DIR .. "/" .. os.time(), 2)
+		end
+	end
+end
+
+return {
+	update = update,
+}
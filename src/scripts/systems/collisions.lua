@@ -1,3 +1,7 @@
+local particles = require("effects.particles")
+local post = require("effects.post")
+local camera = require("systems.camera")
+
 local function on_each_collision(col)
 	local bounce_speed = 20.0
 	local a_id = col.a
@@ -49,9 +53,14 @@ local function on_each_collision(col)
 					)
 			then
 				ENGINE_HANDLES.mark_untargetable(WORLD.player_id(), 1)
+				ENGINE_HANDLES.hit_stop(0.08)
+				post.hit()
+				camera.hit()
+				local position = engine.get_position_2d(WORLD.player_id())
+				engine.create_particle_emitter(particles.hit_sparks(position[1], position[2]))
 				-- local dead = engine.damage(WORLD.player_id(), 2)
 				if dead == true then
-					ENGINE_HANDLES.set_state(WORLD.player_id(), GLOBALS.ACTIONS.Dying)
+					engine.fire_animation_trigger(WORLD.player_id(), "die")
 					WORLD.set_game_over()
 					CONTROLLER.start_input_reenable_timer(100)
 				end
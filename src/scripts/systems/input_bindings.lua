@@ -0,0 +1,44 @@
+-- Default bindings, anything the player rebinds is saved over them in SETTINGS_FILE
+local SETTINGS_FILE = "input_bindings.cfg"
+
+local function bind_defaults()
+	engine.bind_action("gameplay", "Dash", { "space", "gamepad_a" })
+	engine.bind_axis("gameplay", "MoveX", {
+		negative = { "a", "left", "gamepad_left" },
+		positive = { "d", "right", "gamepad_right" },
+		analog = { "gamepad_leftx" },
+	})
+	engine.bind_axis("gameplay", "MoveY", {
+		negative = { "s", "down", "gamepad_down" },
+		positive = { "w", "up", "gamepad_up" },
+		analog = { "gamepad_lefty" },
+	})
+
+	-- arrows, enter and the d-pad already drive focused canvas buttons
+	engine.bind_action("menu", "Back", { "backspace", "gamepad_b" })
+	engine.bind_action("gameplay", "SplitScreen", { "f6" })
+
+	-- captures work from both contexts, see systems/capture.lua
+	for _, context in ipairs({ "gameplay", "menu" }) do
+		engine.bind_action(context, "Screenshot", { "f12" })
+		engine.bind_action(context, "Record", { "f9" })
+	end
+end
+
+local function setup()
+	bind_defaults()
+	engine.load_input_bindings(SETTINGS_FILE)
+	engine.push_input_context("gameplay")
+end
+
+-- for the settings menu, `input` is a name from engine.get_last_input()
+local function rebind(context, action, input)
+	if engine.bind_action(context, action, { input }) then
+		engine.save_input_bindings(SETTINGS_FILE)
+	end
+end
+
+return {
+	setup = setup,
+	rebind = rebind,
+}
@@ -59,7 +59,8 @@ local function apply_drag_to_rigids(dt)
 			engine.set_velocity_2d(id, vel[1], vel[2])
 
 			local idle = 1
-			if speed < idle then
+			-- bodies with an animation graph pick their own state
+			if speed < idle and not elem.animation_graph then
 				ENGINE_HANDLES.set_state(id, GLOBALS.ACTIONS.Idle)
 			end
 		end
@@ -0,0 +1,60 @@
+-- Debug split-screen: cycles the layouts, following the player and the oldest skeletons
+-- until couch co-op has players of its own
+local hud = require("canvas.hud")
+
+local LAYOUTS = { "single", "horizontal", "vertical", "grid" }
+local PLAYERS_PER_LAYOUT = { single = 1, horizontal = 2, vertical = 2, grid = 4 }
+
+local state = {
+	layout = 1,
+	huds = {}, -- one per viewport after the first, made when first needed
+}
+
+local function followed_entities(count)
+	local ids = { WORLD.player_id() }
+	local skellys = {}
+	for id, entity in pairs(CONFIG.entities) do
+		if entity.is_skelly then
+			table.insert(skellys, id)
+		end
+	end
+	table.sort(skellys)
+	for i = 1, count - 1 do
+		table.insert(ids, skellys[i])
+	end
+	return ids
+end
+
+local function show_huds(cameras, targets)
+	engine.set_ui_scene_camera(CONFIG.hud, cameras[1])
+	for index = 2, 4 do
+		local scene = state.huds[index]
+		if cameras[index] and targets[index] then
+			if not scene then
+				local layout = hud(targets[index])
+				scene = { id = engine.create_ui_scene(layout)[1], health_bar = layout.elements[1].id }
+				state.huds[index] = scene
+			end
+			engine.bind_ui_health(scene.health_bar, targets[index])
+			engine.set_ui_scene_camera(scene.id, cameras[index])
+			engine.show_ui_scene(scene.id)
+		elseif scene then
+			engine.hide_ui_scene(scene.id)
+		end
+	end
+end
+
+local function update()
+	if not engine.is_action_just_pressed("SplitScreen") then
+		return
+	end
+	state.layout = state.layout % #LAYOUTS + 1
+	local layout = LAYOUTS[state.layout]
+	local targets = followed_entities(PLAYERS_PER_LAYOUT[layout])
+	local cameras = engine.set_split_screen(layout, targets)
+	show_huds(cameras, targets)
+end
+
+return {
+	update = update,
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::text::font::{Font, FontPage, Glyph};
+
+/// Parses the text variant of the AngelCode BMFont format (`.fnt`).
+/// Page texture ids are resolved relative to `directory` so they match the asset cache keys.
+pub fn parse_bmfont(source: &str, directory: &str) -> Result<Font> {
+    let mut line_height = 0.0;
+    let mut page_size = [0.0, 0.0];
+    let mut page_files: HashMap<usize, String> = HashMap::new();
+    let mut glyphs = HashMap::new();
+    let mut kerning = HashMap::new();
+
+    for line in source.lines() {
+        let mut tokens = tokenize(line).into_iter();
+        let tag = match tokens.next() {
+            Some(tag) => tag,
+            None => continue,
+        };
+        let attributes: HashMap<String, String> = tokens
+            .filter_map(|token| {
+                token
+                    .split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.trim_matches('"').to_string()))
+            })
+            .collect();
+        let number = |key: &str| -> f32 {
+            attributes
+                .get(key)
+                .and_then(|v| v.parse::<f32>().ok())
+                .unwrap_or(0.0)
+        };
+
+        match tag.as_str() {
+            "common" => {
+                line_height = number("lineHeight");
+                page_size = [number("scaleW"), number("scaleH")];
+            }
+            "page" => {
+                let file = attributes
+                    .get("file")
+                    .ok_or_else(|| anyhow!("BMFont page without a file: {}", line))?;
+                page_files.insert(number("id") as usize, format!("{}{}", directory, file));
+            }
+            "char" => {
+                let id = number("id") as u32;
+                if let Some(c) = char::from_u32(id) {
+                    glyphs.insert(
+                        c,
+                        Glyph {
+                            atlas_rect: [
+                                number("x"),
+                                number("y"),
+                                number("width"),
+                                number("height"),
+                            ],
+                            offset: [number("xoffset"), number("yoffset")],
+                            advance: number("xadvance"),
+                            page: number("page") as usize,
+                        },
+                    );
+                }
+            }
+            "kerning" => {
+                let first = char::from_u32(number("first") as u32);
+                let second = char::from_u32(number("second") as u32);
+                if let (Some(first), Some(second)) = (first, second) {
+                    kerning.insert((first, second), number("amount"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if page_files.is_empty() {
+        return Err(anyhow!("BMFont has no pages"));
+    }
+
+    let mut pages = Vec::new();
+    for id in 0..page_files.len() {
+        let texture_id = page_files
+            .remove(&id)
+            .ok_or_else(|| anyhow!("BMFont page ids must be contiguous, missing page {}", id))?;
+        pages.push(FontPage {
+            texture_id,
+            size: page_size,
+        });
+    }
+
+    Ok(Font {
+        line_height,
+        glyphs,
+        kerning,
+        pages,
+    })
+}
+
+// whitespace separated, but quoted values (face="Some Font") stay whole
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
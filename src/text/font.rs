@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    pub atlas_rect: [f32; 4], // x, y, w, h in atlas pixels, top-left origin
+    pub offset: [f32; 2], // from the pen position on the top of the line to the glyph's top-left
+    pub advance: f32,
+    pub page: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct FontPage {
+    pub texture_id: String,
+    pub size: [f32; 2],
+}
+
+/// Glyph metrics in font pixels. Bitmap and TTF fonts end up in the same shape,
+/// so layout and rendering never care where a font came from.
+#[derive(Debug, Clone)]
+pub struct Font {
+    pub line_height: f32,
+    pub glyphs: HashMap<char, Glyph>,
+    pub kerning: HashMap<(char, char), f32>,
+    pub pages: Vec<FontPage>,
+}
+
+impl Font {
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    pub fn kerning(&self, previous: Option<char>, c: char) -> f32 {
+        match previous {
+            Some(p) => self.kerning.get(&(p, c)).copied().unwrap_or(0.0),
+            None => 0.0,
+        }
+    }
+
+    /// UVs in the engine's flipped-texture convention, same order as `SpriteFrame::uv_coords`.
+    pub fn uv_coords(&self, glyph: &Glyph) -> [[f32; 2]; 4] {
+        let page = &self.pages[glyph.page];
+        let [x, y, w, h] = glyph.atlas_rect;
+        let u0 = x / page.size[0];
+        let u1 = (x + w) / page.size[0];
+        let v1 = 1.0 - (y / page.size[1]);
+        let v0 = 1.0 - ((y + h) / page.size[1]);
+        [[u0, v1], [u1, v1], [u1, v0], [u0, v0]]
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    components_systems::{Entity, RenderLayer},
+    graphics_2d::{RenderElement2D, UNIT_RECTANGLE},
+    lua_scriptor::LuaExtendedExecutor,
+    text::font::Font,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl From<&str> for TextAlign {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "center" => TextAlign::Center,
+            "right" => TextAlign::Right,
+            _ => TextAlign::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextLayoutOptions {
+    pub align: TextAlign,
+    pub max_width: Option<f32>, // font pixels, wraps on word boundaries
+    pub line_spacing: f32,      // multiplier on the font's line height
+}
+
+impl Default for TextLayoutOptions {
+    fn default() -> Self {
+        Self {
+            align: TextAlign::Left,
+            max_width: None,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+impl TextLayoutOptions {
+    pub fn from_lua_table(table: &mlua::Table) -> Self {
+        let align: String = table.get("align").unwrap_or("left".to_string());
+        Self {
+            align: TextAlign::from(align.as_str()),
+            max_width: table.get("max_width").ok(),
+            line_spacing: table.get("line_spacing").unwrap_or(1.0),
+        }
+    }
+}
+
+/// Reads `color = { r, g, b, a }` from a Lua table, white when missing.
+pub fn text_color_from_lua(table: &mlua::Table) -> [f32; 4] {
+    LuaExtendedExecutor::table_to_color(table, "color", [1.0, 1.0, 1.0, 1.0])
+}
+
+#[derive(Debug, Clone)]
+pub struct GlyphQuad {
+    pub offset: [f32; 2], // top-left of the glyph from the top-left of the block, y down
+    pub size: [f32; 2],
+    pub uv_coords: [[f32; 2]; 4],
+    pub texture_id: String,
+}
+
+/// Laid out text in font pixels, independent of where it ends up being drawn.
+#[derive(Debug, Clone, Default)]
+pub struct TextMesh {
+    pub glyphs: Vec<GlyphQuad>,
+    pub size: [f32; 2],
+}
+
+pub fn measure(font: &Font, text: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        if let Some(glyph) = font.glyph(c) {
+            width += glyph.advance + font.kerning(previous, c);
+        }
+        previous = Some(c);
+    }
+    width
+}
+
+fn wrap_lines(font: &Font, text: &str, max_width: Option<f32>) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let max_width = match max_width {
+            Some(w) => w,
+            None => {
+                lines.push(paragraph.to_string());
+                continue;
+            }
+        };
+
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            // a single word wider than the box still gets its own line
+            if !line.is_empty() && measure(font, &candidate) > max_width {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub fn layout_text(font: &Font, text: &str, options: &TextLayoutOptions) -> TextMesh {
+    let lines = wrap_lines(font, text, options.max_width);
+    let line_widths: Vec<f32> = lines.iter().map(|line| measure(font, line)).collect();
+    let block_width = options
+        .max_width
+        .unwrap_or_else(|| line_widths.iter().cloned().fold(0.0, f32::max));
+    let line_advance = font.line_height * options.line_spacing;
+
+    let mut glyphs = Vec::new();
+    for (row, (line, line_width)) in lines.iter().zip(line_widths.iter()).enumerate() {
+        let mut pen_x = match options.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (block_width - line_width) * 0.5,
+            TextAlign::Right => block_width - line_width,
+        };
+        let pen_y = row as f32 * line_advance;
+        let mut previous = None;
+
+        for c in line.chars() {
+            if let Some(glyph) = font.glyph(c) {
+                pen_x += font.kerning(previous, c);
+                if glyph.atlas_rect[2] > 0.0 && glyph.atlas_rect[3] > 0.0 {
+                    glyphs.push(GlyphQuad {
+                        offset: [pen_x + glyph.offset[0], pen_y + glyph.offset[1]],
+                        size: [glyph.atlas_rect[2], glyph.atlas_rect[3]],
+                        uv_coords: font.uv_coords(glyph),
+                        texture_id: font.pages[glyph.page].texture_id.clone(),
+                    });
+                }
+                pen_x += glyph.advance;
+            }
+            previous = Some(c);
+        }
+    }
+
+    TextMesh {
+        glyphs,
+        size: [block_width, lines.len() as f32 * line_advance],
+    }
+}
+
+impl TextMesh {
+    /// `top_left` is in the target space (world units or canvas pixels, y up);
+    /// `scale` converts font pixels into that space.
+    pub fn render_elements(
+        &self,
+        top_left: [f32; 2],
+        scale: f32,
+        color: [f32; 4],
+        layer: RenderLayer,
+        z_order: f32,
+        entity: Entity,
+    ) -> Vec<RenderElement2D<'static>> {
+        self.glyphs
+            .iter()
+            .map(|glyph| RenderElement2D {
+                shape: &UNIT_RECTANGLE,
+                position: [
+                    top_left[0] + (glyph.offset[0] + glyph.size[0] * 0.5) * scale,
+                    top_left[1] - (glyph.offset[1] + glyph.size[1] * 0.5) * scale,
+                ],
+                size: [glyph.size[0] * scale, glyph.size[1] * scale],
+                layer,
+                z_order,
+                entity,
+                texture_id: glyph.texture_id.clone(),
+                uv_coords: glyph.uv_coords,
+                rotation: 0.0,
+                tint: color,
+                flash: [0.0, 0.0, 0.0, 0.0],
+                opacity: 1.0,
+                material: None,
+            })
+            .collect()
+    }
+}
+
+/// A laid out string placed in the world for a single frame.
+#[derive(Debug, Clone)]
+pub struct WorldText {
+    pub mesh: TextMesh,
+    pub position: [f32; 2],
+    pub anchor: [f32; 2], // fraction of the block placed on `position`, (0, 0) = top left
+    pub scale: f32,
+    pub color: [f32; 4],
+}
+
+impl WorldText {
+    pub fn render_elements(&self, entity: Entity) -> Vec<RenderElement2D<'static>> {
+        let top_left = [
+            self.position[0] - self.anchor[0] * self.mesh.size[0] * self.scale,
+            self.position[1] + self.anchor[1] * self.mesh.size[1] * self.scale,
+        ];
+        self.mesh.render_elements(
+            top_left,
+            self.scale,
+            self.color,
+            RenderLayer::Effects,
+            f32::MAX,
+            entity,
+        )
+    }
+}
@@ -0,0 +1,9 @@
+mod bmfont;
+mod font;
+mod layout;
+mod ttf;
+
+pub use bmfont::parse_bmfont;
+pub use font::Font;
+pub use layout::{layout_text, text_color_from_lua, TextLayoutOptions, TextMesh, WorldText};
+pub use ttf::{default_charset, rasterize_ttf};
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font as _, FontVec, PxScale, ScaleFont};
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+
+use crate::text::font::{Font, FontPage, Glyph};
+
+const ATLAS_WIDTH: u32 = 512;
+const GLYPH_PADDING: u32 = 1;
+const MAX_ATLAS_HEIGHT: u32 = 8192; // wgpu's default texture size limit
+
+/// Printable ASCII plus Latin-1, enough for names and HUD text.
+pub fn default_charset() -> Vec<char> {
+    (0x20u32..0x7f)
+        .chain(0xa0u32..0x100)
+        .filter_map(char::from_u32)
+        .collect()
+}
+
+/// Rasterizes a TTF/OTF font at `px` pixels per em into a single white glyph atlas.
+/// Color comes from the sprite tint, so one atlas serves every text color.
+pub fn rasterize_ttf(
+    bytes: Vec<u8>,
+    px: f32,
+    texture_id: &str,
+    charset: &[char],
+) -> Result<(Font, RgbaImage)> {
+    let font = FontVec::try_from_vec(bytes).map_err(|e| anyhow!("Invalid font: {}", e))?;
+    let scale = PxScale::from(px);
+    let scaled = font.as_scaled(scale);
+    let ascent = scaled.ascent();
+
+    // shelf pack: place glyphs left to right, start a new row when the current one is full
+    let mut outlines = Vec::new();
+    let mut glyphs = HashMap::new();
+    let (mut pen_x, mut pen_y, mut row_height) = (GLYPH_PADDING, GLYPH_PADDING, 0);
+
+    for &c in charset {
+        let glyph_id = font.glyph_id(c);
+        if glyph_id.0 == 0 && c != ' ' {
+            continue;
+        }
+        let advance = scaled.h_advance(glyph_id);
+        let glyph = glyph_id.with_scale_and_position(scale, ab_glyph::point(0.0, ascent));
+
+        let outline = match font.outline_glyph(glyph) {
+            Some(outline) => outline,
+            None => {
+                // whitespace has metrics but nothing to draw
+                glyphs.insert(
+                    c,
+                    Glyph {
+                        atlas_rect: [0.0, 0.0, 0.0, 0.0],
+                        offset: [0.0, 0.0],
+                        advance,
+                        page: 0,
+                    },
+                );
+                continue;
+            }
+        };
+
+        let bounds = outline.px_bounds();
+        let width = bounds.width().ceil() as u32;
+        let height = bounds.height().ceil() as u32;
+        if width + GLYPH_PADDING * 2 > ATLAS_WIDTH {
+            return Err(anyhow!(
+                "Glyph {:?} is {} px wide at {} px, the atlas is {} px",
+                c,
+                width,
+                px,
+                ATLAS_WIDTH
+            ));
+        }
+        if pen_x + width + GLYPH_PADDING > ATLAS_WIDTH {
+            pen_x = GLYPH_PADDING;
+            pen_y += row_height + GLYPH_PADDING;
+            row_height = 0;
+        }
+
+        glyphs.insert(
+            c,
+            Glyph {
+                atlas_rect: [pen_x as f32, pen_y as f32, width as f32, height as f32],
+                offset: [bounds.min.x, bounds.min.y],
+                advance,
+                page: 0,
+            },
+        );
+        outlines.push((pen_x, pen_y, outline));
+
+        pen_x += width + GLYPH_PADDING;
+        row_height = row_height.max(height);
+    }
+
+    let atlas_height = (pen_y + row_height + GLYPH_PADDING).next_power_of_two();
+    if atlas_height > MAX_ATLAS_HEIGHT {
+        return Err(anyhow!(
+            "{} px glyphs need a {} px tall atlas, at most {} px fit",
+            px,
+            atlas_height,
+            MAX_ATLAS_HEIGHT
+        ));
+    }
+    let mut atlas = RgbaImage::from_pixel(ATLAS_WIDTH, atlas_height, Rgba([255, 255, 255, 0]));
+    for (x, y, outline) in outlines {
+        outline.draw(|gx, gy, coverage| {
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+            atlas.put_pixel(x + gx, y + gy, Rgba([255, 255, 255, alpha]));
+        });
+    }
+
+    let mut kerning = HashMap::new();
+    for &a in charset {
+        for &b in charset {
+            let amount = scaled.kern(font.glyph_id(a), font.glyph_id(b));
+            if amount != 0.0 {
+                kerning.insert((a, b), amount);
+            }
+        }
+    }
+
+    Ok((
+        Font {
+            line_height: scaled.height() + scaled.line_gap(),
+            glyphs,
+            kerning,
+            pages: vec![FontPage {
+                texture_id: texture_id.to_string(),
+                size: [ATLAS_WIDTH as f32, atlas_height as f32],
+            }],
+        },
+        atlas,
+    ))
+}
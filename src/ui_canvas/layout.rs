@@ -0,0 +1,620 @@
+// Flexbox-like layout for canvas trees.
+// Everything here works on plain rectangles in window pixels (origin top left, y down)
+// so it can be checked without a canvas, a window or Lua.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Px(f32),
+    Percent(f32), // 0..1 of the parent's content box
+    Auto,         // the node's content size
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Edges {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Stack, // children are placed independently through their anchor
+    Row,
+    Column,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutStyle {
+    pub width: Length,
+    pub height: Length,
+    pub scale: [f32; 2],
+    pub margin: Edges,
+    pub padding: Edges,
+    // fraction of the parent the node is attached to and the same fraction of the node,
+    // only used when the parent stacks its children
+    pub anchor: [f32; 2],
+    pub offset: [f32; 2], // pixels added after placement
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align_items: Align,
+    pub gap: f32,
+    pub grow: f32, // share of the parent's free main axis space
+}
+
+#[derive(Debug, Clone)]
+pub struct LayoutNode {
+    pub style: LayoutStyle,
+    pub intrinsic: [f32; 2], // content size of leaves, ignored once a node has children
+    pub children: Vec<LayoutNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedLayout {
+    pub rect: Rect,
+    pub children: Vec<ResolvedLayout>,
+}
+
+impl Default for LayoutStyle {
+    fn default() -> Self {
+        Self {
+            width: Length::Auto,
+            height: Length::Auto,
+            scale: [1.0, 1.0],
+            margin: Edges::default(),
+            padding: Edges::default(),
+            anchor: [0.5, 0.5],
+            offset: [0.0, 0.0],
+            direction: Direction::Stack,
+            justify: Justify::Start,
+            align_items: Align::Start,
+            gap: 0.0,
+            grow: 0.0,
+        }
+    }
+}
+
+impl Edges {
+    pub fn all(value: f32) -> Self {
+        Self {
+            left: value,
+            top: value,
+            right: value,
+            bottom: value,
+        }
+    }
+
+    fn horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+
+    fn vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn center(&self) -> [f32; 2] {
+        [self.x + self.w * 0.5, self.y + self.h * 0.5]
+    }
+
+    fn shrink(&self, edges: &Edges) -> Self {
+        Self {
+            x: self.x + edges.left,
+            y: self.y + edges.top,
+            w: (self.w - edges.horizontal()).max(0.0),
+            h: (self.h - edges.vertical()).max(0.0),
+        }
+    }
+}
+
+impl Length {
+    fn resolve(&self, parent: f32, content: f32) -> f32 {
+        match self {
+            Length::Px(px) => *px,
+            Length::Percent(fraction) => parent * fraction,
+            Length::Auto => content,
+        }
+    }
+}
+
+impl LayoutNode {
+    /// Size the node wants when its parent gives no constraints, padding included.
+    /// Percentages cannot be known yet and count as zero.
+    fn measure(&self) -> [f32; 2] {
+        let content = if self.children.is_empty() {
+            self.intrinsic
+        } else {
+            let sizes: Vec<[f32; 2]> = self.children.iter().map(|c| c.outer_measure()).collect();
+            let gaps = self.style.gap * sizes.len().saturating_sub(1) as f32;
+            let sum = |axis: usize| sizes.iter().map(|s| s[axis]).sum::<f32>();
+            let max = |axis: usize| sizes.iter().map(|s| s[axis]).fold(0.0, f32::max);
+            match self.style.direction {
+                Direction::Stack => [max(0), max(1)],
+                Direction::Row => [sum(0) + gaps, max(1)],
+                Direction::Column => [max(0), sum(1) + gaps],
+            }
+        };
+        let padded = [
+            content[0] + self.style.padding.horizontal(),
+            content[1] + self.style.padding.vertical(),
+        ];
+        [
+            self.style.width.resolve(0.0, padded[0]) * self.style.scale[0],
+            self.style.height.resolve(0.0, padded[1]) * self.style.scale[1],
+        ]
+    }
+
+    fn outer_measure(&self) -> [f32; 2] {
+        let size = self.measure();
+        [
+            size[0] + self.style.margin.horizontal(),
+            size[1] + self.style.margin.vertical(),
+        ]
+    }
+
+    // border box size inside a parent content box
+    fn resolve_size(&self, parent: &Rect) -> [f32; 2] {
+        let measured = self.measure();
+        [
+            match self.style.width {
+                Length::Auto => measured[0],
+                width => width.resolve(parent.w, 0.0) * self.style.scale[0],
+            },
+            match self.style.height {
+                Length::Auto => measured[1],
+                height => height.resolve(parent.h, 0.0) * self.style.scale[1],
+            },
+        ]
+    }
+}
+
+/// Lays out `root` inside `parent` (usually the whole window) as if the parent stacked it.
+pub fn compute_layout(root: &LayoutNode, parent: Rect) -> ResolvedLayout {
+    let size = root.resolve_size(&parent);
+    let rect = place_stacked(&root.style, size, &parent);
+    layout_children(root, rect)
+}
+
+fn place_stacked(style: &LayoutStyle, size: [f32; 2], parent: &Rect) -> Rect {
+    let outer = [
+        size[0] + style.margin.horizontal(),
+        size[1] + style.margin.vertical(),
+    ];
+    Rect::new(
+        parent.x + (parent.w - outer[0]) * style.anchor[0] + style.margin.left + style.offset[0],
+        parent.y + (parent.h - outer[1]) * style.anchor[1] + style.margin.top + style.offset[1],
+        size[0],
+        size[1],
+    )
+}
+
+fn layout_children(node: &LayoutNode, rect: Rect) -> ResolvedLayout {
+    let content = rect.shrink(&node.style.padding);
+    let rects = match node.style.direction {
+        Direction::Stack => node
+            .children
+            .iter()
+            .map(|child| place_stacked(&child.style, child.resolve_size(&content), &content))
+            .collect(),
+        Direction::Row => flow(node, &content, 0),
+        Direction::Column => flow(node, &content, 1),
+    };
+
+    ResolvedLayout {
+        rect,
+        children: node
+            .children
+            .iter()
+            .zip(rects)
+            .map(|(child, rect)| layout_children(child, rect))
+            .collect(),
+    }
+}
+
+// row (axis 0) or column (axis 1) placement
+fn flow(node: &LayoutNode, content: &Rect, main: usize) -> Vec<Rect> {
+    let cross = 1 - main;
+    let origin = [content.x, content.y];
+    let extent = [content.w, content.h];
+    let style = &node.style;
+    let count = node.children.len();
+
+    let margins = |child: &LayoutNode| {
+        let m = &child.style.margin;
+        // leading and trailing margin per axis
+        [[m.left, m.right], [m.top, m.bottom]]
+    };
+
+    let mut sizes: Vec<[f32; 2]> = node
+        .children
+        .iter()
+        .map(|child| child.resolve_size(content))
+        .collect();
+
+    let used: f32 = node
+        .children
+        .iter()
+        .zip(sizes.iter())
+        .map(|(child, size)| size[main] + margins(child)[main].iter().sum::<f32>())
+        .sum::<f32>()
+        + style.gap * count.saturating_sub(1) as f32;
+    let mut free = extent[main] - used;
+
+    let total_grow: f32 = node.children.iter().map(|c| c.style.grow.max(0.0)).sum();
+    if free > 0.0 && total_grow > 0.0 {
+        for (child, size) in node.children.iter().zip(sizes.iter_mut()) {
+            size[main] += free * child.style.grow.max(0.0) / total_grow;
+        }
+        free = 0.0;
+    }
+
+    let (mut cursor, gap) = match style.justify {
+        Justify::Start => (0.0, style.gap),
+        Justify::Center => (free.max(0.0) * 0.5, style.gap),
+        Justify::End => (free.max(0.0), style.gap),
+        Justify::SpaceBetween if count > 1 && free > 0.0 => {
+            (0.0, style.gap + free / (count - 1) as f32)
+        }
+        Justify::SpaceBetween => (0.0, style.gap),
+    };
+
+    let mut rects = Vec::with_capacity(count);
+    for (child, size) in node.children.iter().zip(sizes.iter_mut()) {
+        let m = margins(child);
+        let cross_space = extent[cross] - m[cross][0] - m[cross][1];
+        let cross_offset = match style.align_items {
+            Align::Start => 0.0,
+            Align::Center => (cross_space - size[cross]) * 0.5,
+            Align::End => cross_space - size[cross],
+            Align::Stretch => {
+                size[cross] = cross_space.max(0.0);
+                0.0
+            }
+        };
+
+        let mut position = [0.0; 2];
+        position[main] = origin[main] + cursor + m[main][0];
+        position[cross] = origin[cross] + m[cross][0] + cross_offset;
+        rects.push(Rect::new(
+            position[0] + child.style.offset[0],
+            position[1] + child.style.offset[1],
+            size[0],
+            size[1],
+        ));
+
+        cursor += m[main][0] + size[main] + m[main][1] + gap;
+    }
+    rects
+}
+
+// Lua
+
+pub fn parse_length(table: &mlua::Table, key: &str, default: Length) -> Length {
+    match table.get::<mlua::Value>(key) {
+        Ok(mlua::Value::Integer(px)) => Length::Px(px as f32),
+        Ok(mlua::Value::Number(px)) => Length::Px(px as f32),
+        Ok(mlua::Value::String(s)) => {
+            let s = s.to_string_lossy();
+            match s.trim().strip_suffix('%') {
+                Some(percent) => percent
+                    .trim()
+                    .parse::<f32>()
+                    .map(|p| Length::Percent(p / 100.0))
+                    .unwrap_or(default),
+                None if s.trim() == "auto" => Length::Auto,
+                None => s.trim().parse::<f32>().map(Length::Px).unwrap_or(default),
+            }
+        }
+        _ => default,
+    }
+}
+
+/// `margin = 8` or `margin = { left = 4, top = 8 }`, missing sides are 0.
+pub fn parse_edges(table: &mlua::Table, key: &str) -> Edges {
+    match table.get::<mlua::Value>(key) {
+        Ok(mlua::Value::Table(sides)) => Edges {
+            left: sides.get("left").unwrap_or(0.0),
+            top: sides.get("top").unwrap_or(0.0),
+            right: sides.get("right").unwrap_or(0.0),
+            bottom: sides.get("bottom").unwrap_or(0.0),
+        },
+        Ok(value) => Edges::all(value.as_f32().unwrap_or(0.0)),
+        Err(_) => Edges::default(),
+    }
+}
+
+/// `anchor = "top_right"` or `anchor_x` / `anchor_y` fractions, which win over the name.
+/// Without either the anchor is `default`.
+pub fn parse_anchor(table: &mlua::Table, default: [f32; 2]) -> [f32; 2] {
+    let named: Option<String> = table.get("anchor").ok();
+    let [x, y] = match named.as_deref() {
+        Some("top_left") => [0.0, 0.0],
+        Some("top") => [0.5, 0.0],
+        Some("top_right") => [1.0, 0.0],
+        Some("left") => [0.0, 0.5],
+        Some("center") => [0.5, 0.5],
+        Some("right") => [1.0, 0.5],
+        Some("bottom_left") => [0.0, 1.0],
+        Some("bottom") => [0.5, 1.0],
+        Some("bottom_right") => [1.0, 1.0],
+        _ => default,
+    };
+    [
+        table.get("anchor_x").unwrap_or(x),
+        table.get("anchor_y").unwrap_or(y),
+    ]
+}
+
+impl LayoutStyle {
+    /// `default_size` is used for missing `width`/`height`: scenes fill their parent, elements fit their content.
+    pub fn from_lua_table(table: &mlua::Table, default_size: Length) -> Self {
+        let direction: String = table.get("direction").unwrap_or_default();
+        let justify: String = table.get("justify").unwrap_or_default();
+        let align: String = table.get("align_items").unwrap_or_default();
+
+        Self {
+            width: parse_length(table, "width", default_size),
+            height: parse_length(table, "height", default_size),
+            scale: [
+                table.get("scale_x").unwrap_or(1.0),
+                table.get("scale_y").unwrap_or(1.0),
+            ],
+            margin: parse_edges(table, "margin"),
+            padding: parse_edges(table, "padding"),
+            anchor: parse_anchor(table, [0.5, 0.5]),
+            offset: [
+                table.get("position_x").unwrap_or(0.0),
+                table.get("position_y").unwrap_or(0.0),
+            ],
+            direction: match direction.as_str() {
+                "row" => Direction::Row,
+                "column" => Direction::Column,
+                _ => Direction::Stack,
+            },
+            justify: match justify.as_str() {
+                "center" => Justify::Center,
+                "end" => Justify::End,
+                "space_between" => Justify::SpaceBetween,
+                _ => Justify::Start,
+            },
+            align_items: match align.as_str() {
+                "center" => Align::Center,
+                "end" => Align::End,
+                "stretch" => Align::Stretch,
+                _ => Align::Start,
+            },
+            gap: table.get("gap").unwrap_or(0.0),
+            grow: table.get("grow").unwrap_or(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(style: LayoutStyle, intrinsic: [f32; 2]) -> LayoutNode {
+        LayoutNode {
+            style,
+            intrinsic,
+            children: Vec::new(),
+        }
+    }
+
+    fn sized(width: f32, height: f32) -> LayoutStyle {
+        LayoutStyle {
+            width: Length::Px(width),
+            height: Length::Px(height),
+            ..LayoutStyle::default()
+        }
+    }
+
+    fn window() -> Rect {
+        Rect::new(0.0, 0.0, 800.0, 600.0)
+    }
+
+    #[test]
+    fn anchors_place_the_same_fraction_of_the_node_on_the_parent() {
+        let centered = compute_layout(&leaf(sized(100.0, 50.0), [0.0, 0.0]), window());
+        assert_eq!(centered.rect, Rect::new(350.0, 275.0, 100.0, 50.0));
+
+        let bottom_right = LayoutStyle {
+            anchor: [1.0, 1.0],
+            offset: [-10.0, -20.0],
+            ..sized(100.0, 50.0)
+        };
+        let corner = compute_layout(&leaf(bottom_right, [0.0, 0.0]), window());
+        assert_eq!(corner.rect, Rect::new(690.0, 530.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn margins_push_away_from_the_anchored_edge() {
+        let style = LayoutStyle {
+            anchor: [0.0, 0.0],
+            margin: Edges::all(8.0),
+            ..sized(100.0, 50.0)
+        };
+        let layout = compute_layout(&leaf(style, [0.0, 0.0]), window());
+        assert_eq!(layout.rect, Rect::new(8.0, 8.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn padding_shrinks_the_box_children_are_placed_in() {
+        let parent = LayoutNode {
+            style: LayoutStyle {
+                anchor: [0.0, 0.0],
+                padding: Edges {
+                    left: 10.0,
+                    top: 20.0,
+                    right: 30.0,
+                    bottom: 40.0,
+                },
+                ..sized(200.0, 200.0)
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![
+                leaf(
+                    LayoutStyle {
+                        anchor: [0.0, 0.0],
+                        ..sized(10.0, 10.0)
+                    },
+                    [0.0, 0.0],
+                ),
+                leaf(
+                    LayoutStyle {
+                        anchor: [1.0, 1.0],
+                        ..sized(10.0, 10.0)
+                    },
+                    [0.0, 0.0],
+                ),
+            ],
+        };
+        let layout = compute_layout(&parent, window());
+        assert_eq!(layout.children[0].rect, Rect::new(10.0, 20.0, 10.0, 10.0));
+        assert_eq!(layout.children[1].rect, Rect::new(160.0, 150.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn auto_size_fits_children_and_padding() {
+        let column = LayoutNode {
+            style: LayoutStyle {
+                direction: Direction::Column,
+                padding: Edges::all(5.0),
+                gap: 4.0,
+                ..LayoutStyle::default()
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![
+                leaf(LayoutStyle::default(), [60.0, 20.0]),
+                leaf(LayoutStyle::default(), [40.0, 30.0]),
+            ],
+        };
+        let layout = compute_layout(&column, window());
+        assert_eq!(layout.rect.w, 70.0);
+        assert_eq!(layout.rect.h, 64.0);
+    }
+
+    #[test]
+    fn rows_stack_children_with_gaps_justify_and_align() {
+        let row = LayoutNode {
+            style: LayoutStyle {
+                anchor: [0.0, 0.0],
+                direction: Direction::Row,
+                justify: Justify::Center,
+                align_items: Align::End,
+                gap: 10.0,
+                ..sized(300.0, 100.0)
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![
+                leaf(sized(50.0, 20.0), [0.0, 0.0]),
+                leaf(sized(70.0, 40.0), [0.0, 0.0]),
+            ],
+        };
+        let layout = compute_layout(&row, window());
+        // 130 px used of 300, centered
+        assert_eq!(layout.children[0].rect, Rect::new(85.0, 80.0, 50.0, 20.0));
+        assert_eq!(layout.children[1].rect, Rect::new(145.0, 60.0, 70.0, 40.0));
+    }
+
+    #[test]
+    fn columns_space_between_and_stretch() {
+        let column = LayoutNode {
+            style: LayoutStyle {
+                anchor: [0.0, 0.0],
+                direction: Direction::Column,
+                justify: Justify::SpaceBetween,
+                align_items: Align::Stretch,
+                ..sized(100.0, 200.0)
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![
+                leaf(LayoutStyle::default(), [20.0, 50.0]),
+                leaf(LayoutStyle::default(), [20.0, 50.0]),
+            ],
+        };
+        let layout = compute_layout(&column, window());
+        assert_eq!(layout.children[0].rect, Rect::new(0.0, 0.0, 100.0, 50.0));
+        assert_eq!(layout.children[1].rect, Rect::new(0.0, 150.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn percent_sizes_resolve_against_the_parent_content_box() {
+        let parent = LayoutNode {
+            style: LayoutStyle {
+                anchor: [0.0, 0.0],
+                padding: Edges::all(50.0),
+                ..sized(400.0, 300.0)
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![leaf(
+                LayoutStyle {
+                    width: Length::Percent(0.5),
+                    height: Length::Percent(1.0),
+                    anchor: [0.0, 0.0],
+                    ..LayoutStyle::default()
+                },
+                [0.0, 0.0],
+            )],
+        };
+        let layout = compute_layout(&parent, window());
+        assert_eq!(layout.children[0].rect, Rect::new(50.0, 50.0, 150.0, 200.0));
+
+        let full = LayoutStyle {
+            width: Length::Percent(1.0),
+            height: Length::Percent(0.25),
+            ..LayoutStyle::default()
+        };
+        let layout = compute_layout(&leaf(full, [0.0, 0.0]), window());
+        assert_eq!(layout.rect, Rect::new(0.0, 225.0, 800.0, 150.0));
+    }
+
+    #[test]
+    fn grow_shares_the_free_main_axis_space() {
+        let grow = |grow: f32| LayoutStyle {
+            grow,
+            ..sized(0.0, 10.0)
+        };
+        let row = LayoutNode {
+            style: LayoutStyle {
+                anchor: [0.0, 0.0],
+                direction: Direction::Row,
+                ..sized(300.0, 10.0)
+            },
+            intrinsic: [0.0, 0.0],
+            children: vec![leaf(grow(1.0), [0.0, 0.0]), leaf(grow(2.0), [0.0, 0.0])],
+        };
+        let layout = compute_layout(&row, window());
+        assert_eq!(layout.children[0].rect, Rect::new(0.0, 0.0, 100.0, 10.0));
+        assert_eq!(layout.children[1].rect, Rect::new(100.0, 0.0, 200.0, 10.0));
+    }
+}
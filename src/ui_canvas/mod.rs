@@ -1,3 +1,6 @@
+mod layout;
 mod ui_canvas;
+mod widgets;
 
-pub use ui_canvas::{parse_scene_from_lua, Canvas, CanvasElement, CanvasScene};
+pub use layout::parse_anchor;
+pub use ui_canvas::{parse_scene_from_lua, Canvas, CanvasElement, CanvasScene, UiClick};
@@ -3,18 +3,57 @@ use std::collections::HashMap;
 use cgmath::Vector2;
 
 use crate::{
-    components_systems::{physics_2d::Shape2D, ActionState, Animation, AnimationComponent, Entity},
+    components_systems::{
+        physics_2d::Shape2D, ActionState, Animation, AnimationComponent, Entity, HealthComponent,
+        RenderLayer,
+    },
     graphics_2d::{RenderElement2D, RenderQueue2D},
     lua_scriptor::LuaExtendedExecutor,
+    text::{layout_text, text_color_from_lua, Font, TextLayoutOptions, TextMesh, WorldText},
+    ui_canvas::{
+        layout::{compute_layout, LayoutNode, LayoutStyle, Length, Rect, ResolvedLayout},
+        widgets::{Button, NineSlice, ProgressBar},
+    },
 };
 
+// canvas elements only ever play their first animation
+const CANVAS_STATE: ActionState = ActionState::Custom(0);
+
 #[derive(Debug)]
 pub struct CanvasElement {
-    position: Vector2<f32>,
-    scale: Vector2<f32>,
+    style: LayoutStyle,
+    // resolved by the canvas layout, window pixels with the origin top left
+    rect: Rect,
+    z: f32,
     shape: Shape2D,
-    pub sprite_sheet: String,
-    animation: AnimationComponent,
+    content: CanvasContent,
+}
+
+#[derive(Debug)]
+enum CanvasContent {
+    Sprite {
+        sprite_sheet: String,
+        animation: AnimationComponent,
+    },
+    Text(CanvasText),
+    Panel {
+        panel: NineSlice,
+        tint: [f32; 4],
+    },
+    Button {
+        button: Button,
+        label: Option<CanvasText>,
+    },
+    ProgressBar(ProgressBar),
+}
+
+#[derive(Debug)]
+pub struct CanvasText {
+    text: String,
+    font: String,
+    options: TextLayoutOptions,
+    color: [f32; 4],
+    mesh: TextMesh,
 }
 
 #[derive(Debug)]
@@ -23,6 +62,14 @@ pub struct CanvasScene {
     pub elements: HashMap<Entity, CanvasElement>,
     active_scenes: Vec<Entity>,
     active_elements: Vec<Entity>,
+    style: LayoutStyle,
+    children: Vec<CanvasChild>, // declaration order, rows and columns flow in this order
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CanvasChild {
+    Element(Entity),
+    Scene(Entity),
 }
 
 #[derive(Debug)]
@@ -30,14 +77,408 @@ pub struct Canvas {
     next_id: u32,
     scenes: HashMap<Entity, CanvasScene>,
     active_scenes: Vec<Entity>,
+    screen_size: [f32; 2],
+    scene_regions: HashMap<Entity, Rect>, // top level scenes laid out in part of the window
+    texts: Vec<WorldText>,                // immediate mode, cleared after every frame
+    focused: Option<Entity>,
+    pressed: Option<Entity>,
+    layout_dirty: bool,
+}
+
+/// A button that was clicked or activated, the engine runs the callback once the canvas is released.
+pub struct UiClick {
+    pub id: Entity,
+    pub on_click: Option<mlua::Function>,
+}
+
+// an active button's hit box in the static camera's space
+#[derive(Debug, Clone, Copy)]
+struct ButtonBounds {
+    id: Entity,
+    center: [f32; 2],
+    half_size: [f32; 2],
+    z: f32,
+}
+
+impl ButtonBounds {
+    fn contains(&self, point: [f32; 2]) -> bool {
+        (point[0] - self.center[0]).abs() <= self.half_size[0]
+            && (point[1] - self.center[1]).abs() <= self.half_size[1]
+    }
+}
+
+impl CanvasElement {
+    /// Center of the element in the static camera's space (origin at screen center, y up).
+    pub fn resolve_center(&self, screen_size: [f32; 2]) -> [f32; 2] {
+        let center = self.rect.center();
+        [
+            center[0] - screen_size[0] * 0.5,
+            screen_size[1] * 0.5 - center[1],
+        ]
+    }
+
+    pub fn scaled_size(&self) -> [f32; 2] {
+        [self.rect.w, self.rect.h]
+    }
+
+    // content size for `auto` widths and heights, before scaling
+    fn intrinsic_size(&self) -> [f32; 2] {
+        match &self.content {
+            CanvasContent::Text(text) => text.mesh.size,
+            CanvasContent::Button {
+                label: Some(label), ..
+            } => label.mesh.size,
+            _ => [0.0, 0.0],
+        }
+    }
+
+    /// Top-left corner in the static camera's space.
+    fn resolve_top_left(&self, screen_size: [f32; 2]) -> [f32; 2] {
+        let center = self.resolve_center(screen_size);
+        let size = self.scaled_size();
+        [center[0] - size[0] * 0.5, center[1] + size[1] * 0.5]
+    }
+
+    fn button_mut(&mut self) -> Option<&mut Button> {
+        match &mut self.content {
+            CanvasContent::Button { button, .. } => Some(button),
+            _ => None,
+        }
+    }
+
+    fn progress_bar_mut(&mut self) -> Option<&mut ProgressBar> {
+        match &mut self.content {
+            CanvasContent::ProgressBar(bar) => Some(bar),
+            _ => None,
+        }
+    }
+
+    fn set_text(&mut self, text: &str, fonts: &HashMap<String, Font>) -> bool {
+        match &mut self.content {
+            CanvasContent::Text(content) => content.set_text(text, fonts),
+            CanvasContent::Button {
+                label: Some(label), ..
+            } => label.set_text(text, fonts),
+            _ => false,
+        }
+    }
+
+    fn extract_into<'a>(
+        &'a self,
+        id: Entity,
+        screen_size: [f32; 2],
+        queue: &mut RenderQueue2D<'a>,
+    ) {
+        match &self.content {
+            CanvasContent::Sprite {
+                sprite_sheet,
+                animation,
+            } => queue.transparent.push(RenderElement2D {
+                shape: &self.shape,
+                position: self.resolve_center(screen_size),
+                size: self.scaled_size(),
+                layer: RenderLayer::Overhead, // the canvas has its own pass, only z matters
+                z_order: self.z,
+                entity: id,
+                texture_id: sprite_sheet.clone(),
+                uv_coords: animation.current_frame.uv_coords,
+                rotation: 0.0,
+                tint: [1.0, 1.0, 1.0, 1.0],
+                flash: [0.0, 0.0, 0.0, 0.0],
+                opacity: 1.0,
+                material: None,
+            }),
+            CanvasContent::Text(content) => queue.transparent.extend(content.render_elements(
+                self.resolve_center(screen_size),
+                self.style.scale[0],
+                self.z,
+                id,
+            )),
+            CanvasContent::Panel { panel, tint } => {
+                queue.transparent.extend(panel.render_elements(
+                    self.resolve_top_left(screen_size),
+                    self.scaled_size(),
+                    *tint,
+                    self.z,
+                    id,
+                ))
+            }
+            CanvasContent::Button { button, label } => {
+                queue.transparent.extend(button.panel.render_elements(
+                    self.resolve_top_left(screen_size),
+                    self.scaled_size(),
+                    button.tint(),
+                    self.z,
+                    id,
+                ));
+                if let Some(label) = label {
+                    queue.transparent.extend(label.render_elements(
+                        self.resolve_center(screen_size),
+                        self.style.scale[0],
+                        self.z,
+                        id,
+                    ));
+                }
+            }
+            CanvasContent::ProgressBar(bar) => queue.transparent.extend(bar.render_elements(
+                self.resolve_top_left(screen_size),
+                self.scaled_size(),
+                self.z,
+                id,
+            )),
+        }
+    }
+}
+
+impl CanvasText {
+    fn from_lua_table(table: &mlua::Table, key: &str, fonts: &HashMap<String, Font>) -> Self {
+        let text: String = table.get(key).unwrap_or_default();
+        let font: String = table.get("font").unwrap_or("default".to_string());
+        let options = TextLayoutOptions::from_lua_table(table);
+        let mesh = match fonts.get(&font) {
+            Some(f) => layout_text(f, &text, &options),
+            None => {
+                println!("Font not loaded for canvas text: {}", font);
+                TextMesh::default()
+            }
+        };
+        Self {
+            text,
+            font,
+            options,
+            color: text_color_from_lua(table),
+            mesh,
+        }
+    }
+
+    fn set_text(&mut self, text: &str, fonts: &HashMap<String, Font>) -> bool {
+        let font = match fonts.get(&self.font) {
+            Some(font) => font,
+            None => return false,
+        };
+        self.text = text.to_string();
+        self.mesh = layout_text(font, &self.text, &self.options);
+        true
+    }
+
+    /// Centers the laid out block on `center` (static camera space).
+    fn render_elements(
+        &self,
+        center: [f32; 2],
+        scale: f32,
+        z: f32,
+        id: Entity,
+    ) -> Vec<RenderElement2D<'static>> {
+        self.mesh.render_elements(
+            [
+                center[0] - self.mesh.size[0] * scale * 0.5,
+                center[1] + self.mesh.size[1] * scale * 0.5,
+            ],
+            scale,
+            self.color,
+            RenderLayer::Overhead,
+            z,
+            id,
+        )
+    }
+}
+
+impl CanvasScene {
+    /// Every sprite sheet used by this scene and its nested scenes.
+    pub fn sprite_sheets(&self) -> Vec<String> {
+        let mut sheets: Vec<String> = self
+            .elements
+            .values()
+            .filter_map(|element| match &element.content {
+                CanvasContent::Sprite { sprite_sheet, .. } => Some(sprite_sheet.clone()),
+                CanvasContent::Text(_) => None,
+                CanvasContent::Panel { panel, .. } => Some(panel.texture_id.clone()),
+                CanvasContent::Button { button, .. } => Some(button.panel.texture_id.clone()),
+                CanvasContent::ProgressBar(bar) => Some(bar.panel.texture_id.clone()),
+            })
+            .collect();
+        for scene in self.scenes.values() {
+            sheets.extend(scene.sprite_sheets());
+        }
+        sheets
+    }
+
+    fn set_scene_active(&mut self, id: Entity, active: bool) -> bool {
+        if self.scenes.contains_key(&id) {
+            set_active(&mut self.active_scenes, id, active);
+            return true;
+        }
+        self.scenes
+            .values_mut()
+            .any(|scene| scene.set_scene_active(id, active))
+    }
+
+    fn set_element_active(&mut self, id: Entity, active: bool) -> bool {
+        if self.elements.contains_key(&id) {
+            set_active(&mut self.active_elements, id, active);
+            return true;
+        }
+        self.scenes
+            .values_mut()
+            .any(|scene| scene.set_element_active(id, active))
+    }
+
+    fn set_text(&mut self, id: Entity, text: &str, fonts: &HashMap<String, Font>) -> bool {
+        if let Some(element) = self.elements.get_mut(&id) {
+            return element.set_text(text, fonts);
+        }
+        self.scenes
+            .values_mut()
+            .any(|scene| scene.set_text(id, text, fonts))
+    }
+
+    fn element_mut(&mut self, id: Entity) -> Option<&mut CanvasElement> {
+        if self.elements.contains_key(&id) {
+            return self.elements.get_mut(&id);
+        }
+        self.scenes
+            .values_mut()
+            .find_map(|scene| scene.element_mut(id))
+    }
+
+    fn for_each_element_mut(&mut self, f: &mut dyn FnMut(&mut CanvasElement)) {
+        for element in self.elements.values_mut() {
+            f(element);
+        }
+        for scene in self.scenes.values_mut() {
+            scene.for_each_element_mut(f);
+        }
+    }
+
+    fn collect_active_buttons(&self, screen_size: [f32; 2], out: &mut Vec<ButtonBounds>) {
+        for id in self.active_elements.iter() {
+            if let Some(element) = self.elements.get(id) {
+                if let CanvasContent::Button { .. } = element.content {
+                    let size = element.scaled_size();
+                    out.push(ButtonBounds {
+                        id: *id,
+                        center: element.resolve_center(screen_size),
+                        half_size: [size[0] * 0.5, size[1] * 0.5],
+                        z: element.z,
+                    });
+                }
+            }
+        }
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get(id) {
+                scene.collect_active_buttons(screen_size, out);
+            }
+        }
+    }
+
+    // active children in declaration order, hidden ones take no space
+    fn active_children(&self) -> Vec<CanvasChild> {
+        self.children
+            .iter()
+            .filter(|child| match child {
+                CanvasChild::Element(id) => self.active_elements.contains(id),
+                CanvasChild::Scene(id) => self.active_scenes.contains(id),
+            })
+            .copied()
+            .collect()
+    }
+
+    fn layout_node(&self) -> LayoutNode {
+        let children = self
+            .active_children()
+            .into_iter()
+            .filter_map(|child| match child {
+                CanvasChild::Element(id) => self.elements.get(&id).map(|element| LayoutNode {
+                    style: element.style,
+                    intrinsic: element.intrinsic_size(),
+                    children: Vec::new(),
+                }),
+                CanvasChild::Scene(id) => self.scenes.get(&id).map(|scene| scene.layout_node()),
+            })
+            .collect();
+        LayoutNode {
+            style: self.style,
+            intrinsic: [0.0, 0.0],
+            children,
+        }
+    }
+
+    // walks the same children as `layout_node`, so results line up by index
+    fn apply_layout(&mut self, resolved: &ResolvedLayout) {
+        for (child, layout) in self
+            .active_children()
+            .into_iter()
+            .zip(resolved.children.iter())
+        {
+            match child {
+                CanvasChild::Element(id) => {
+                    if let Some(element) = self.elements.get_mut(&id) {
+                        element.rect = layout.rect;
+                    }
+                }
+                CanvasChild::Scene(id) => {
+                    if let Some(scene) = self.scenes.get_mut(&id) {
+                        scene.apply_layout(layout);
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_animations(&mut self, dt: f32) {
+        for id in self.active_elements.iter() {
+            if let Some(CanvasElement {
+                content: CanvasContent::Sprite { animation, .. },
+                ..
+            }) = self.elements.get_mut(id)
+            {
+                animation.advance(&CANVAS_STATE, dt);
+            }
+        }
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get_mut(id) {
+                scene.update_animations(dt);
+            }
+        }
+    }
+
+    fn extract_into<'a>(&'a self, screen_size: [f32; 2], queue: &mut RenderQueue2D<'a>) {
+        for id in self.active_elements.iter() {
+            if let Some(element) = self.elements.get(id) {
+                element.extract_into(*id, screen_size, queue);
+            }
+        }
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get(id) {
+                scene.extract_into(screen_size, queue);
+            }
+        }
+    }
+}
+
+fn set_active(active_list: &mut Vec<Entity>, id: Entity, active: bool) {
+    let position = active_list.iter().position(|e| *e == id);
+    match (position, active) {
+        (None, true) => active_list.push(id),
+        (Some(index), false) => {
+            active_list.remove(index);
+        }
+        _ => {}
+    }
 }
 
 impl Canvas {
-    pub fn new() -> Self {
+    pub fn new(width: u32, height: u32) -> Self {
         Self {
             next_id: 0,
             scenes: HashMap::new(),
             active_scenes: Vec::new(),
+            screen_size: [width as f32, height as f32],
+            scene_regions: HashMap::new(),
+            texts: Vec::new(),
+            focused: None,
+            pressed: None,
+            layout_dirty: true,
         }
     }
 
@@ -47,11 +488,309 @@ impl Canvas {
         return e;
     }
 
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.screen_size = [width as f32, height as f32];
+        self.layout_dirty = true;
+    }
+
     pub fn add_scene(&mut self, entity: Entity, scene: (CanvasScene, bool)) {
         self.scenes.insert(entity.clone(), scene.0);
         if scene.1 {
             self.active_scenes.push(entity.clone());
         }
+        self.layout_dirty = true;
+    }
+
+    /// Lays a top level scene out in `region`, `[x, y, width, height]` in window pixels,
+    /// instead of the whole window. None gives it the window back.
+    pub fn set_scene_region(&mut self, id: Entity, region: Option<[f32; 4]>) {
+        let region = region.map(|[x, y, w, h]| Rect::new(x, y, w, h));
+        if self.scene_regions.get(&id).copied() != region {
+            match region {
+                Some(region) => self.scene_regions.insert(id, region),
+                None => self.scene_regions.remove(&id),
+            };
+            self.layout_dirty = true;
+        }
+    }
+
+    /// Shows or hides a scene anywhere in the canvas tree. Returns false for unknown ids.
+    pub fn set_scene_active(&mut self, id: Entity, active: bool) -> bool {
+        let found = if self.scenes.contains_key(&id) {
+            set_active(&mut self.active_scenes, id, active);
+            true
+        } else {
+            self.scenes
+                .values_mut()
+                .any(|scene| scene.set_scene_active(id, active))
+        };
+        self.layout_dirty |= found;
+        found
+    }
+
+    pub fn set_element_active(&mut self, id: Entity, active: bool) -> bool {
+        let found = self
+            .scenes
+            .values_mut()
+            .any(|scene| scene.set_element_active(id, active));
+        self.layout_dirty |= found;
+        found
+    }
+
+    /// Re-lays out a text element. Returns false for unknown ids, sprite elements or missing fonts.
+    pub fn set_text(&mut self, id: Entity, text: &str, fonts: &HashMap<String, Font>) -> bool {
+        let found = self
+            .scenes
+            .values_mut()
+            .any(|scene| scene.set_text(id, text, fonts));
+        self.layout_dirty |= found;
+        found
+    }
+
+    /// Recomputes every rectangle when something that affects layout changed since the last call.
+    pub fn update_layout(&mut self) {
+        if !self.layout_dirty {
+            return;
+        }
+        let screen = Rect::new(0.0, 0.0, self.screen_size[0], self.screen_size[1]);
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get_mut(id) {
+                let parent = self.scene_regions.get(id).copied().unwrap_or(screen);
+                let resolved = compute_layout(&scene.layout_node(), parent);
+                scene.apply_layout(&resolved);
+            }
+        }
+        self.layout_dirty = false;
+    }
+
+    /// Queues text for this frame only. `position` is in pixels from the top left, y down.
+    pub fn draw_text(
+        &mut self,
+        mesh: TextMesh,
+        position: [f32; 2],
+        anchor: [f32; 2],
+        scale: f32,
+        color: [f32; 4],
+    ) {
+        self.texts.push(WorldText {
+            mesh,
+            position: [
+                position[0] - self.screen_size[0] * 0.5,
+                self.screen_size[1] * 0.5 - position[1],
+            ],
+            anchor,
+            scale,
+            color,
+        });
+    }
+
+    pub fn clear_texts(&mut self) {
+        self.texts.clear();
+    }
+
+    fn element_mut(&mut self, id: Entity) -> Option<&mut CanvasElement> {
+        self.scenes
+            .values_mut()
+            .find_map(|scene| scene.element_mut(id))
+    }
+
+    fn active_buttons(&self) -> Vec<ButtonBounds> {
+        let mut buttons = Vec::new();
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get(id) {
+                scene.collect_active_buttons(self.screen_size, &mut buttons);
+            }
+        }
+        buttons
+    }
+
+    // window pixels (y down) to the static camera's space
+    fn to_canvas_space(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            point[0] - self.screen_size[0] * 0.5,
+            self.screen_size[1] * 0.5 - point[1],
+        ]
+    }
+
+    // topmost active button under a window position
+    fn button_at(&self, point: [f32; 2]) -> Option<Entity> {
+        let point = self.to_canvas_space(point);
+        self.active_buttons()
+            .into_iter()
+            .filter(|bounds| bounds.contains(point))
+            .max_by(|a, b| a.z.partial_cmp(&b.z).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|bounds| bounds.id)
+    }
+
+    pub fn has_focusable(&self) -> bool {
+        !self.active_buttons().is_empty()
+    }
+
+    pub fn is_pressing(&self) -> bool {
+        self.pressed.is_some()
+    }
+
+    /// Moves focus to a button, or clears it with `None`. Returns false for ids that are not buttons.
+    pub fn focus(&mut self, id: Option<Entity>) -> bool {
+        if let Some(id) = id {
+            if self.element_mut(id).and_then(|e| e.button_mut()).is_none() {
+                return false;
+            }
+        }
+        if let Some(previous) = self.focused.take() {
+            if let Some(button) = self.element_mut(previous).and_then(|e| e.button_mut()) {
+                button.focused = false;
+            }
+        }
+        if let Some(id) = id {
+            if let Some(button) = self.element_mut(id).and_then(|e| e.button_mut()) {
+                button.focused = true;
+            }
+        }
+        self.focused = id;
+        true
+    }
+
+    /// Hovering a button focuses it, so mouse and keyboard share a single highlight.
+    pub fn pointer_moved(&mut self, point: [f32; 2]) {
+        let hovered = self.button_at(point);
+        if let Some(pressed) = self.pressed {
+            // pressed buttons only look pressed while the cursor is still on them
+            if let Some(button) = self.element_mut(pressed).and_then(|e| e.button_mut()) {
+                button.pressed = hovered == Some(pressed);
+            }
+            return;
+        }
+        if hovered.is_some() && hovered != self.focused {
+            self.focus(hovered);
+        }
+    }
+
+    /// Returns true when the press landed on a button and should not reach the game.
+    pub fn pointer_pressed(&mut self, point: [f32; 2]) -> bool {
+        let id = match self.button_at(point) {
+            Some(id) => id,
+            None => return false,
+        };
+        self.focus(Some(id));
+        if let Some(button) = self.element_mut(id).and_then(|e| e.button_mut()) {
+            button.pressed = true;
+        }
+        self.pressed = Some(id);
+        true
+    }
+
+    /// Clicks only count when the release happens over the button that was pressed.
+    pub fn pointer_released(&mut self, point: [f32; 2]) -> Option<UiClick> {
+        let id = self.pressed.take()?;
+        let released_on = self.button_at(point);
+        let button = self.element_mut(id).and_then(|e| e.button_mut())?;
+        button.pressed = false;
+        if released_on != Some(id) {
+            return None;
+        }
+        Some(UiClick {
+            id,
+            on_click: button.on_click.clone(),
+        })
+    }
+
+    /// Moves focus to the closest active button in `direction` (y up).
+    /// With nothing focused the top-left button is picked.
+    pub fn navigate(&mut self, direction: [f32; 2]) -> bool {
+        let buttons = self.active_buttons();
+        let current = self
+            .focused
+            .and_then(|id| buttons.iter().find(|bounds| bounds.id == id).copied());
+
+        let next = match current {
+            None => buttons
+                .iter()
+                .min_by(|a, b| {
+                    (b.center[1], a.center[0])
+                        .partial_cmp(&(a.center[1], b.center[0]))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|bounds| bounds.id),
+            Some(current) => buttons
+                .iter()
+                .filter(|bounds| bounds.id != current.id)
+                .filter_map(|bounds| {
+                    let dx = bounds.center[0] - current.center[0];
+                    let dy = bounds.center[1] - current.center[1];
+                    let along = dx * direction[0] + dy * direction[1];
+                    if along <= 0.0 {
+                        return None;
+                    }
+                    // prefer buttons straight ahead over closer ones off to the side
+                    let across = (dx * direction[1] - dy * direction[0]).abs();
+                    Some((along + across * 2.0, bounds.id))
+                })
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|(_, id)| id),
+        };
+
+        match next {
+            Some(id) => self.focus(Some(id)),
+            None => false,
+        }
+    }
+
+    pub fn activate_focused(&mut self) -> Option<UiClick> {
+        let id = self.focused?;
+        if !self.active_buttons().iter().any(|bounds| bounds.id == id) {
+            return None;
+        }
+        let button = self.element_mut(id).and_then(|e| e.button_mut())?;
+        Some(UiClick {
+            id,
+            on_click: button.on_click.clone(),
+        })
+    }
+
+    pub fn set_progress(&mut self, id: Entity, value: f32) -> bool {
+        match self.element_mut(id).and_then(|e| e.progress_bar_mut()) {
+            Some(bar) => {
+                bar.value = value.clamp(0.0, 1.0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn bind_health(&mut self, id: Entity, entity: Option<Entity>) -> bool {
+        match self.element_mut(id).and_then(|e| e.progress_bar_mut()) {
+            Some(bar) => {
+                bar.health_of = entity;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pulls bound progress bars from their entity's health.
+    pub fn sync_health(&mut self, health_bars: &HashMap<Entity, HealthComponent>) {
+        for scene in self.scenes.values_mut() {
+            scene.for_each_element_mut(&mut |element| {
+                if let Some(bar) = element.progress_bar_mut() {
+                    if let Some(health) = bar.health_of.and_then(|e| health_bars.get(&e)) {
+                        bar.value = if health.total == 0 {
+                            0.0
+                        } else {
+                            health.current as f32 / health.total as f32
+                        };
+                    }
+                }
+            });
+        }
+    }
+
+    pub fn update_animations(&mut self, dt: f32) {
+        for id in self.active_scenes.iter() {
+            if let Some(scene) = self.scenes.get_mut(id) {
+                scene.update_animations(dt);
+            }
+        }
     }
 
     pub fn extract_render_queue_2d(&self) -> RenderQueue2D {
@@ -61,26 +800,26 @@ impl Canvas {
         };
 
         for entity in self.active_scenes.iter() {
-            for (_, element) in self.scenes.get(entity).unwrap().elements.iter() {
-                queue.transparent.push(RenderElement2D {
-                    shape: &Shape2D::Rectangle {
-                        half_extents: Vector2 { x: 1.0, y: 1.0 },
-                    },
-                    position: [0.0, 0.0],
-                    size: [1.0, 1.0],
-                    z_order: 0.0,
-                    texture_id: element.sprite_sheet.clone(),
-                    uv_coords: element.animation.current_frame.uv_coords,
-                });
+            if let Some(scene) = self.scenes.get(entity) {
+                scene.extract_into(self.screen_size, &mut queue);
             }
         }
 
+        // immediate text goes on top of every scene
+        for text in self.texts.iter() {
+            queue.transparent.extend(text.render_elements(Entity::MAX));
+        }
+
         queue
     }
 }
 
 // Lua
-pub fn parse_scene_from_lua(table: mlua::Table, canvas: &mut Canvas) -> (CanvasScene, bool) {
+pub fn parse_scene_from_lua(
+    table: mlua::Table,
+    canvas: &mut Canvas,
+    fonts: &HashMap<String, Font>,
+) -> (CanvasScene, bool) {
     let elements_table: mlua::Table = table.get("elements").unwrap();
     let scenes_table: mlua::Table = table.get("scenes").unwrap();
 
@@ -88,23 +827,37 @@ pub fn parse_scene_from_lua(table: mlua::Table, canvas: &mut Canvas) -> (CanvasS
     let mut elements = HashMap::new();
     let mut active_elements = Vec::<Entity>::new();
     let mut active_scenes = Vec::<Entity>::new();
+    let mut children = Vec::new();
 
-    for val in elements_table.sequence_values() {
+    for val in elements_table.sequence_values::<mlua::Table>() {
+        let val = val.unwrap();
+        let e_tup = match parse_element_from_lua(&val, fonts) {
+            Ok(e_tup) => e_tup,
+            Err(e) => {
+                println!("Skipping canvas element: {}", e);
+                continue;
+            }
+        };
         let id = canvas.new_entity();
-        let e_tup = parse_element_from_lua(val.unwrap());
+        // hand the id back so Lua can toggle nested elements later
+        let _ = val.set("id", id);
         if e_tup.1 {
             active_elements.push(id.clone());
         }
         elements.insert(id, e_tup.0);
+        children.push(CanvasChild::Element(id));
     }
 
-    for val in scenes_table.sequence_values() {
+    for val in scenes_table.sequence_values::<mlua::Table>() {
+        let val = val.unwrap();
         let id = canvas.new_entity();
-        let s_tup = parse_scene_from_lua(val.unwrap(), canvas);
+        let _ = val.set("id", id);
+        let s_tup = parse_scene_from_lua(val, canvas, fonts);
         if s_tup.1 {
             active_scenes.push(id.clone());
         }
         scenes.insert(id, s_tup.0);
+        children.push(CanvasChild::Scene(id));
     }
 
     (
@@ -113,42 +866,58 @@ pub fn parse_scene_from_lua(table: mlua::Table, canvas: &mut Canvas) -> (CanvasS
             elements,
             active_scenes,
             active_elements,
+            // scenes fill their parent unless told otherwise
+            style: LayoutStyle::from_lua_table(&table, Length::Percent(1.0)),
+            children,
         },
         table.get("initially_active").unwrap_or(false),
     )
 }
 
-fn parse_element_from_lua(table: mlua::Table) -> (CanvasElement, bool) {
-    println!("{:?}", LuaExtendedExecutor::pretty_print_table(&table, 0));
-    let first: mlua::Table = table.get("animations").unwrap();
-    println!("{:?}", LuaExtendedExecutor::pretty_print_table(&first, 0));
-    let animation = Animation::from_lua_table(first.get(0).unwrap());
-    let animations = HashMap::from([(ActionState::Custom(0), animation.0.clone())]);
+fn parse_element_from_lua(
+    table: &mlua::Table,
+    fonts: &HashMap<String, Font>,
+) -> mlua::Result<(CanvasElement, bool)> {
+    let widget: String = table.get("widget").unwrap_or_default();
 
-    (
+    let content = match widget.as_str() {
+        "panel" => CanvasContent::Panel {
+            panel: NineSlice::from_lua_table(table)?,
+            tint: LuaExtendedExecutor::table_to_color(table, "color", [1.0, 1.0, 1.0, 1.0]),
+        },
+        "button" => CanvasContent::Button {
+            button: Button::from_lua_table(table)?,
+            label: table
+                .contains_key("label")
+                .unwrap_or(false)
+                .then(|| CanvasText::from_lua_table(table, "label", fonts)),
+        },
+        "progress_bar" => CanvasContent::ProgressBar(ProgressBar::from_lua_table(table)?),
+        _ if table.contains_key("text").unwrap_or(false) => {
+            CanvasContent::Text(CanvasText::from_lua_table(table, "text", fonts))
+        }
+        _ => {
+            let first: mlua::Table = table.get("animations").unwrap();
+            let animation = Animation::from_lua_table(first.get(0).unwrap());
+            let animations = HashMap::from([(CANVAS_STATE, animation.0.clone())]);
+            CanvasContent::Sprite {
+                sprite_sheet: animation.1,
+                animation: AnimationComponent::new(animations, &CANVAS_STATE),
+            }
+        }
+    };
+
+    Ok((
         CanvasElement {
-            position: Vector2 {
-                x: table.get("position_x").unwrap(),
-                y: table.get("position_y").unwrap(),
-            },
-            scale: Vector2 {
-                x: table.get("scale_x").unwrap(),
-                y: table.get("scale_y").unwrap(),
-            },
+            // elements fit their content unless given a size
+            style: LayoutStyle::from_lua_table(table, Length::Auto),
+            rect: Rect::default(),
+            z: table.get("z").unwrap_or(0.0),
             shape: Shape2D::Rectangle {
-                half_extents: Vector2 {
-                    x: table.get("width").unwrap(),
-                    y: table.get("height").unwrap(),
-                },
-            },
-            sprite_sheet: animation.1,
-            animation: AnimationComponent {
-                animations,
-                current_frame: animation.0.frames[0].clone(),
-                current_frame_index: 1,
-                frame_timer: 0.0,
+                half_extents: Vector2 { x: 0.5, y: 0.5 },
             },
+            content,
         },
         table.get("initially_active").unwrap_or(false),
-    )
+    ))
 }
@@ -0,0 +1,195 @@
+use crate::{
+    components_systems::{Entity, RenderLayer},
+    graphics_2d::{RenderElement2D, UNIT_RECTANGLE},
+    lua_scriptor::LuaExtendedExecutor,
+};
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// A texture split into a 3x3 grid: corners keep their size, edges and center stretch.
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    pub texture_id: String,
+    texture_size: [f32; 2],
+    border: [f32; 4],  // left, top, right, bottom in texture pixels
+    border_scale: f32, // screen pixels per texture pixel for the border
+}
+
+#[derive(Debug)]
+pub struct Button {
+    pub panel: NineSlice,
+    pub colors: ButtonColors,
+    pub on_click: Option<mlua::Function>,
+    pub focused: bool,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ButtonColors {
+    pub normal: [f32; 4],
+    pub focused: [f32; 4],
+    pub pressed: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct ProgressBar {
+    pub panel: NineSlice,
+    pub value: f32, // 0..1
+    pub background: [f32; 4],
+    pub fill: [f32; 4],
+    pub health_of: Option<Entity>, // follows this entity's HealthComponent when set
+}
+
+impl NineSlice {
+    /// Fails when `texture`, `texture_width` or `texture_height` is missing.
+    pub fn from_lua_table(table: &mlua::Table) -> mlua::Result<Self> {
+        let required = |key: &str| mlua::Error::runtime(format!("canvas widgets need a {}", key));
+        let border: f32 = table.get("border").unwrap_or(0.0);
+        Ok(Self {
+            texture_id: table.get("texture").map_err(|_| required("texture"))?,
+            texture_size: [
+                table
+                    .get("texture_width")
+                    .map_err(|_| required("texture_width"))?,
+                table
+                    .get("texture_height")
+                    .map_err(|_| required("texture_height"))?,
+            ],
+            border: [
+                table.get("border_left").unwrap_or(border),
+                table.get("border_top").unwrap_or(border),
+                table.get("border_right").unwrap_or(border),
+                table.get("border_bottom").unwrap_or(border),
+            ],
+            border_scale: table.get("border_scale").unwrap_or(1.0),
+        })
+    }
+
+    /// `top_left` is in the static camera's space (y up), `size` in pixels.
+    pub fn render_elements(
+        &self,
+        top_left: [f32; 2],
+        size: [f32; 2],
+        tint: [f32; 4],
+        z_order: f32,
+        entity: Entity,
+    ) -> Vec<RenderElement2D<'static>> {
+        let [tw, th] = self.texture_size;
+        let [left, top, right, bottom] = self.border;
+
+        // screen space cuts, borders shrink together when the element is smaller than them
+        let fit_x = (size[0] / ((left + right) * self.border_scale)).min(1.0);
+        let fit_y = (size[1] / ((top + bottom) * self.border_scale)).min(1.0);
+        let (sl, sr) = (
+            left * self.border_scale * fit_x,
+            right * self.border_scale * fit_x,
+        );
+        let (st, sb) = (
+            top * self.border_scale * fit_y,
+            bottom * self.border_scale * fit_y,
+        );
+        let columns = [(0.0, sl), (sl, size[0] - sr), (size[0] - sr, size[0])];
+        let rows = [(0.0, st), (st, size[1] - sb), (size[1] - sb, size[1])];
+
+        // texture cuts in pixels, top-left origin
+        let u_cuts = [(0.0, left), (left, tw - right), (tw - right, tw)];
+        let v_cuts = [(0.0, top), (top, th - bottom), (th - bottom, th)];
+
+        let mut elements = Vec::with_capacity(9);
+        for (row, (y0, y1)) in rows.iter().enumerate() {
+            for (column, (x0, x1)) in columns.iter().enumerate() {
+                let (w, h) = (x1 - x0, y1 - y0);
+                if w <= 0.0 || h <= 0.0 {
+                    continue;
+                }
+                let u0 = u_cuts[column].0 / tw;
+                let u1 = u_cuts[column].1 / tw;
+                let v1 = 1.0 - v_cuts[row].0 / th;
+                let v0 = 1.0 - v_cuts[row].1 / th;
+
+                elements.push(RenderElement2D {
+                    shape: &UNIT_RECTANGLE,
+                    position: [top_left[0] + x0 + w * 0.5, top_left[1] - y0 - h * 0.5],
+                    size: [w, h],
+                    layer: RenderLayer::Overhead,
+                    z_order,
+                    entity,
+                    texture_id: self.texture_id.clone(),
+                    uv_coords: [[u0, v1], [u1, v1], [u1, v0], [u0, v0]],
+                    rotation: 0.0,
+                    tint,
+                    flash: [0.0, 0.0, 0.0, 0.0],
+                    opacity: 1.0,
+                    material: None,
+                });
+            }
+        }
+        elements
+    }
+}
+
+impl Button {
+    pub fn from_lua_table(table: &mlua::Table) -> mlua::Result<Self> {
+        let normal = LuaExtendedExecutor::table_to_color(table, "normal_color", WHITE);
+        Ok(Self {
+            panel: NineSlice::from_lua_table(table)?,
+            colors: ButtonColors {
+                normal,
+                focused: LuaExtendedExecutor::table_to_color(table, "focused_color", normal),
+                pressed: LuaExtendedExecutor::table_to_color(table, "pressed_color", normal),
+            },
+            on_click: table.get("on_click").ok(),
+            focused: false,
+            pressed: false,
+        })
+    }
+
+    pub fn tint(&self) -> [f32; 4] {
+        if self.pressed {
+            self.colors.pressed
+        } else if self.focused {
+            self.colors.focused
+        } else {
+            self.colors.normal
+        }
+    }
+}
+
+impl ProgressBar {
+    pub fn from_lua_table(table: &mlua::Table) -> mlua::Result<Self> {
+        Ok(Self {
+            panel: NineSlice::from_lua_table(table)?,
+            value: table.get::<f32>("value").unwrap_or(1.0).clamp(0.0, 1.0),
+            background: LuaExtendedExecutor::table_to_color(
+                table,
+                "background_color",
+                [0.2, 0.2, 0.2, 1.0],
+            ),
+            fill: LuaExtendedExecutor::table_to_color(table, "fill_color", WHITE),
+            health_of: table.get("health_of").ok(),
+        })
+    }
+
+    pub fn render_elements(
+        &self,
+        top_left: [f32; 2],
+        size: [f32; 2],
+        z_order: f32,
+        entity: Entity,
+    ) -> Vec<RenderElement2D<'static>> {
+        let mut elements =
+            self.panel
+                .render_elements(top_left, size, self.background, z_order, entity);
+        if self.value > 0.0 {
+            // same sort key as the background, the stable sort keeps it on top
+            elements.extend(self.panel.render_elements(
+                top_left,
+                [size[0] * self.value, size[1]],
+                self.fill,
+                z_order,
+                entity,
+            ));
+        }
+        elements
+    }
+}
@@ -1,3 +1,4 @@
+mod spatial_grid;
 mod world;
 
 pub use world::{AreaInfo, AreaRole, World};
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::components_systems::Entity;
+
+type CellRange = [i32; 4]; // min x, min y, max x, max y, inclusive
+type CellIter<'a> = Box<dyn Iterator<Item = ((i32, i32), &'a Vec<Entity>)> + 'a>;
+
+/// Entities bucketed into the cells of a uniform grid that their bounds overlap, so a
+/// rectangle query only looks at the entities near it.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+    ranges: HashMap<Entity, CellRange>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            ranges: HashMap::new(),
+        }
+    }
+
+    /// Files `entity` under `bounds`, `[min x, min y, max x, max y]`. Only touches the cells
+    /// when the entity moved into different ones.
+    pub fn insert(&mut self, entity: Entity, bounds: [f32; 4]) {
+        let range = self.cell_range(bounds);
+        match self.ranges.get(&entity) {
+            Some(previous) if *previous == range => return,
+            Some(_) => self.remove(entity),
+            None => {}
+        }
+        for cell in cells_in(range) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+        self.ranges.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(range) = self.ranges.remove(&entity) else {
+            return;
+        };
+        for cell in cells_in(range) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|e| *e != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Drops every entity `keep` returns false for.
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        let stale: Vec<Entity> = self
+            .ranges
+            .keys()
+            .copied()
+            .filter(|entity| !keep(*entity))
+            .collect();
+        for entity in stale {
+            self.remove(entity);
+        }
+    }
+
+    /// Every entity in a cell `rect` overlaps, once each. The bounds themselves aren't kept,
+    /// so callers test them if they need an exact answer.
+    pub fn query(&self, rect: [f32; 4], found: &mut Vec<Entity>) {
+        let query = self.cell_range(rect);
+        let query_cells = (query[2] - query[0] + 1) as i64 * (query[3] - query[1] + 1) as i64;
+        // far zoomed out views cover more cells than are occupied, walk the occupied ones then
+        self.query_cells(query, query_cells > self.cells.len() as i64, found);
+    }
+
+    fn query_cells(&self, query: CellRange, walk_occupied: bool, found: &mut Vec<Entity>) {
+        let cells: CellIter = if walk_occupied {
+            Box::new(
+                self.cells
+                    .iter()
+                    .map(|(cell, entities)| (*cell, entities))
+                    .filter(move |((x, y), _)| {
+                        (query[0]..=query[2]).contains(x) && (query[1]..=query[3]).contains(y)
+                    }),
+            )
+        } else {
+            Box::new(
+                cells_in(query)
+                    .filter_map(|cell| self.cells.get(&cell).map(|entities| (cell, entities))),
+            )
+        };
+        for ((x, y), entities) in cells {
+            for entity in entities {
+                // an entity spanning several cells is reported by the first one the query shares
+                let range = self.ranges[entity];
+                if (x, y) == (range[0].max(query[0]), range[1].max(query[1])) {
+                    found.push(*entity);
+                }
+            }
+        }
+    }
+
+    fn cell_range(&self, bounds: [f32; 4]) -> CellRange {
+        let cell = |value: f32| (value / self.cell_size).floor() as i32;
+        [
+            cell(bounds[0]),
+            cell(bounds[1]),
+            cell(bounds[2]),
+            cell(bounds[3]),
+        ]
+    }
+}
+
+fn cells_in(range: CellRange) -> impl Iterator<Item = (i32, i32)> {
+    (range[1]..=range[3]).flat_map(move |y| (range[0]..=range[2]).map(move |x| (x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(grid: &SpatialGrid, rect: [f32; 4]) -> Vec<Entity> {
+        let mut found = Vec::new();
+        grid.query(rect, &mut found);
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn entities_spanning_several_cells_are_found_once() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(1, [5.0, 5.0, 25.0, 15.0]); // six cells
+        grid.insert(2, [-3.0, -3.0, -1.0, -1.0]);
+        assert_eq!(grid.cells.len(), 7);
+
+        assert_eq!(query(&grid, [0.0, 0.0, 30.0, 30.0]), vec![1]);
+        // starting inside the entity, past its first cell
+        assert_eq!(query(&grid, [12.0, 12.0, 40.0, 40.0]), vec![1]);
+        assert_eq!(query(&grid, [-5.0, -5.0, 100.0, 100.0]), vec![1, 2]);
+        assert_eq!(query(&grid, [50.0, 50.0, 60.0, 60.0]), vec![]);
+    }
+
+    #[test]
+    fn moving_to_other_cells_empties_the_old_ones() {
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(1, [0.0, 0.0, 15.0, 5.0]);
+        grid.insert(2, [1.0, 1.0, 2.0, 2.0]);
+        grid.insert(1, [100.0, 100.0, 105.0, 105.0]);
+
+        assert!(!grid.cells.contains_key(&(1, 0)));
+        assert_eq!(grid.cells[&(0, 0)], vec![2]);
+        assert_eq!(query(&grid, [0.0, 0.0, 20.0, 20.0]), vec![2]);
+        assert_eq!(query(&grid, [95.0, 95.0, 110.0, 110.0]), vec![1]);
+
+        grid.remove(2);
+        grid.retain(|entity| entity != 1);
+        assert!(grid.cells.is_empty());
+        assert!(grid.ranges.is_empty());
+    }
+
+    #[test]
+    fn both_query_walks_find_the_same_entities() {
+        let mut grid = SpatialGrid::new(4.0);
+        // a deterministic scatter of small and large bounds, some off the negative side
+        let mut seed = 7_u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as f32 / 65_536.0
+        };
+        for entity in 0..60 {
+            let (x, y) = (next() * 80.0 - 40.0, next() * 80.0 - 40.0);
+            let size = if entity % 5 == 0 { 12.0 } else { 1.5 };
+            grid.insert(entity, [x, y, x + size, y + size]);
+        }
+
+        for rect in [
+            [-50.0, -50.0, 50.0, 50.0],
+            [-6.0, -2.0, 9.0, 3.0],
+            [0.0, 0.0, 0.5, 0.5],
+            [-39.0, 10.0, -20.0, 40.0],
+            [1000.0, 1000.0, 1010.0, 1010.0],
+        ] {
+            let range = grid.cell_range(rect);
+            let mut occupied = Vec::new();
+            grid.query_cells(range, true, &mut occupied);
+            let mut covered = Vec::new();
+            grid.query_cells(range, false, &mut covered);
+            occupied.sort();
+            covered.sort();
+            assert_eq!(occupied, covered, "{:?}", rect);
+
+            // nothing reported twice, and everything whose cells touch the query is there
+            let mut unique = covered.clone();
+            unique.dedup();
+            assert_eq!(unique, covered, "{:?}", rect);
+            let expected: Vec<Entity> = (0..60)
+                .filter(|entity| {
+                    let cells = grid.ranges[entity];
+                    cells[0] <= range[2]
+                        && cells[2] >= range[0]
+                        && cells[1] <= range[3]
+                        && cells[3] >= range[1]
+                })
+                .collect();
+            assert_eq!(covered, expected, "{:?}", rect);
+        }
+    }
+}
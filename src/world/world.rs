@@ -6,11 +6,18 @@ use crate::{
     components_systems::{
         physics2d::Point2D,
         physics_2d::{Area2D, FlipComponent, PhysicsBody2D, Transform2D},
-        ActionStateComponent, AnimationComponent, Entity, HealthComponent, SpriteSheetComponent,
+        ActionStateComponent, AnimationComponent, AnimationGraph, Entity, FacingComponent,
+        HealthComponent, Light2D, Material, MaterialComponent, ParticleEmitter, PostEffect,
+        RenderLayer, RenderLayerComponent, SortMode, SpriteColorComponent, SpriteSheetComponent,
     },
     graphics_2d::{RenderElement2D, RenderQueue2D},
+    text::WorldText,
+    world::spatial_grid::SpatialGrid,
 };
 
+// world units per cell of the sprite index, a few skeletons wide
+const SPRITE_INDEX_CELL_SIZE: f32 = 8.0;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AreaRole {
     Physics,
@@ -44,9 +51,17 @@ pub struct WorldDebug {
 pub struct World {
     next_id: u32,
     pub flips: HashMap<Entity, FlipComponent>,
+    pub facings: HashMap<Entity, FacingComponent>,
     pub health_bars: HashMap<Entity, HealthComponent>,
     pub animations: HashMap<Entity, AnimationComponent>,
+    pub animation_graphs: HashMap<Entity, AnimationGraph>,
+    pub animation_time_scale: f32, // every entity's clips, 0 freezes them for hit stops
     pub sprite_sheets: HashMap<Entity, SpriteSheetComponent>,
+    pub sprite_colors: HashMap<Entity, SpriteColorComponent>,
+    pub materials: HashMap<String, Material>, // registered by name from Lua
+    pub entity_materials: HashMap<Entity, MaterialComponent>,
+    pub render_layers: HashMap<Entity, RenderLayerComponent>,
+    pub layer_sort_modes: HashMap<RenderLayer, SortMode>,
     pub transforms_2d: HashMap<Entity, Transform2D>,
     pub action_states: HashMap<Entity, ActionStateComponent>,
     pub physics_bodies_2d: HashMap<Entity, PhysicsBody2D>,
@@ -55,10 +70,16 @@ pub struct World {
     pub hurtboxes_2d: HashMap<Entity, HashMap<Entity, Area2D>>,
     pub area_roles: HashMap<Entity, AreaInfo>,
     pub debug: WorldDebug,
+    pub particle_emitters: HashMap<Entity, ParticleEmitter>,
+    pub lights: HashMap<Entity, Light2D>,
+    pub ambient_light: [f32; 3], // what unlit areas are multiplied by, white leaves the scene as drawn
+    pub post_effects: Vec<PostEffect>, // full-screen passes, applied in order
+    pub texts: Vec<WorldText>,   // drawn once, cleared after every frame
 
     // keep this concept hidden for now.
     // interactions should take place through our getters/setters
     parent_area_info: HashMap<Entity, HashMap<AreaRole, ParentAreaInfo>>,
+    sprite_index: SpatialGrid, // animated entities by their bounds, for culling
 }
 
 impl World {
@@ -66,17 +87,34 @@ impl World {
         Self {
             next_id: 0,
             health_bars: HashMap::new(),
+            facings: HashMap::new(),
             transforms_2d: HashMap::new(),
             action_states: HashMap::new(),
             animations: HashMap::new(),
+            animation_graphs: HashMap::new(),
+            animation_time_scale: 1.0,
             physics_bodies_2d: HashMap::new(),
             sprite_sheets: HashMap::new(),
+            sprite_colors: HashMap::new(),
+            materials: HashMap::new(),
+            entity_materials: HashMap::new(),
+            render_layers: HashMap::new(),
+            layer_sort_modes: RenderLayer::ALL
+                .iter()
+                .map(|layer| (*layer, layer.default_sort_mode()))
+                .collect(),
             physical_colliders_2d: HashMap::new(),
             hitboxes_2d: HashMap::new(),
             hurtboxes_2d: HashMap::new(),
             area_roles: HashMap::new(),
             flips: HashMap::new(),
             parent_area_info: HashMap::new(),
+            particle_emitters: HashMap::new(),
+            lights: HashMap::new(),
+            ambient_light: [1.0, 1.0, 1.0],
+            post_effects: Vec::new(),
+            texts: Vec::new(),
+            sprite_index: SpatialGrid::new(SPRITE_INDEX_CELL_SIZE),
             debug: WorldDebug {
                 // this lowers frame rate.
                 // use with minimal objs in scene
@@ -244,49 +282,128 @@ impl World {
         }
     }
 
-    pub fn extract_render_queue_2d(&self) -> RenderQueue2D {
+    /// Re-files the animated entities whose bounds moved into other cells of the sprite index.
+    /// Called right before rendering, after everything that moves them.
+    pub fn update_sprite_index(&mut self) {
+        for (entity, transform) in self.transforms_2d.iter() {
+            if self.animations.contains_key(entity) {
+                self.sprite_index.insert(*entity, sprite_bounds(transform));
+            }
+        }
+        let (animations, transforms) = (&self.animations, &self.transforms_2d);
+        self.sprite_index
+            .retain(|entity| animations.contains_key(&entity) && transforms.contains_key(&entity));
+    }
+
+    /// The sprites overlapping `visible`, `[min x, min y, max x, max y]` in world units, and
+    /// every world text.
+    pub fn extract_render_queue_2d(&self, visible: [f32; 4]) -> RenderQueue2D<'_> {
         let mut transparent = Vec::new();
         let mut opaque = Vec::new();
 
-        for (entity, animation) in self.animations.iter() {
-            if let Some(transform) = self.transforms_2d.get(entity) {
-                let uv_coords = animation.current_frame.uv_coords;
-                let action_animation = &animation.animations[&self
-                    .action_states
-                    .get(&entity)
-                    .expect("Animation not found")
-                    .state];
-                let sprite = self
-                    .sprite_sheets
-                    .get(&action_animation.sprite_sheet_id)
-                    .expect("Sprite Sheets not found");
-
-                let tmp = RenderElement2D {
-                    shape: &transform.shape,
-                    position: transform.position.into(),
-                    size: transform.scale.into(),
-                    z_order: -transform.position[1], // Sort top to bottom: lower y = drawn later
-                    texture_id: sprite.texture_id.clone(),
-                    uv_coords,
-                };
+        let mut candidates = Vec::new();
+        self.sprite_index.query(visible, &mut candidates);
+        for entity in candidates.iter() {
+            let (Some(animation), Some(transform)) =
+                (self.animations.get(entity), self.transforms_2d.get(entity))
+            else {
+                continue;
+            };
+            if !overlaps(sprite_bounds(transform), visible) {
+                continue;
+            }
+            let uv_coords = animation.current_frame.uv_coords;
+            let action_animation = animation
+                .clip(
+                    &self
+                        .action_states
+                        .get(&entity)
+                        .expect("Animation not found")
+                        .state,
+                )
+                .expect("Animation not found");
+            let sprite = self
+                .sprite_sheets
+                .get(&action_animation.sprite_sheet_id)
+                .expect("Sprite Sheets not found");
+
+            let color = self.sprite_colors.get(entity).copied().unwrap_or_default();
+
+            let layer = self.render_layers.get(entity).copied().unwrap_or_default();
+            let material = self.entity_materials.get(entity).and_then(|component| {
+                self.materials
+                    .get(&component.material)
+                    .map(|material| component.resolve(material))
+            });
+            let sort_mode = self.layer_sort_modes[&layer.layer];
+
+            let tmp = RenderElement2D {
+                shape: &transform.shape,
+                position: transform.position.into(),
+                size: transform.scale.into(),
+                layer: layer.layer,
+                z_order: layer.sort_key(sort_mode, transform.position.y, *entity),
+                entity: *entity,
+                texture_id: sprite.texture_id.clone(),
+                uv_coords,
+                rotation: transform.rotation_radians,
+                tint: color.tint,
+                flash: color.flash,
+                opacity: color.opacity,
+                material,
+            };
 
-                if action_animation.is_transparent {
-                    transparent.push(tmp);
-                } else {
-                    opaque.push(tmp);
-                }
+            // faded sprites have to blend with whatever is behind them
+            if action_animation.is_transparent || color.is_translucent() {
+                transparent.push(tmp);
+            } else {
+                opaque.push(tmp);
             }
         }
 
+        for text in self.texts.iter() {
+            transparent.extend(text.render_elements(Entity::MAX));
+        }
+
         RenderQueue2D {
             transparent,
             opaque,
         }
     }
 
+    pub fn clear_texts(&mut self) {
+        self.texts.clear();
+    }
+
+    pub fn set_layer_sort_mode(&mut self, layer: RenderLayer, mode: SortMode) {
+        self.layer_sort_modes.insert(layer, mode);
+    }
+
     pub fn clear_forces(&mut self) {
         for (_entity, body) in self.physics_bodies_2d.iter_mut() {
             body.force_accumulator = Vector2::new(0.0, 0.0);
         }
     }
 }
+
+/// `[min x, min y, max x, max y]` of the sprite drawn for `transform`, loose for rotated ones.
+fn sprite_bounds(transform: &Transform2D) -> [f32; 4] {
+    let [width, height] = transform.get_size();
+    let (half_width, half_height) = if transform.rotation_radians == 0.0 {
+        (width * 0.5, height * 0.5)
+    } else {
+        let radius = (width * width + height * height).sqrt() * 0.5;
+        (radius, radius)
+    };
+    let position = transform.position;
+    [
+        position.x - half_width,
+        position.y - half_height,
+        position.x + half_width,
+        position.y + half_height,
+    ]
+}
+
+fn overlaps(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] <= b[2] && b[0] <= a[2] && a[1] <= b[3] && b[1] <= a[3]
+}